@@ -0,0 +1,75 @@
+mod store;
+use mlua::{Error as LuaError, Lua, Result as LuaResult, UserData, UserDataMethods};
+use std::{error::Error, fmt, path::PathBuf};
+pub use store::{fingerprint, DraftStore, Entry, STORE_VERSION};
+
+#[derive(Debug)]
+pub enum BufIdError {
+  IoError(std::io::Error),
+  InvalidStore(PathBuf, String),
+}
+
+impl fmt::Display for BufIdError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      BufIdError::IoError(e) => write!(f, "IO error: {}", e),
+      BufIdError::InvalidStore(path, e) => {
+        write!(f, "Invalid draft ID store {}: {}", path.display(), e)
+      }
+    }
+  }
+}
+
+impl Error for BufIdError {}
+impl From<std::io::Error> for BufIdError {
+  fn from(err: std::io::Error) -> Self {
+    BufIdError::IoError(err)
+  }
+}
+
+impl From<BufIdError> for LuaError {
+  fn from(err: BufIdError) -> Self {
+    LuaError::external(err)
+  }
+}
+
+impl UserData for DraftStore {
+  fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+    // store:check(path: String) -> String?
+    methods.add_method("check", |_, this, path: String| Ok(this.check(&path)?));
+
+    // store:assign(path: String, id: String)
+    methods.add_method("assign", |_, this, (path, id): (String, String)| {
+      Ok(this.assign(&path, &id)?)
+    });
+
+    // store:update(path: String, id: String) -> bool
+    methods.add_method("update", |_, this, (path, id): (String, String)| {
+      Ok(this.update(&path, &id)?)
+    });
+
+    // store:remove(path: String) -> String?
+    methods.add_method("remove", |_, this, path: String| Ok(this.remove(&path)?));
+  }
+}
+
+#[mlua::lua_module]
+fn buf_id_lib(lua: &Lua) -> LuaResult<mlua::Table> {
+  let exports = lua.create_table()?;
+
+  // store(path: String, legacy_path?: String) -> DraftStore
+  exports.set(
+    "store",
+    lua.create_function(
+      |_, (path, legacy): (String, Option<String>)| -> LuaResult<DraftStore> {
+        let store = DraftStore::new(path);
+        Ok(match legacy {
+          Some(legacy) => store.with_legacy(legacy),
+          None => store,
+        })
+      },
+    )?,
+  )?;
+
+  Ok(exports)
+}
@@ -0,0 +1,319 @@
+use crate::BufIdError;
+use fs4::fs_std::FileExt;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs::{self, File, OpenOptions},
+  io::{ErrorKind, Write},
+  path::{Path, PathBuf},
+};
+
+/// Version written to the store, bumped when the layout of an entry changes.
+pub const STORE_VERSION: u32 = 1;
+
+/// A Markdown file and the Zhihu draft it is uploaded to.
+///
+/// The file is recognised by its canonical path first, then by its inode once it has been
+/// moved, and at last by the fingerprint of its content when it has been copied to another
+/// machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+  pub id: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub path: Option<PathBuf>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub inode: Option<u64>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub fingerprint: Option<String>,
+}
+
+impl Entry {
+  /// Whether the file recorded by the entry is gone, so that a file found at another path may
+  /// take its place. Entries migrated from the inode map have no path at all.
+  fn is_stale(&self) -> bool {
+    match &self.path {
+      Some(path) => !path.exists(),
+      None => true,
+    }
+  }
+
+  /// Record where the file lives now, returns whether anything changed.
+  fn heal(&mut self, key: &FileKey) -> bool {
+    let path = Some(key.path.clone());
+    let fingerprint = Some(key.fingerprint.clone());
+    let changed = self.path != path || self.inode != key.inode || self.fingerprint != fingerprint;
+    self.path = path;
+    self.inode = key.inode;
+    self.fingerprint = fingerprint;
+    changed
+  }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreFile {
+  version: u32,
+  #[serde(default)]
+  entries: Vec<Entry>,
+}
+
+/// Everything the store knows to identify a file on disk.
+struct FileKey {
+  path: PathBuf,
+  inode: Option<u64>,
+  fingerprint: String,
+}
+
+impl FileKey {
+  fn read(file: &Path) -> Result<Self, BufIdError> {
+    let path = fs::canonicalize(file)?;
+    let metadata = fs::metadata(&path)?;
+    let content = fs::read(&path)?;
+    Ok(FileKey {
+      path,
+      inode: inode(&metadata),
+      fingerprint: fingerprint(&content),
+    })
+  }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> Option<u64> {
+  use std::os::unix::fs::MetadataExt;
+  Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> Option<u64> {
+  None
+}
+
+/// Fingerprint of a file content, the lowercase hex MD5.
+pub fn fingerprint(content: &[u8]) -> String {
+  format!("{:x}", Md5::digest(content))
+}
+
+/// Index of the entry of `key`, by path, then by inode, then by fingerprint. Inodes and
+/// fingerprints only match entries whose file is gone, and must match a single one.
+fn find(entries: &[Entry], key: &FileKey) -> Option<usize> {
+  if let Some(index) = entries
+    .iter()
+    .position(|entry| entry.path.as_ref() == Some(&key.path))
+  {
+    return Some(index);
+  }
+  let unique = |matches: &dyn Fn(&Entry) -> bool| {
+    let mut found = entries
+      .iter()
+      .enumerate()
+      .filter(|(_, entry)| matches(entry) && entry.is_stale())
+      .map(|(index, _)| index);
+    match (found.next(), found.next()) {
+      (Some(index), None) => Some(index),
+      _ => None,
+    }
+  };
+  key
+    .inode
+    .and_then(|inode| unique(&|entry| entry.inode == Some(inode)))
+    .or_else(|| unique(&|entry| entry.fingerprint.as_ref() == Some(&key.fingerprint)))
+}
+
+/// Read the inode map `buf_id.lua` used to keep in `zhvim_buf_ids.json`.
+fn read_legacy(path: &Path) -> Result<Vec<Entry>, BufIdError> {
+  let content = match fs::read_to_string(path) {
+    Ok(content) => content,
+    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => return Err(e.into()),
+  };
+  if content.trim().is_empty() {
+    return Ok(Vec::new());
+  }
+  let value: serde_json::Value = serde_json::from_str(&content)
+    .map_err(|e| BufIdError::InvalidStore(path.to_path_buf(), e.to_string()))?;
+  // `vim.fn.json_encode({})` writes an empty map as `[]`
+  let Some(map) = value.as_object() else {
+    return Ok(Vec::new());
+  };
+  let mut entries: Vec<Entry> = map
+    .iter()
+    .filter_map(|(inode, id)| {
+      let id = match id {
+        serde_json::Value::String(id) => id.clone(),
+        serde_json::Value::Number(id) => id.to_string(),
+        _ => return None,
+      };
+      Some(Entry {
+        id,
+        path: None,
+        inode: Some(inode.parse().ok()?),
+        fingerprint: None,
+      })
+    })
+    .collect();
+  entries.sort_by_key(|entry| entry.inode);
+  Ok(entries)
+}
+
+/// Draft IDs of local Markdown files, kept in a JSON file.
+///
+/// Every operation holds an exclusive lock on a `.lock` file next to the store, so that several
+/// Neovim instances can share it, and replaces the store by renaming a temporary file over it.
+#[derive(Debug, Clone)]
+pub struct DraftStore {
+  path: PathBuf,
+  legacy: Option<PathBuf>,
+}
+
+impl DraftStore {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    DraftStore {
+      path: path.into(),
+      legacy: None,
+    }
+  }
+
+  /// Import the inode map of `zhvim_buf_ids.json` when the store does not exist yet.
+  pub fn with_legacy(mut self, legacy: impl Into<PathBuf>) -> Self {
+    self.legacy = Some(legacy.into());
+    self
+  }
+
+  /// The draft ID of `file`, if any. Moved or copied files are matched and recorded at their
+  /// new path.
+  pub fn check(&self, file: impl AsRef<Path>) -> Result<Option<String>, BufIdError> {
+    let key = match FileKey::read(file.as_ref()) {
+      Ok(key) => key,
+      Err(BufIdError::IoError(e)) if e.kind() == ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(e),
+    };
+    self.transaction(|store| match find(&store.entries, &key) {
+      Some(index) => {
+        let entry = &mut store.entries[index];
+        (Some(entry.id.clone()), entry.heal(&key))
+      }
+      None => (None, false),
+    })
+  }
+
+  /// Set the draft ID of `file`, replacing the one it had.
+  pub fn assign(&self, file: impl AsRef<Path>, id: &str) -> Result<(), BufIdError> {
+    let key = FileKey::read(file.as_ref())?;
+    self.transaction(|store| {
+      match find(&store.entries, &key) {
+        Some(index) => {
+          let entry = &mut store.entries[index];
+          entry.heal(&key);
+          entry.id = id.to_string();
+        }
+        None => {
+          let mut entry = Entry {
+            id: id.to_string(),
+            path: None,
+            inode: None,
+            fingerprint: None,
+          };
+          entry.heal(&key);
+          store.entries.push(entry);
+        }
+      }
+      ((), true)
+    })
+  }
+
+  /// Change the draft ID of `file`, returns `false` if it had none.
+  pub fn update(&self, file: impl AsRef<Path>, id: &str) -> Result<bool, BufIdError> {
+    let key = FileKey::read(file.as_ref())?;
+    self.transaction(|store| match find(&store.entries, &key) {
+      Some(index) => {
+        let entry = &mut store.entries[index];
+        entry.heal(&key);
+        entry.id = id.to_string();
+        (true, true)
+      }
+      None => (false, false),
+    })
+  }
+
+  /// Forget the draft ID of `file`, returns the ID it had.
+  pub fn remove(&self, file: impl AsRef<Path>) -> Result<Option<String>, BufIdError> {
+    let key = FileKey::read(file.as_ref())?;
+    self.transaction(|store| match find(&store.entries, &key) {
+      Some(index) => (Some(store.entries.remove(index).id), true),
+      None => (None, false),
+    })
+  }
+
+  /// All entries of the store.
+  pub fn entries(&self) -> Result<Vec<Entry>, BufIdError> {
+    self.transaction(|store| (store.entries.clone(), false))
+  }
+
+  /// Run `f` on the store under the lock, and save the store if `f` reports a change.
+  fn transaction<T>(&self, f: impl FnOnce(&mut StoreFile) -> (T, bool)) -> Result<T, BufIdError> {
+    if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+      fs::create_dir_all(dir)?;
+    }
+    let lock = OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .open(self.sibling("lock"))?;
+    FileExt::lock_exclusive(&lock)?;
+
+    let (mut store, migrated) = self.load()?;
+    let (result, changed) = f(&mut store);
+    if changed || migrated {
+      self.save(&store)?;
+    }
+    // The lock is released when the file is closed
+    drop(lock);
+    Ok(result)
+  }
+
+  fn load(&self) -> Result<(StoreFile, bool), BufIdError> {
+    match fs::read_to_string(&self.path) {
+      Ok(content) => {
+        let store = serde_json::from_str(&content)
+          .map_err(|e| BufIdError::InvalidStore(self.path.clone(), e.to_string()))?;
+        Ok((store, false))
+      }
+      Err(e) if e.kind() == ErrorKind::NotFound => {
+        let entries = match &self.legacy {
+          Some(legacy) => read_legacy(legacy)?,
+          None => Vec::new(),
+        };
+        let migrated = !entries.is_empty();
+        Ok((
+          StoreFile {
+            version: STORE_VERSION,
+            entries,
+          },
+          migrated,
+        ))
+      }
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  fn save(&self, store: &StoreFile) -> Result<(), BufIdError> {
+    let content = serde_json::to_string_pretty(&StoreFile {
+      version: STORE_VERSION,
+      entries: store.entries.clone(),
+    })
+    .map_err(|e| BufIdError::InvalidStore(self.path.clone(), e.to_string()))?;
+    let temp = self.sibling("tmp");
+    let mut file = File::create(&temp)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, &self.path)?;
+    Ok(())
+  }
+
+  /// `<store>.<extension>`, next to the store.
+  fn sibling(&self, extension: &str) -> PathBuf {
+    let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    self.path.with_file_name(name)
+  }
+}
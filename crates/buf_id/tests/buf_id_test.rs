@@ -0,0 +1,157 @@
+use buf_id::{fingerprint, BufIdError, DraftStore, STORE_VERSION};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+fn note(dir: &Path, name: &str, content: &str) -> std::path::PathBuf {
+  let path = dir.join(name);
+  fs::write(&path, content).unwrap();
+  path
+}
+
+fn store(dir: &TempDir) -> DraftStore {
+  DraftStore::new(dir.path().join("data/zhvim_drafts.json"))
+}
+
+#[test]
+fn test_assign_and_check_by_path() {
+  let dir = TempDir::new().unwrap();
+  let store = store(&dir);
+  let path = note(dir.path(), "my note.md", "# Hello");
+
+  assert_eq!(store.check(&path).unwrap(), None);
+  store.assign(&path, "123").unwrap();
+  assert_eq!(store.check(&path).unwrap(), Some("123".to_string()));
+  // A relative spelling of the same file is the same entry
+  let dotted = dir.path().join("data/../my note.md");
+  assert_eq!(store.check(&dotted).unwrap(), Some("123".to_string()));
+
+  let entries = store.entries().unwrap();
+  assert_eq!(entries.len(), 1);
+  assert_eq!(entries[0].path, Some(fs::canonicalize(&path).unwrap()));
+  assert_eq!(entries[0].fingerprint, Some(fingerprint(b"# Hello")));
+
+  let saved = fs::read_to_string(dir.path().join("data/zhvim_drafts.json")).unwrap();
+  let saved: serde_json::Value = serde_json::from_str(&saved).unwrap();
+  assert_eq!(saved["version"], STORE_VERSION);
+  assert!(!dir.path().join("data/zhvim_drafts.json.tmp").exists());
+}
+
+#[test]
+fn test_check_missing_file() {
+  let dir = TempDir::new().unwrap();
+  let store = store(&dir);
+  assert_eq!(store.check(dir.path().join("nope.md")).unwrap(), None);
+  assert!(matches!(
+    store.assign(dir.path().join("nope.md"), "1"),
+    Err(BufIdError::IoError(_))
+  ));
+}
+
+#[test]
+fn test_replaced_file_keeps_id() {
+  let dir = TempDir::new().unwrap();
+  let store = store(&dir);
+  let path = note(dir.path(), "a.md", "first");
+  store.assign(&path, "1").unwrap();
+
+  // Save by rename, as editors and `git checkout` do, gives the path a new inode
+  let temp = note(dir.path(), "a.md.swp", "second");
+  fs::rename(&temp, &path).unwrap();
+  assert_eq!(store.check(&path).unwrap(), Some("1".to_string()));
+  assert_eq!(
+    store.entries().unwrap()[0].fingerprint,
+    Some(fingerprint(b"second"))
+  );
+}
+
+#[test]
+fn test_moved_file_is_found_by_inode() {
+  let dir = TempDir::new().unwrap();
+  let store = store(&dir);
+  let old = note(dir.path(), "old.md", "content");
+  store.assign(&old, "1").unwrap();
+  fs::write(&old, "edited").unwrap();
+
+  let new = dir.path().join("new.md");
+  fs::rename(&old, &new).unwrap();
+  assert_eq!(store.check(&new).unwrap(), Some("1".to_string()));
+  assert_eq!(
+    store.entries().unwrap()[0].path,
+    Some(fs::canonicalize(&new).unwrap())
+  );
+}
+
+#[test]
+fn test_copied_file_is_found_by_fingerprint() {
+  let dir = TempDir::new().unwrap();
+  let store = store(&dir);
+  let old = note(dir.path(), "old.md", "content");
+  store.assign(&old, "1").unwrap();
+
+  // While the original exists, a copy is a new note
+  let copy = note(dir.path(), "copy.md", "content");
+  assert_eq!(store.check(&copy).unwrap(), None);
+
+  // Once it is gone, as on another machine, the copy takes its place
+  fs::remove_file(&old).unwrap();
+  assert_eq!(store.check(&copy).unwrap(), Some("1".to_string()));
+}
+
+#[test]
+fn test_update_and_remove() {
+  let dir = TempDir::new().unwrap();
+  let store = store(&dir);
+  let path = note(dir.path(), "a.md", "a");
+
+  assert!(!store.update(&path, "2").unwrap());
+  store.assign(&path, "1").unwrap();
+  assert!(store.update(&path, "2").unwrap());
+  assert_eq!(store.check(&path).unwrap(), Some("2".to_string()));
+
+  assert_eq!(store.remove(&path).unwrap(), Some("2".to_string()));
+  assert_eq!(store.remove(&path).unwrap(), None);
+  assert_eq!(store.check(&path).unwrap(), None);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_migrate_inode_map() {
+  use std::os::unix::fs::MetadataExt;
+  let dir = TempDir::new().unwrap();
+  let path = note(dir.path(), "a.md", "a");
+  let inode = fs::metadata(&path).unwrap().ino();
+  let legacy = dir.path().join("zhvim_buf_ids.json");
+  fs::write(&legacy, format!(r#"{{"{}": "42", "1": "7"}}"#, inode)).unwrap();
+
+  let store = store(&dir).with_legacy(&legacy);
+  assert_eq!(store.entries().unwrap().len(), 2);
+  assert_eq!(store.check(&path).unwrap(), Some("42".to_string()));
+  assert_eq!(
+    store.entries().unwrap()[1].path,
+    Some(fs::canonicalize(&path).unwrap())
+  );
+
+  // Only a missing store is migrated
+  fs::write(&legacy, r#"{"1": "8"}"#).unwrap();
+  assert_eq!(store.entries().unwrap()[0].id, "7");
+}
+
+#[test]
+fn test_migrate_empty_inode_map() {
+  let dir = TempDir::new().unwrap();
+  let legacy = dir.path().join("zhvim_buf_ids.json");
+  fs::write(&legacy, "[]").unwrap();
+  let store = store(&dir).with_legacy(&legacy);
+  assert!(store.entries().unwrap().is_empty());
+}
+
+#[test]
+fn test_invalid_store() {
+  let dir = TempDir::new().unwrap();
+  let store = store(&dir);
+  fs::create_dir_all(dir.path().join("data")).unwrap();
+  fs::write(dir.path().join("data/zhvim_drafts.json"), "{").unwrap();
+  let path = note(dir.path(), "a.md", "a");
+  let err = store.check(&path).unwrap_err();
+  assert!(err.to_string().starts_with("Invalid draft ID store"));
+}
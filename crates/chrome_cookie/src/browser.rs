@@ -0,0 +1,272 @@
+use crate::{ChromeCookieError, FixedPassword, KWallet, KeyProvider, Platform, SecretService};
+use std::path::{Path, PathBuf};
+
+/// Where a Chromium-based browser keeps its data and the password of its cookies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Browser {
+  /// Name used in the configuration and commands, e.g. `brave`.
+  pub id: &'static str,
+  pub name: &'static str,
+  /// User data directory under `~/.config` on Linux.
+  pub linux_dir: &'static str,
+  /// Flatpak application ID, whose user data directory is under `~/.var/app/<id>/config`.
+  pub flatpak_id: Option<&'static str>,
+  /// User data directory of the Snap package, relative to the home directory.
+  pub snap_dir: Option<&'static str>,
+  /// User data directory under `~/Library/Application Support` on macOS.
+  pub macos_dir: &'static str,
+  /// Keychain service and KDE Wallet entry of the password, `<Name> Safe Storage`.
+  pub safe_storage: &'static str,
+  /// `application` attribute of the password in the Secret Service.
+  pub secret_application: &'static str,
+  /// KDE Wallet folder of the password.
+  pub kwallet_folder: &'static str,
+  /// Whether the user data directory is itself the profile, as for Opera.
+  pub single_profile: bool,
+}
+
+/// Chromium-based browsers cookies can be read from.
+pub const BROWSERS: &[Browser] = &[
+  Browser {
+    id: "chrome",
+    name: "Google Chrome",
+    linux_dir: "google-chrome",
+    flatpak_id: Some("com.google.Chrome"),
+    snap_dir: None,
+    macos_dir: "Google/Chrome",
+    safe_storage: "Chrome Safe Storage",
+    secret_application: "chrome",
+    kwallet_folder: "Chrome Keys",
+    single_profile: false,
+  },
+  Browser {
+    id: "chromium",
+    name: "Chromium",
+    linux_dir: "chromium",
+    flatpak_id: Some("org.chromium.Chromium"),
+    snap_dir: Some("snap/chromium/common/chromium"),
+    macos_dir: "Chromium",
+    safe_storage: "Chromium Safe Storage",
+    secret_application: "chromium",
+    kwallet_folder: "Chromium Keys",
+    single_profile: false,
+  },
+  Browser {
+    id: "brave",
+    name: "Brave",
+    linux_dir: "BraveSoftware/Brave-Browser",
+    flatpak_id: Some("com.brave.Browser"),
+    snap_dir: Some("snap/brave/current/.config/BraveSoftware/Brave-Browser"),
+    macos_dir: "BraveSoftware/Brave-Browser",
+    safe_storage: "Brave Safe Storage",
+    secret_application: "brave",
+    kwallet_folder: "Brave Keys",
+    single_profile: false,
+  },
+  Browser {
+    id: "edge",
+    name: "Microsoft Edge",
+    linux_dir: "microsoft-edge",
+    flatpak_id: Some("com.microsoft.Edge"),
+    snap_dir: None,
+    macos_dir: "Microsoft Edge",
+    safe_storage: "Microsoft Edge Safe Storage",
+    secret_application: "microsoft-edge",
+    kwallet_folder: "Microsoft Edge Keys",
+    single_profile: false,
+  },
+  Browser {
+    id: "vivaldi",
+    name: "Vivaldi",
+    linux_dir: "vivaldi",
+    flatpak_id: Some("com.vivaldi.Vivaldi"),
+    snap_dir: Some("snap/vivaldi/current/.config/vivaldi"),
+    macos_dir: "Vivaldi",
+    safe_storage: "Vivaldi Safe Storage",
+    secret_application: "vivaldi",
+    kwallet_folder: "Vivaldi Keys",
+    single_profile: false,
+  },
+  Browser {
+    id: "opera",
+    name: "Opera",
+    linux_dir: "opera",
+    flatpak_id: Some("com.opera.Opera"),
+    snap_dir: Some("snap/opera/current/.config/opera"),
+    macos_dir: "com.operasoftware.Opera",
+    safe_storage: "Opera Safe Storage",
+    secret_application: "opera",
+    kwallet_folder: "Opera Keys",
+    single_profile: true,
+  },
+];
+
+/// The browser of the registry with this ID.
+pub fn browser(id: &str) -> Result<&'static Browser, ChromeCookieError> {
+  BROWSERS
+    .iter()
+    .find(|browser| browser.id == id)
+    .ok_or_else(|| ChromeCookieError::UnknownBrowser(id.to_string()))
+}
+
+impl Browser {
+  /// Candidate user data directories on `platform`, native installs first.
+  pub fn user_data_dirs(&self, home: &Path, platform: Platform) -> Vec<PathBuf> {
+    match platform {
+      Platform::MacOs => vec![home
+        .join("Library/Application Support")
+        .join(self.macos_dir)],
+      Platform::Linux => {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+          .map(PathBuf::from)
+          .filter(|dir| dir.is_absolute())
+          .unwrap_or_else(|| home.join(".config"));
+        let mut dirs = vec![config.join(self.linux_dir)];
+        if let Some(id) = self.flatpak_id {
+          dirs.push(
+            home
+              .join(".var/app")
+              .join(id)
+              .join("config")
+              .join(self.linux_dir),
+          );
+        }
+        if let Some(snap) = self.snap_dir {
+          dirs.push(home.join(snap));
+        }
+        dirs
+      }
+      Platform::Windows => Vec::new(),
+    }
+  }
+
+  /// The first user data directory that exists for the current user.
+  pub fn user_data_dir(&self) -> Result<PathBuf, ChromeCookieError> {
+    let home = dirs::home_dir().ok_or(ChromeCookieError::UnsupportedPlatform)?;
+    self
+      .user_data_dirs(&home, Platform::current())
+      .into_iter()
+      .find(|dir| dir.is_dir())
+      .ok_or(ChromeCookieError::NotInstalled(self.name))
+  }
+
+  /// Profiles of the browser, the last used one first, see [`profiles`].
+  pub fn profiles(&self) -> Result<Vec<Profile>, ChromeCookieError> {
+    let dir = self.user_data_dir()?;
+    if !self.single_profile {
+      return profiles(&dir);
+    }
+    Ok(vec![Profile {
+      name: self.name.to_string(),
+      dir: String::new(),
+      cookie_path: profile_cookie_path(&dir),
+      last_used: true,
+      path: dir,
+    }])
+  }
+
+  /// Cookie database of the last used profile holding one.
+  pub fn cookie_path(&self) -> Result<PathBuf, ChromeCookieError> {
+    self
+      .profiles()?
+      .into_iter()
+      .find_map(|profile| profile.cookie_path)
+      .ok_or(ChromeCookieError::NoCookieDatabase(self.name))
+  }
+
+  /// Providers of the password on Linux in the order they are tried: the `explicit` one from
+  /// the configuration, the Secret Service and KDE Wallet.
+  pub fn key_providers(&self, explicit: Option<&str>) -> Vec<Box<dyn KeyProvider>> {
+    let mut providers: Vec<Box<dyn KeyProvider>> = Vec::new();
+    if let Some(password) = explicit {
+      providers.push(Box::new(FixedPassword(password.to_string())));
+    }
+    providers.push(Box::new(SecretService {
+      application: self.secret_application.to_string(),
+    }));
+    providers.push(Box::new(KWallet {
+      wallet: "kdewallet".to_string(),
+      folder: self.kwallet_folder.to_string(),
+      entry: self.safe_storage.to_string(),
+    }));
+    providers
+  }
+}
+
+/// A browser profile, e.g. the "Work" one in `Profile 2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+  /// Name shown in the profile menu.
+  pub name: String,
+  /// Directory name in the user data directory, e.g. `Default` or `Profile 2`.
+  pub dir: String,
+  pub path: PathBuf,
+  /// Cookie database, `Network/Cookies` in recent versions and `Cookies` before. `None` until
+  /// the profile has stored a cookie.
+  pub cookie_path: Option<PathBuf>,
+  /// Whether the browser opened this profile last.
+  pub last_used: bool,
+}
+
+/// Cookie database of a profile directory.
+pub fn profile_cookie_path(profile: &Path) -> Option<PathBuf> {
+  ["Network/Cookies", "Cookies"]
+    .into_iter()
+    .map(|file| profile.join(file))
+    .find(|path| path.is_file())
+}
+
+/// Profiles of the user data directory `dir`, the last used one first, from the
+/// `profile.info_cache` of its `Local State`.
+///
+/// Without a profile list, e.g. before the browser first quit, the `Default` profile is returned
+/// if it exists.
+pub fn profiles(dir: &Path) -> Result<Vec<Profile>, ChromeCookieError> {
+  let local_state = match std::fs::read_to_string(dir.join("Local State")) {
+    Ok(text) => serde_json::from_str::<serde_json::Value>(&text)?,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => serde_json::Value::Null,
+    Err(e) => return Err(e.into()),
+  };
+  let last_used = local_state["profile"]["last_used"]
+    .as_str()
+    .unwrap_or("Default");
+  let mut profiles: Vec<Profile> = match local_state["profile"]["info_cache"].as_object() {
+    Some(cache) if !cache.is_empty() => cache
+      .iter()
+      .map(|(key, info)| {
+        (
+          key.clone(),
+          info["name"].as_str().unwrap_or(key).to_string(),
+        )
+      })
+      .collect(),
+    _ if dir.join("Default").is_dir() => vec![("Default".to_string(), "Default".to_string())],
+    _ => Vec::new(),
+  }
+  .into_iter()
+  .map(|(key, name)| {
+    let path = dir.join(&key);
+    Profile {
+      name,
+      cookie_path: profile_cookie_path(&path),
+      last_used: key == last_used,
+      dir: key,
+      path,
+    }
+  })
+  .collect();
+  // Last used first, then `Default`, `Profile 1`, `Profile 2`, ... in number order
+  profiles.sort_by_key(|profile| {
+    let number = profile
+      .dir
+      .strip_prefix("Profile ")
+      .and_then(|n| n.parse::<u32>().ok());
+    (
+      !profile.last_used,
+      profile.dir != "Default",
+      number.unwrap_or(u32::MAX),
+      profile.dir.clone(),
+    )
+  });
+  Ok(profiles)
+}
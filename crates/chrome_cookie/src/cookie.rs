@@ -0,0 +1,207 @@
+use crate::ChromeCookieError;
+use rusqlite::Connection;
+use std::cmp::Reverse;
+use std::net::IpAddr;
+
+/// `SameSite` attribute of a cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+  None,
+  Lax,
+  Strict,
+  /// Set without the attribute, which browsers treat as `Lax` or `None` depending on version.
+  Unspecified,
+}
+
+impl SameSite {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      SameSite::None => "none",
+      SameSite::Lax => "lax",
+      SameSite::Strict => "strict",
+      SameSite::Unspecified => "unspecified",
+    }
+  }
+}
+
+/// A cookie read from a browser, with the attributes that decide where it is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+  /// Domain of the cookie, with a leading `.` when it is also sent to subdomains.
+  pub host: String,
+  pub name: String,
+  pub value: String,
+  pub path: String,
+  /// Expiry in seconds since the Unix epoch, `None` for session cookies.
+  pub expires: Option<i64>,
+  pub secure: bool,
+  pub http_only: bool,
+  pub same_site: SameSite,
+  /// Top-level site of a partitioned (CHIPS) cookie, e.g. `https://example.com`.
+  pub partition_key: Option<String>,
+}
+
+impl Cookie {
+  pub fn is_expired(&self, now: i64) -> bool {
+    self.expires.is_some_and(|expires| expires <= now)
+  }
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_now() -> i64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map_or(0, |time| time.as_secs() as i64)
+}
+
+fn is_ip(host: &str) -> bool {
+  host.parse::<IpAddr>().is_ok()
+    || host
+      .strip_prefix('[')
+      .and_then(|host| host.strip_suffix(']'))
+      .is_some_and(|host| host.parse::<IpAddr>().is_ok())
+}
+
+/// Request host without the leading `.` callers used to pass, e.g. `.zhihu.com`.
+fn request_host(host: &str) -> String {
+  host.trim_start_matches('.').to_ascii_lowercase()
+}
+
+/// Whether a cookie of `cookie_host` is sent to `host`: a host-only cookie to its host alone and
+/// a domain cookie, whose host starts with `.`, to the domain and its subdomains.
+/// ref: [RFC 6265 5.1.3](https://www.rfc-editor.org/rfc/rfc6265#section-5.1.3)
+pub fn domain_match(cookie_host: &str, host: &str) -> bool {
+  let host = request_host(host);
+  let cookie_host = cookie_host.to_ascii_lowercase();
+  match cookie_host.strip_prefix('.') {
+    None => cookie_host == host,
+    Some(domain) => {
+      host == domain
+        || (!is_ip(&host)
+          && host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.')))
+    }
+  }
+}
+
+/// Whether a cookie of `cookie_path` is sent to requests for `path`.
+/// ref: [RFC 6265 5.1.4](https://www.rfc-editor.org/rfc/rfc6265#section-5.1.4)
+pub fn path_match(cookie_path: &str, path: &str) -> bool {
+  match path.strip_prefix(cookie_path) {
+    Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+    None => false,
+  }
+}
+
+/// The hosts cookies sent to `host` may be stored under: its own and those of its parent
+/// domains. Narrows a query before values are decrypted.
+pub(crate) fn candidate_hosts(host: &str) -> Vec<String> {
+  let host = request_host(host);
+  if is_ip(&host) {
+    return vec![host];
+  }
+  let mut hosts = vec![host.clone(), format!(".{}", host)];
+  let mut rest = host.as_str();
+  while let Some((_, parent)) = rest.split_once('.') {
+    hosts.push(format!(".{}", parent));
+    rest = parent;
+  }
+  hosts
+}
+
+/// Which cookies a query returns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieFilter {
+  /// Host of the request cookies must domain-match, any host if `None`.
+  pub host: Option<String>,
+  /// Path of the request cookies must path-match, any path if `None`.
+  pub path: Option<String>,
+  /// Only cookies of this name.
+  pub name: Option<String>,
+  /// Whether expired cookies the browser has not purged yet are returned.
+  pub include_expired: bool,
+}
+
+impl CookieFilter {
+  /// Unexpired cookies sent to `host`.
+  pub fn host(host: &str) -> Self {
+    Self {
+      host: Some(host.to_string()),
+      ..Self::default()
+    }
+  }
+
+  pub fn matches(&self, cookie: &Cookie, now: i64) -> bool {
+    let host = self
+      .host
+      .iter()
+      .all(|host| domain_match(&cookie.host, host));
+    let path = self.path.iter().all(|path| path_match(&cookie.path, path));
+    let name = self.name.iter().all(|name| cookie.name == *name);
+    host && path && name && (self.include_expired || !cookie.is_expired(now))
+  }
+
+  /// SQL condition on the host and name columns, with its parameters.
+  pub(crate) fn sql_condition(&self, host_column: &str) -> (String, Vec<String>) {
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    if let Some(host) = &self.host {
+      let hosts = candidate_hosts(host);
+      conditions.push(format!(
+        "{} IN ({})",
+        host_column,
+        vec!["?"; hosts.len()].join(", ")
+      ));
+      params.extend(hosts);
+    }
+    if let Some(name) = &self.name {
+      conditions.push("name = ?".to_string());
+      params.push(name.clone());
+    }
+    if conditions.is_empty() {
+      conditions.push("1".to_string());
+    }
+    (conditions.join(" AND "), params)
+  }
+}
+
+/// Order cookies as they are sent in a `Cookie` header, longer paths first, then more specific
+/// hosts, so the first cookie of each name is the one a request gets.
+/// ref: [RFC 6265 5.4](https://www.rfc-editor.org/rfc/rfc6265#section-5.4)
+pub fn sort_cookies(cookies: &mut [Cookie]) {
+  cookies.sort_by_key(send_order);
+}
+
+/// Key of [`sort_cookies`].
+pub(crate) fn send_order(cookie: &Cookie) -> (Reverse<usize>, Reverse<usize>, bool) {
+  (
+    Reverse(cookie.path.len()),
+    Reverse(cookie.host.trim_start_matches('.').len()),
+    cookie.host.starts_with('.'),
+  )
+}
+
+/// Columns of `table` for a `SELECT`, each optional one replaced by its default when the
+/// database predates it.
+pub(crate) fn select_columns(
+  conn: &Connection,
+  table: &str,
+  columns: &[(&str, &str)],
+) -> Result<String, ChromeCookieError> {
+  let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+  let existing: Vec<String> = stmt
+    .query_map([], |row| row.get(1))?
+    .collect::<Result<_, _>>()?;
+  let columns: Vec<&str> = columns
+    .iter()
+    .map(|&(column, default)| {
+      if existing.iter().any(|c| c == column) {
+        column
+      } else {
+        default
+      }
+    })
+    .collect();
+  Ok(columns.join(", "))
+}
@@ -0,0 +1,278 @@
+use crate::{resolve_password, ChromeCookieError, KeyProvider, V10_PASSWORD};
+use aes::Aes128;
+use aes_gcm::{
+  aead::{Aead, KeyInit},
+  Aes128Gcm, Aes256Gcm,
+};
+use block_modes::block_padding::Pkcs7;
+use block_modes::{BlockMode, Cbc};
+use hmac::Hmac;
+use pbkdf2::pbkdf2;
+use sha1::Sha1;
+
+const SALT: &[u8] = b"saltysalt";
+type Aes128Cbc = Cbc<Aes128, Pkcs7>;
+
+/// Platform a cookie database was written on, which decides what its `v10` prefix means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+  MacOs,
+  Linux,
+  Windows,
+}
+
+impl Platform {
+  /// The platform this library runs on.
+  pub fn current() -> Self {
+    if cfg!(target_os = "macos") {
+      Platform::MacOs
+    } else if cfg!(target_os = "windows") {
+      Platform::Windows
+    } else {
+      Platform::Linux
+    }
+  }
+
+  pub fn parse(platform: &str) -> Option<Self> {
+    match platform {
+      "macos" => Some(Platform::MacOs),
+      "linux" => Some(Platform::Linux),
+      "windows" => Some(Platform::Windows),
+      _ => None,
+    }
+  }
+}
+
+/// Decrypts cookie values of one scheme.
+pub trait CookieDecryptor {
+  /// Plaintext of `encrypted_value`, its 3-byte version prefix included.
+  fn decrypt(&self, encrypted_value: &[u8]) -> Result<Vec<u8>, ChromeCookieError>;
+}
+
+/// Decrypt the AES-128-CBC data of a `v10` or `v11` value, IV of all spaces.
+fn decrypt_cbc(key: &[u8; 16], encrypted_data: &[u8]) -> Result<Vec<u8>, ChromeCookieError> {
+  const IV: [u8; 16] = [b' '; 16];
+  let cipher = Aes128Cbc::new_from_slices(key, &IV)
+    .map_err(|_| ChromeCookieError::DecryptionFailed("AES-CBC init failed".into()))?;
+  cipher
+    .decrypt_vec(encrypted_data)
+    .map_err(|_| ChromeCookieError::DecryptionFailed("AES-CBC decrypt failed".into()))
+}
+
+fn encrypted_data(encrypted_value: &[u8]) -> Result<&[u8], ChromeCookieError> {
+  encrypted_value
+    .get(3..)
+    .filter(|data| !data.is_empty())
+    .ok_or_else(|| ChromeCookieError::DecryptionFailed("value is too short".into()))
+}
+
+/// `v10` values of macOS, AES-128-CBC with a key derived from the Keychain password.
+/// ref: [chromium](https://source.chromium.org/chromium/chromium/src/+/main:components/os_crypt/sync/os_crypt_mac.mm)
+pub struct MacCbc {
+  key: [u8; 16],
+}
+
+impl MacCbc {
+  pub fn new(password: &str) -> Self {
+    let mut key = [0u8; 16];
+    pbkdf2::<Hmac<Sha1>>(password.as_bytes(), SALT, 1003, &mut key)
+      .expect("HMAC accepts keys of any length");
+    Self { key }
+  }
+}
+
+impl CookieDecryptor for MacCbc {
+  fn decrypt(&self, encrypted_value: &[u8]) -> Result<Vec<u8>, ChromeCookieError> {
+    decrypt_cbc(&self.key, encrypted_data(encrypted_value)?)
+  }
+}
+
+pub fn derive_linux_key(password: &str) -> [u8; 16] {
+  let iterations = 1;
+  let mut key = [0u8; 16];
+  pbkdf2::<Hmac<Sha1>>(password.as_bytes(), SALT, iterations, &mut key)
+    .expect("HMAC accepts keys of any length");
+  key
+}
+
+/// Keys of Chrome cookies on Linux: `v10` cookies are encrypted with the fixed
+/// [`V10_PASSWORD`], `v11` ones with the password Chrome keeps in the keyring.
+/// ref: [chromium](https://source.chromium.org/chromium/chromium/src/+/main:components/os_crypt/sync/os_crypt_linux.cc)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinuxKeys {
+  v10: [u8; 16],
+  v11: Option<[u8; 16]>,
+}
+
+impl LinuxKeys {
+  /// Keys for the keyring password `v11_password`, without one only `v10` cookies decrypt.
+  pub fn new(v11_password: Option<&str>) -> Self {
+    Self {
+      v10: derive_linux_key(V10_PASSWORD),
+      v11: v11_password.map(derive_linux_key),
+    }
+  }
+
+  /// Keys for the first password any of `providers` holds.
+  pub fn resolve(providers: &[Box<dyn KeyProvider>]) -> Result<Self, ChromeCookieError> {
+    let password = resolve_password(providers)?;
+    Ok(Self::new(password.as_deref()))
+  }
+
+  /// Decrypt a `v10` or `v11` cookie value, `None` for any other value.
+  pub fn decrypt(&self, encrypted_value: &[u8]) -> Result<Option<String>, ChromeCookieError> {
+    if encrypted_value.len() <= 3 || !matches!(&encrypted_value[..3], b"v10" | b"v11") {
+      return Ok(None);
+    }
+    Ok(crate::cookie_text(&self.plaintext(encrypted_value)?))
+  }
+
+  fn plaintext(&self, encrypted_value: &[u8]) -> Result<Vec<u8>, ChromeCookieError> {
+    // Chrome Linux uses "saltysalt" as salt, 1 iteration, 16-byte key, IV of all spaces
+    let key = match encrypted_value.get(..3) {
+      Some(b"v11") => self.v11.ok_or_else(|| {
+        ChromeCookieError::DecryptionFailed(
+          "v11 cookies need the keyring password, none was found in the Secret Service or KDE Wallet"
+            .into(),
+        )
+      })?,
+      _ => self.v10,
+    };
+    decrypt_cbc(&key, encrypted_data(encrypted_value)?)
+  }
+}
+
+/// `v10` and `v11` values of Linux, AES-128-CBC with the keys of [`LinuxKeys`].
+pub struct LinuxCbc(pub LinuxKeys);
+
+impl CookieDecryptor for LinuxCbc {
+  fn decrypt(&self, encrypted_value: &[u8]) -> Result<Vec<u8>, ChromeCookieError> {
+    self.0.plaintext(encrypted_value)
+  }
+}
+
+/// Values encrypted with AES-GCM under a master key: `v10` on Windows, whose master key is the
+/// DPAPI-protected `os_crypt.encrypted_key` of `Local State`, and app-bound `v20` values.
+/// ref: [chromium](https://source.chromium.org/chromium/chromium/src/+/main:components/os_crypt/sync/os_crypt_win.cc)
+pub struct AesGcm {
+  key: Vec<u8>,
+}
+
+impl AesGcm {
+  /// `key` is the 16 or 32-byte master key.
+  pub fn new(key: &[u8]) -> Result<Self, ChromeCookieError> {
+    if !matches!(key.len(), 16 | 32) {
+      return Err(ChromeCookieError::DecryptionFailed(format!(
+        "AES-GCM master key has {} bytes, expected 16 or 32",
+        key.len()
+      )));
+    }
+    Ok(Self { key: key.to_vec() })
+  }
+}
+
+impl CookieDecryptor for AesGcm {
+  fn decrypt(&self, encrypted_value: &[u8]) -> Result<Vec<u8>, ChromeCookieError> {
+    // 12-byte nonce, then the ciphertext and its 16-byte tag
+    let data = encrypted_data(encrypted_value)?;
+    if data.len() < 12 + 16 {
+      return Err(ChromeCookieError::DecryptionFailed(
+        "AES-GCM value is too short".into(),
+      ));
+    }
+    let (nonce, ciphertext) = data.split_at(12);
+    let plain = if self.key.len() == 16 {
+      Aes128Gcm::new_from_slice(&self.key)
+        .expect("key length is checked")
+        .decrypt(nonce.into(), ciphertext)?
+    } else {
+      Aes256Gcm::new_from_slice(&self.key)
+        .expect("key length is checked")
+        .decrypt(nonce.into(), ciphertext)?
+    };
+    Ok(plain)
+  }
+}
+
+/// The decryptors of a cookie database, picking one for each value by its version prefix and
+/// the platform the database comes from.
+pub struct Decryptors {
+  platform: Platform,
+  /// `None` on Windows, which never used CBC
+  cbc: Option<Box<dyn CookieDecryptor>>,
+  gcm: Option<AesGcm>,
+}
+
+impl Decryptors {
+  /// `password` is the Keychain or keyring password, which Linux browsers without a keyring do
+  /// not have, `master_key` the AES-GCM key of Windows databases and `v20` values.
+  pub fn new(
+    platform: Platform,
+    password: Option<&str>,
+    master_key: Option<&[u8]>,
+  ) -> Result<Self, ChromeCookieError> {
+    let cbc: Option<Box<dyn CookieDecryptor>> = match platform {
+      Platform::MacOs => {
+        let password = password.ok_or_else(|| {
+          ChromeCookieError::DecryptionFailed("macOS cookies need the Keychain password".into())
+        })?;
+        Some(Box::new(MacCbc::new(password)))
+      }
+      Platform::Linux => Some(Box::new(LinuxCbc(LinuxKeys::new(password)))),
+      Platform::Windows => None,
+    };
+    Ok(Self {
+      platform,
+      cbc,
+      gcm: master_key.map(AesGcm::new).transpose()?,
+    })
+  }
+
+  /// Decryptors of Linux databases with `keys`, e.g. resolved from key providers.
+  pub fn linux(keys: LinuxKeys) -> Self {
+    Self {
+      platform: Platform::Linux,
+      cbc: Some(Box::new(LinuxCbc(keys))),
+      gcm: None,
+    }
+  }
+
+  pub fn platform(&self) -> Platform {
+    self.platform
+  }
+
+  /// The decryptor of `encrypted_value`, `None` when it is not encrypted.
+  pub fn select(
+    &self,
+    encrypted_value: &[u8],
+  ) -> Result<Option<&dyn CookieDecryptor>, ChromeCookieError> {
+    let gcm = || {
+      self
+        .gcm
+        .as_ref()
+        .map(|gcm| gcm as &dyn CookieDecryptor)
+        .ok_or_else(|| {
+          ChromeCookieError::DecryptionFailed("AES-GCM values need the master key".into())
+        })
+    };
+    if encrypted_value.len() <= 3 {
+      return Ok(None);
+    }
+    match (&encrypted_value[..3], self.platform) {
+      (b"v10", Platform::Windows) | (b"v20", _) => gcm().map(Some),
+      (b"v10", _) | (b"v11", Platform::Linux) => Ok(self.cbc.as_deref()),
+      (b"v11", _) => Err(ChromeCookieError::DecryptionFailed(
+        "v11 values only come from Linux, set the platform of the database".into(),
+      )),
+      _ => Ok(None),
+    }
+  }
+
+  /// Plaintext of `encrypted_value`, `None` when it is not encrypted.
+  pub fn decrypt(&self, encrypted_value: &[u8]) -> Result<Option<Vec<u8>>, ChromeCookieError> {
+    match self.select(encrypted_value)? {
+      Some(decryptor) => decryptor.decrypt(encrypted_value).map(Some),
+      None => Ok(None),
+    }
+  }
+}
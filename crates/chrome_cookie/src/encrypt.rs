@@ -22,6 +22,6 @@ pub fn encrypt_aes128_cbc_pkcs7(key: &[u8; 16], iv: &[u8; 16], plaintext: &[u8])
 /// Generates a random 16-byte IV.
 pub fn generate_random_iv() -> [u8; 16] {
   let mut iv = [0u8; 16];
-  rand::thread_rng().fill_bytes(&mut iv);
+  rand::rng().fill_bytes(&mut iv);
   iv
 }
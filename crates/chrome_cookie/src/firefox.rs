@@ -0,0 +1,400 @@
+use crate::cookie::{select_columns, send_order};
+use crate::{
+  containers, decompress_mozlz4, open_cookie_db, unix_now, ChromeCookieError, Cookie, CookieFilter,
+  OriginAttributes, Platform, SameSite,
+};
+use std::path::{Path, PathBuf};
+
+/// A Firefox profile listed in `profiles.ini`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirefoxProfile {
+  /// Name shown in `about:profiles`.
+  pub name: String,
+  pub path: PathBuf,
+  /// Whether an install of Firefox (release, ESR, Developer Edition, ...) opens it by default.
+  pub default: bool,
+  /// `cookies.sqlite` of the profile, `None` until it has stored a cookie.
+  pub cookie_path: Option<PathBuf>,
+}
+
+/// Sections of an INI file in order, each with its `key=value` pairs.
+fn parse_ini(text: &str) -> Vec<(String, Vec<(String, String)>)> {
+  let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+  for line in text.lines().map(str::trim) {
+    if line.is_empty() || line.starts_with([';', '#']) {
+      continue;
+    }
+    if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+      sections.push((section.trim().to_string(), Vec::new()));
+    } else if let (Some((key, value)), Some((_, entries))) =
+      (line.split_once('='), sections.last_mut())
+    {
+      entries.push((key.trim().to_string(), value.trim().to_string()));
+    }
+  }
+  sections
+}
+
+fn ini_value<'a>(entries: &'a [(String, String)], key: &str) -> Option<&'a str> {
+  entries
+    .iter()
+    .find(|(k, _)| k.eq_ignore_ascii_case(key))
+    .map(|(_, v)| v.as_str())
+}
+
+/// Candidate Firefox directories holding `profiles.ini` on `platform`, native installs first.
+pub fn firefox_dirs(home: &Path, platform: Platform) -> Vec<PathBuf> {
+  match platform {
+    Platform::MacOs => vec![home.join("Library/Application Support/Firefox")],
+    Platform::Linux => vec![
+      home.join(".mozilla/firefox"),
+      home.join(".var/app/org.mozilla.firefox/.mozilla/firefox"),
+      home.join("snap/firefox/common/.mozilla/firefox"),
+    ],
+    Platform::Windows => vec![home.join("AppData/Roaming/Mozilla/Firefox")],
+  }
+}
+
+/// The first Firefox directory with a `profiles.ini` for the current user.
+pub fn firefox_dir() -> Result<PathBuf, ChromeCookieError> {
+  let home = dirs::home_dir().ok_or(ChromeCookieError::UnsupportedPlatform)?;
+  firefox_dirs(&home, Platform::current())
+    .into_iter()
+    .find(|dir| dir.join("profiles.ini").is_file())
+    .ok_or(ChromeCookieError::NotInstalled("Firefox"))
+}
+
+/// Profiles of the Firefox directory `dir`, default ones first.
+///
+/// Since Firefox 67 each install has its own default profile, listed in `installs.ini` and the
+/// `Install*` sections of `profiles.ini`. Older versions mark it with `Default=1`.
+pub fn firefox_profiles(dir: &Path) -> Result<Vec<FirefoxProfile>, ChromeCookieError> {
+  let profiles_ini = parse_ini(&std::fs::read_to_string(dir.join("profiles.ini"))?);
+  let installs_ini = match std::fs::read_to_string(dir.join("installs.ini")) {
+    Ok(text) => parse_ini(&text),
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+    Err(e) => return Err(e.into()),
+  };
+  let resolve = |path: &str, relative: bool| {
+    if relative {
+      dir.join(path)
+    } else {
+      PathBuf::from(path)
+    }
+  };
+  // Install defaults are relative paths, except for profiles outside of `dir`
+  let install_defaults: Vec<PathBuf> = profiles_ini
+    .iter()
+    .filter(|(section, _)| section.starts_with("Install"))
+    .chain(installs_ini.iter())
+    .filter_map(|(_, entries)| ini_value(entries, "Default"))
+    .map(|path| {
+      let absolute = Path::new(path).is_absolute();
+      resolve(path, !absolute)
+    })
+    .collect();
+
+  let mut profiles: Vec<(usize, FirefoxProfile)> = profiles_ini
+    .iter()
+    .filter(|(section, _)| section.starts_with("Profile"))
+    .filter_map(|(_, entries)| {
+      let path = ini_value(entries, "Path")?;
+      let relative = ini_value(entries, "IsRelative") != Some("0");
+      let path = resolve(path, relative);
+      let install_default = install_defaults.iter().position(|p| *p == path);
+      let legacy_default = ini_value(entries, "Default") == Some("1");
+      let rank = match (install_default, legacy_default) {
+        (Some(position), _) => position,
+        (None, true) => install_defaults.len(),
+        (None, false) => usize::MAX,
+      };
+      let cookie_path = Some(path.join("cookies.sqlite")).filter(|path| path.is_file());
+      Some((
+        rank,
+        FirefoxProfile {
+          name: ini_value(entries, "Name").unwrap_or_default().to_string(),
+          default: rank != usize::MAX,
+          cookie_path,
+          path,
+        },
+      ))
+    })
+    .collect();
+  // Stable, so profiles that are not defaults keep the order of `profiles.ini`
+  profiles.sort_by_key(|(rank, _)| *rank);
+  Ok(profiles.into_iter().map(|(_, profile)| profile).collect())
+}
+
+/// `cookies.sqlite` of the first default Firefox profile holding one.
+pub fn firefox_cookie_path() -> Result<PathBuf, ChromeCookieError> {
+  firefox_profiles(&firefox_dir()?)?
+    .into_iter()
+    .find_map(|profile| profile.cookie_path)
+    .ok_or(ChromeCookieError::NoCookieDatabase("Firefox"))
+}
+
+/// A cookie of the session store, which Firefox writes before `cookies.sqlite`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionCookie {
+  pub cookie: Cookie,
+  pub origin: OriginAttributes,
+}
+
+/// Session store of the profile holding `cookie_path`, updated every few seconds while Firefox
+/// runs.
+fn session_store_path(cookie_path: &Path) -> PathBuf {
+  cookie_path
+    .parent()
+    .unwrap_or(Path::new(""))
+    .join("sessionstore-backups/recovery.jsonlz4")
+}
+
+/// Seconds since the Unix epoch of a Firefox expiry, in seconds before Firefox 136 and in
+/// milliseconds since.
+fn firefox_expiry(expiry: i64) -> Option<i64> {
+  // Expiry is capped at 400 days, so no time in seconds comes near year 5000
+  const MILLISECONDS_FROM: i64 = 100_000_000_000;
+  match expiry {
+    ..=0 => None,
+    MILLISECONDS_FROM.. => Some(expiry / 1000),
+    _ => Some(expiry),
+  }
+}
+
+fn firefox_same_site(same_site: i64) -> SameSite {
+  match same_site {
+    0 => SameSite::None,
+    1 => SameSite::Lax,
+    2 => SameSite::Strict,
+    _ => SameSite::Unspecified,
+  }
+}
+
+/// Cookies of a session store file, `recovery.jsonlz4` or `sessionstore.jsonlz4`.
+///
+/// Firefox keeps them in the top-level `cookies` since version 74 and in each window before.
+pub fn session_cookies(path: &Path) -> Result<Vec<SessionCookie>, ChromeCookieError> {
+  let json = decompress_mozlz4(&std::fs::read(path)?)?;
+  let session: serde_json::Value = serde_json::from_slice(&json)?;
+  let windows = session["windows"].as_array().into_iter().flatten();
+  let cookies = std::iter::once(&session["cookies"])
+    .chain(windows.map(|window| &window["cookies"]))
+    .filter_map(|cookies| cookies.as_array())
+    .flatten();
+  let text =
+    |cookie: &serde_json::Value, key: &str| cookie[key].as_str().unwrap_or_default().to_string();
+  Ok(
+    cookies
+      .map(|cookie| {
+        let origin = OriginAttributes::from_json(&cookie["originAttributes"]);
+        let path = text(cookie, "path");
+        SessionCookie {
+          cookie: Cookie {
+            host: text(cookie, "host"),
+            name: text(cookie, "name"),
+            value: text(cookie, "value"),
+            path: if path.is_empty() {
+              "/".to_string()
+            } else {
+              path
+            },
+            expires: cookie["expiry"].as_i64().and_then(firefox_expiry),
+            secure: cookie["secure"].as_bool().unwrap_or(false),
+            http_only: cookie["httponly"].as_bool().unwrap_or(false),
+            same_site: cookie["sameSite"]
+              .as_i64()
+              .map_or(SameSite::Unspecified, firefox_same_site),
+            partition_key: Some(origin.partition_key.clone()).filter(|key| !key.is_empty()),
+          },
+          origin,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Columns of the `moz_cookies` table, each with its value in databases that predate it.
+const FIREFOX_COLUMNS: &[(&str, &str)] = &[
+  ("host", "host"),
+  ("name", "name"),
+  ("value", "value"),
+  ("path", "'/'"),
+  ("expiry", "0"),
+  ("isSecure", "0"),
+  ("isHttpOnly", "0"),
+  ("sameSite", "-1"),
+  ("originAttributes", "''"),
+  ("lastAccessed", "0"),
+];
+
+/// A cookie of `cookies.sqlite` or the session store with the time it was last set or read,
+/// in microseconds since the Unix epoch.
+struct FirefoxRow {
+  cookie: Cookie,
+  origin: OriginAttributes,
+  updated: i64,
+}
+
+/// Cookies of `cookies.sqlite` merged with those of the session store, the newest value of each
+/// cookie kept, of every origin `filter` selects.
+fn firefox_rows(
+  cookie_path: &str,
+  filter: &CookieFilter,
+) -> Result<Vec<FirefoxRow>, ChromeCookieError> {
+  let conn = open_cookie_db(cookie_path)?;
+  let columns = select_columns(&conn, "moz_cookies", FIREFOX_COLUMNS)?;
+  let (condition, params) = filter.sql_condition("host");
+  let mut stmt = conn.prepare(&format!(
+    "SELECT {} FROM moz_cookies WHERE {}",
+    columns, condition
+  ))?;
+  let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+    let origin = OriginAttributes::parse(&row.get::<_, Option<String>>(8)?.unwrap_or_default());
+    Ok(FirefoxRow {
+      cookie: Cookie {
+        host: row.get(0)?,
+        name: row.get(1)?,
+        value: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+        path: row
+          .get::<_, Option<String>>(3)?
+          .unwrap_or_else(|| "/".to_string()),
+        expires: firefox_expiry(row.get::<_, Option<i64>>(4)?.unwrap_or(0)),
+        secure: row.get::<_, Option<i64>>(5)?.unwrap_or(0) != 0,
+        http_only: row.get::<_, Option<i64>>(6)?.unwrap_or(0) != 0,
+        same_site: firefox_same_site(row.get::<_, Option<i64>>(7)?.unwrap_or(-1)),
+        partition_key: Some(origin.partition_key.clone()).filter(|key| !key.is_empty()),
+      },
+      origin,
+      updated: row.get::<_, Option<i64>>(9)?.unwrap_or(0),
+    })
+  })?;
+  let mut rows: Vec<FirefoxRow> = rows.collect::<Result<_, _>>()?;
+
+  let session_path = session_store_path(Path::new(cookie_path));
+  let cookies = match session_cookies(&session_path) {
+    Ok(cookies) => cookies,
+    Err(ChromeCookieError::IoError(e)) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+    Err(e) => return Err(e),
+  };
+  if !cookies.is_empty() {
+    // Session cookies carry no time, so they are as new as the file
+    let written = std::fs::metadata(&session_path)?
+      .modified()?
+      .duration_since(std::time::UNIX_EPOCH)
+      .map_or(0, |time| time.as_micros() as i64);
+    for SessionCookie { cookie, origin } in cookies {
+      let same = |row: &&mut FirefoxRow| {
+        row.cookie.host == cookie.host
+          && row.cookie.name == cookie.name
+          && row.cookie.path == cookie.path
+          && row.origin == origin
+      };
+      match rows.iter_mut().find(same) {
+        Some(row) if row.updated >= written => {}
+        Some(row) => {
+          row.cookie = cookie;
+          row.updated = written;
+        }
+        None => rows.push(FirefoxRow {
+          cookie,
+          origin,
+          updated: written,
+        }),
+      }
+    }
+  }
+  let now = unix_now();
+  rows.retain(|row| filter.matches(&row.cookie, now));
+  rows.sort_by_key(|row| send_order(&row.cookie));
+  Ok(rows)
+}
+
+fn cookies_of(rows: Vec<FirefoxRow>, origin: &OriginAttributes) -> Vec<Cookie> {
+  rows
+    .into_iter()
+    .filter(|row| row.origin == *origin)
+    .map(|row| row.cookie)
+    .collect()
+}
+
+/// Cookies of the jar of `origin` that `filter` selects, in the order a request sends them.
+pub fn get_firefox_cookies_matching(
+  cookie_path: &str,
+  filter: &CookieFilter,
+  origin: &OriginAttributes,
+) -> Result<Vec<Cookie>, ChromeCookieError> {
+  Ok(cookies_of(firefox_rows(cookie_path, filter)?, origin))
+}
+
+/// Value of the cookie `cookie_name` a request to `host` sends from the jar of `origin`, e.g.
+/// [`OriginAttributes::default`] outside of containers.
+pub fn get_firefox_cookie_value(
+  cookie_path: &str,
+  host: &str,
+  cookie_name: &str,
+  origin: &OriginAttributes,
+) -> Result<Option<String>, ChromeCookieError> {
+  let filter = CookieFilter {
+    name: Some(cookie_name.to_string()),
+    ..CookieFilter::host(host)
+  };
+  let cookies = get_firefox_cookies_matching(cookie_path, &filter, origin)?;
+  Ok(cookies.into_iter().next().map(|cookie| cookie.value))
+}
+
+/// Unexpired cookies a request to `host` sends from the jar of `origin`.
+pub fn get_firefox_cookies_for_host(
+  cookie_path: &str,
+  host: &str,
+  origin: &OriginAttributes,
+) -> Result<Vec<Cookie>, ChromeCookieError> {
+  get_firefox_cookies_matching(cookie_path, &CookieFilter::host(host), origin)
+}
+
+/// Get all unexpired cookies of the jar of `origin` from the Firefox cookie database and
+/// session store
+pub fn get_firefox_cookies(
+  cookie_path: &str,
+  origin: &OriginAttributes,
+) -> Result<Vec<Cookie>, ChromeCookieError> {
+  get_firefox_cookies_matching(cookie_path, &CookieFilter::default(), origin)
+}
+
+/// The cookies of a host in one jar, e.g. a login in a container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirefoxSession {
+  pub origin: OriginAttributes,
+  /// Name of the container of `origin.user_context_id` from `containers.json`.
+  pub container: Option<String>,
+  pub cookies: Vec<Cookie>,
+}
+
+/// The cookies `filter` selects grouped by jar, the default one first, then by container.
+pub fn firefox_sessions(
+  cookie_path: &str,
+  filter: &CookieFilter,
+) -> Result<Vec<FirefoxSession>, ChromeCookieError> {
+  let profile = Path::new(cookie_path).parent().unwrap_or(Path::new(""));
+  let containers = containers(profile)?;
+  let mut sessions: Vec<FirefoxSession> = Vec::new();
+  for row in firefox_rows(cookie_path, filter)? {
+    let index = match sessions.iter().position(|s| s.origin == row.origin) {
+      Some(index) => index,
+      None => {
+        let container = containers
+          .iter()
+          .find(|c| c.user_context_id == row.origin.user_context_id)
+          .map(|c| c.name.clone());
+        sessions.push(FirefoxSession {
+          origin: row.origin,
+          container,
+          cookies: Vec::new(),
+        });
+        sessions.len() - 1
+      }
+    };
+    sessions[index].cookies.push(row.cookie);
+  }
+  sessions.sort_by(|a, b| a.origin.cmp(&b.origin));
+  Ok(sessions)
+}
@@ -0,0 +1,120 @@
+use crate::ChromeCookieError;
+use std::io::ErrorKind;
+use std::process::Command;
+
+/// Password of `v10` cookies on Linux, used when Chrome has no keyring to store one.
+/// ref: [chromium](https://source.chromium.org/chromium/chromium/src/+/main:components/os_crypt/sync/os_crypt_linux.cc)
+pub const V10_PASSWORD: &str = "peanuts";
+
+/// A source of the "Safe Storage" password Chrome encrypts `v11` cookies with on Linux.
+pub trait KeyProvider {
+  /// Short name used in error messages, e.g. `secret-service`.
+  fn name(&self) -> &str;
+
+  /// The password, `None` when this source does not hold one.
+  fn password(&self) -> Result<Option<String>, ChromeCookieError>;
+}
+
+/// A password known in advance, e.g. set in the plugin configuration.
+pub struct FixedPassword(pub String);
+
+impl KeyProvider for FixedPassword {
+  fn name(&self) -> &str {
+    "fixed"
+  }
+
+  fn password(&self) -> Result<Option<String>, ChromeCookieError> {
+    Ok(Some(self.0.clone()))
+  }
+}
+
+/// Look up the password in the Secret Service (GNOME Keyring, KeePassXC, ...) through libsecret's
+/// `secret-tool`, from the `libsecret-tools` package of most distributions.
+pub struct SecretService {
+  /// `application` attribute of the item, `chrome` for Google Chrome.
+  pub application: String,
+}
+
+impl KeyProvider for SecretService {
+  fn name(&self) -> &str {
+    "secret-service"
+  }
+
+  fn password(&self) -> Result<Option<String>, ChromeCookieError> {
+    lookup(Command::new("secret-tool").args(["lookup", "application", &self.application]))
+  }
+}
+
+/// Look up the password in KDE Wallet through `kwallet-query`.
+pub struct KWallet {
+  pub wallet: String,
+  /// Folder of the entry, `Chrome Keys` for Google Chrome.
+  pub folder: String,
+  /// Entry name, `Chrome Safe Storage` for Google Chrome.
+  pub entry: String,
+}
+
+impl KeyProvider for KWallet {
+  fn name(&self) -> &str {
+    "kwallet"
+  }
+
+  fn password(&self) -> Result<Option<String>, ChromeCookieError> {
+    lookup(Command::new("kwallet-query").args([
+      "--read-password",
+      &self.entry,
+      "--folder",
+      &self.folder,
+      &self.wallet,
+    ]))
+  }
+}
+
+/// Run a lookup command, a missing entry is no password while a missing program is an error.
+fn lookup(command: &mut Command) -> Result<Option<String>, ChromeCookieError> {
+  let out = match command.output() {
+    Ok(out) => out,
+    Err(e) if e.kind() == ErrorKind::NotFound => {
+      return Err(ChromeCookieError::CommandFailed(format!(
+        "`{}` is not installed",
+        command.get_program().to_string_lossy()
+      )))
+    }
+    Err(e) => return Err(e.into()),
+  };
+  if !out.status.success() {
+    return Ok(None);
+  }
+  let password = String::from_utf8(out.stdout)?;
+  let password = password.trim_end_matches(['\r', '\n']);
+  Ok((!password.is_empty()).then(|| password.to_string()))
+}
+
+/// Providers of Google Chrome's password in the order they are tried: the `explicit` one from
+/// the configuration, the Secret Service and KDE Wallet.
+/// See [`crate::Browser::key_providers`] for the other browsers.
+pub fn linux_key_providers(explicit: Option<&str>) -> Vec<Box<dyn KeyProvider>> {
+  crate::BROWSERS[0].key_providers(explicit)
+}
+
+/// The first password any of `providers` holds.
+///
+/// A failing provider does not hide the ones after it, the errors are only returned when no
+/// provider has a password.
+pub fn resolve_password(
+  providers: &[Box<dyn KeyProvider>],
+) -> Result<Option<String>, ChromeCookieError> {
+  let mut failures = Vec::new();
+  for provider in providers {
+    match provider.password() {
+      Ok(Some(password)) => return Ok(Some(password)),
+      Ok(None) => {}
+      Err(e) => failures.push(format!("{}: {}", provider.name(), e)),
+    }
+  }
+  if failures.is_empty() {
+    Ok(None)
+  } else {
+    Err(ChromeCookieError::CommandFailed(failures.join("; ")))
+  }
+}
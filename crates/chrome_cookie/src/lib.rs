@@ -1,25 +1,47 @@
-use aes::Aes128;
 use aes_gcm::{
   aead::{Aead, KeyInit},
   Aes128Gcm,
 };
 use base64::{engine::general_purpose, Engine as _};
-use block_modes::block_padding::Pkcs7;
-use block_modes::{BlockMode, Cbc};
-use dirs;
-use hmac::Hmac;
 use mlua::{Error as LuaError, Lua, Result as LuaResult};
-use pbkdf2::{pbkdf2, pbkdf2_hmac};
+use pbkdf2::pbkdf2_hmac;
 use rusqlite::Connection;
 use serde_json::Value;
 use sha1::Sha1;
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::PathBuf;
-use std::{error::Error, fmt, process::Command};
+use std::{error::Error, fmt};
+mod browser;
+mod cookie;
+mod decrypt;
 pub mod encrypt;
+mod firefox;
+mod keyring;
+mod mozlz4;
+mod origin;
+mod snapshot;
+pub use browser::{browser, profile_cookie_path, profiles, Browser, Profile, BROWSERS};
+use cookie::select_columns;
+pub use cookie::{
+  domain_match, path_match, sort_cookies, unix_now, Cookie, CookieFilter, SameSite,
+};
+pub use decrypt::{
+  derive_linux_key, AesGcm, CookieDecryptor, Decryptors, LinuxCbc, LinuxKeys, MacCbc, Platform,
+};
+pub use firefox::{
+  firefox_cookie_path, firefox_dir, firefox_dirs, firefox_profiles, firefox_sessions,
+  get_firefox_cookie_value, get_firefox_cookies, get_firefox_cookies_for_host,
+  get_firefox_cookies_matching, session_cookies, FirefoxProfile, FirefoxSession, SessionCookie,
+};
+pub use keyring::{
+  linux_key_providers, resolve_password, FixedPassword, KWallet, KeyProvider, SecretService,
+  V10_PASSWORD,
+};
+pub use mozlz4::{decompress_mozlz4, MOZLZ4_MAGIC};
+pub use origin::{containers, Container, OriginAttributes};
+pub use snapshot::{open_cookie_db, CookieDb};
 
 const SALT: &[u8] = b"saltysalt";
-type Aes128Cbc = Cbc<Aes128, Pkcs7>;
 
 #[derive(Debug)]
 pub enum ChromeCookieError {
@@ -31,6 +53,13 @@ pub enum ChromeCookieError {
   SqliteError(rusqlite::Error),
   UnsupportedPlatform,
   JsonErr(String),
+  UnknownBrowser(String),
+  /// No user data directory of the browser exists.
+  NotInstalled(&'static str),
+  /// No profile of the browser has stored cookies yet.
+  NoCookieDatabase(&'static str),
+  /// A mozLz4 file of Firefox, e.g. the session store, is malformed.
+  MozLz4(String),
 }
 impl fmt::Display for ChromeCookieError {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -45,6 +74,15 @@ impl fmt::Display for ChromeCookieError {
         write!(f, "Unsupported platform for Chrome cookies")
       }
       ChromeCookieError::JsonErr(e) => write!(f, "Json error: {}", e),
+      ChromeCookieError::UnknownBrowser(id) => {
+        let ids: Vec<&str> = BROWSERS.iter().map(|browser| browser.id).collect();
+        write!(f, "Unknown browser `{}`, use one of {}", id, ids.join(", "))
+      }
+      ChromeCookieError::NotInstalled(name) => write!(f, "No {} user data found", name),
+      ChromeCookieError::NoCookieDatabase(name) => {
+        write!(f, "No {} profile has a cookie database", name)
+      }
+      ChromeCookieError::MozLz4(e) => write!(f, "Invalid mozLz4 data: {}", e),
     }
   }
 }
@@ -76,7 +114,7 @@ impl From<ChromeCookieError> for LuaError {
 }
 
 impl From<mlua::Error> for ChromeCookieError {
-  fn from(err: mlua::Error) -> Self {
+  fn from(_: mlua::Error) -> Self {
     ChromeCookieError::UnsupportedPlatform
   }
 }
@@ -87,49 +125,56 @@ impl From<serde_json::Error> for ChromeCookieError {
   }
 }
 
-/// Get the Chrome password from macOS Keychain (legacy versions)
-fn get_chrome_password_macos() -> Result<String, ChromeCookieError> {
+/// Get the browser password from macOS Keychain (legacy versions)
+#[cfg(target_os = "macos")]
+fn get_browser_password_macos(browser: &Browser) -> Result<Option<String>, ChromeCookieError> {
+  use std::process::Command;
+
   let out = Command::new("security")
-    .args(&["find-generic-password", "-w", "-s", "Chrome Safe Storage"])
+    .args(["find-generic-password", "-w", "-s", browser.safe_storage])
     .output()?;
-  Ok(String::from_utf8(out.stdout)?.trim().to_string())
-}
-
-/// Get the Chrome encrypted key from Linux Local State file
-fn get_chrome_password_linux() -> Result<String, ChromeCookieError> {
-  use serde_json::Value;
-  use std::env;
-  use std::fs;
-
-  let home_dir = env::var("HOME").map_err(|_| ChromeCookieError::JsonErr("No HOME env".into()))?;
-  let local_state_path = format!("{}/.config/google-chrome/Local State", home_dir);
-  let data = fs::read_to_string(local_state_path)?;
-  let v: Value = serde_json::from_str(&data)?;
-  let key = v["os_crypt"]["encrypted_key"]
-    .as_str()
-    .ok_or_else(|| ChromeCookieError::JsonErr("No encrypted_key found".into()))?;
-  Ok(key.to_string())
+  let password = String::from_utf8(out.stdout)?.trim().to_string();
+  Ok((!password.is_empty()).then_some(password))
 }
 
-fn get_chrome_password() -> Result<String, ChromeCookieError> {
+/// Get the password of a browser's cookies, `explicit` is a password from the configuration
+/// which skips the lookup
+///
+/// On Linux it is looked up in the Secret Service and KDE Wallet, `None` when the browser stores
+/// none there and so only has `v10` cookies.
+pub fn get_browser_password(
+  browser: &Browser,
+  explicit: Option<&str>,
+) -> Result<Option<String>, ChromeCookieError> {
   #[cfg(target_os = "macos")]
   {
-    get_chrome_password_macos()
+    match explicit {
+      Some(password) => Ok(Some(password.to_string())),
+      None => get_browser_password_macos(browser),
+    }
   }
   #[cfg(target_os = "linux")]
   {
-    get_chrome_password_linux()
+    resolve_password(&browser.key_providers(explicit))
   }
   #[cfg(not(any(target_os = "macos", target_os = "linux")))]
   {
+    let _ = (browser, explicit);
     Err(ChromeCookieError::UnsupportedPlatform)
   }
 }
 
+/// Get the Chrome password, see [`get_browser_password`]
+fn get_chrome_password(explicit: Option<&str>) -> Result<Option<String>, ChromeCookieError> {
+  get_browser_password(&BROWSERS[0], explicit)
+}
+
 /// Get the master key for decrypting Chrome cookies on macOS (Unused in legacy versions)
 fn get_master_key() -> Result<[u8; 16], ChromeCookieError> {
-  let password = get_chrome_password()?;
-  let mut ls = PathBuf::from(dirs::home_dir().unwrap());
+  let password = get_chrome_password(None)?.ok_or_else(|| {
+    ChromeCookieError::DecryptionFailed("no Chrome Safe Storage password in the Keychain".into())
+  })?;
+  let mut ls = dirs::home_dir().unwrap();
   ls.push("Library/Application Support/Google/Chrome/Local State");
   let txt = fs::read_to_string(ls)?;
   let json: Value = serde_json::from_str(&txt)
@@ -156,6 +201,15 @@ fn get_master_key() -> Result<[u8; 16], ChromeCookieError> {
   Ok(master)
 }
 
+/// Text of a decrypted value, `None` when empty.
+fn cookie_text(plaintext: &[u8]) -> Option<String> {
+  let cookie_str = String::from_utf8_lossy(plaintext);
+
+  // Remove trailing nulls or backticks (if any)
+  let cleaned_cookie = cookie_str.trim_end_matches(['\0', '`']);
+  (!cleaned_cookie.is_empty()).then(|| cleaned_cookie.to_string())
+}
+
 /// Decryption function for Chrome cookies on macOS (legacy version)
 /// ref: [cyberark](https://www.cyberark.com/resources/threat-research-blog/the-current-state-of-browser-cookies)
 /// ref: [chromium](https://source.chromium.org/chromium/chromium/src/+/main:components/os_crypt/sync/os_crypt_mac.mm)
@@ -163,180 +217,291 @@ pub fn decrypt_chrome_cookie_macos_legacy(
   encrypted_value: &[u8],
   password: &str,
 ) -> Result<Option<String>, ChromeCookieError> {
-  const PREFIX: &[u8] = b"v10";
-  const SALT: &[u8] = b"saltysalt";
-  const IV: [u8; 16] = [b' '; 16];
-  const ITERATIONS: u32 = 1003;
-
-  if encrypted_value.len() <= PREFIX.len() || &encrypted_value[..PREFIX.len()] != PREFIX {
+  if !encrypted_value.starts_with(b"v10") || encrypted_value.len() <= 3 {
     return Ok(None);
   }
-
-  let mut key = [0u8; 16];
-  pbkdf2::<Hmac<Sha1>>(password.as_bytes(), SALT, ITERATIONS, &mut key);
-
-  let encrypted_data = &encrypted_value[PREFIX.len()..];
-
-  let cipher = Aes128Cbc::new_from_slices(&key, &IV)
-    .map_err(|_| ChromeCookieError::DecryptionFailed("AES-CBC init failed".into()))?;
-  let decrypted = cipher
-    .decrypt_vec(encrypted_data)
-    .map_err(|_| ChromeCookieError::DecryptionFailed("AES-CBC decrypt failed".into()))?;
-
-  let cookie_str = String::from_utf8_lossy(&decrypted).to_string();
-  if cookie_str.is_empty() {
-    Ok(None)
-  } else {
-    Ok(Some(cookie_str))
-  }
-}
-
-pub fn derive_linux_key(password: &str) -> [u8; 16] {
-  let salt = b"saltysalt";
-  let iterations = 1;
-  let mut key = [0u8; 16];
-  pbkdf2::<Hmac<Sha1>>(password.as_bytes(), salt, iterations, &mut key);
-  key
+  Ok(cookie_text(
+    &MacCbc::new(password).decrypt(encrypted_value)?,
+  ))
 }
 
-/// Decrypt Chrome cookies on Linux
+/// Decrypt Chrome cookies on Linux, `password` being the keyring password of `v11` cookies
 /// ref: [chromium](https://source.chromium.org/chromium/chromium/src/+/main:components/os_crypt/sync/os_crypt_linux.cc)
 pub fn decrypt_chrome_cookie_linux(
   encrypted_value: &[u8],
   password: &str,
 ) -> Result<Option<String>, ChromeCookieError> {
-  // Chrome Linux uses "saltysalt" as salt, 1 iteration, 16-byte key, IV of all spaces
-  let iv = [b' '; 16];
-  let key = derive_linux_key(password);
-
-  // Check for v10/v11 prefix
-  if encrypted_value.len() <= 3 {
-    return Ok(None);
-  }
-  let prefix = &encrypted_value[..3];
-  if prefix != b"v10" && prefix != b"v11" {
-    return Ok(None);
-  }
-  let encrypted_data = &encrypted_value[3..];
-
-  let cipher = Aes128Cbc::new_from_slices(&key, &iv)
-    .map_err(|_| ChromeCookieError::DecryptionFailed("AES-CBC init failed".into()))?;
+  LinuxKeys::new(Some(password)).decrypt(encrypted_value)
+}
 
-  let decrypted = cipher
-    .decrypt_vec(encrypted_data)
-    .map_err(|_| ChromeCookieError::DecryptionFailed("AES-CBC decrypt failed".into()))?;
+/// Decrypt a cookie with the decryptor its version prefix and the database platform call for
+pub fn decrypt_chrome_cookie(
+  encrypted_value: &[u8],
+  decryptors: &Decryptors,
+) -> Result<Option<String>, ChromeCookieError> {
+  Ok(
+    decryptors
+      .decrypt(encrypted_value)?
+      .and_then(|plain| cookie_text(&plain)),
+  )
+}
 
-  let cookie_str = String::from_utf8_lossy(&decrypted);
+/// First `meta.version` of cookie databases whose values start with the SHA-256 of their host.
+pub const HOST_HASH_DB_VERSION: i64 = 24;
+
+/// `meta.version` of a Chrome cookie database, 0 if it has none.
+pub fn cookie_db_version(conn: &Connection) -> Result<i64, ChromeCookieError> {
+  let version = conn.query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| {
+    row.get::<_, rusqlite::types::Value>(0)
+  });
+  match version {
+    Ok(rusqlite::types::Value::Integer(version)) => Ok(version),
+    Ok(rusqlite::types::Value::Text(version)) => version.trim().parse().map_err(|_| {
+      ChromeCookieError::DecryptionFailed(format!("invalid cookie database version {}", version))
+    }),
+    Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0),
+    Err(rusqlite::Error::SqliteFailure(_, Some(message))) if message.contains("no such table") => {
+      Ok(0)
+    }
+    Err(e) => Err(e.into()),
+  }
+}
 
-  // Remove trailing nulls or backticks (if any)
-  let cleaned_cookie = cookie_str
-    .trim_end_matches(|c| c == '\0' || c == '`')
-    .to_string();
-
-  if cleaned_cookie.is_empty() {
-    Ok(None)
-  } else {
-    Ok(Some(cleaned_cookie))
+/// Strip the SHA-256 of `host_key` Chrome puts before values from database version
+/// [`HOST_HASH_DB_VERSION`] on, so that a value cannot be moved to another host.
+///
+/// A missing hash in such a database means the value was decrypted with the wrong key.
+/// ref: [chromium](https://source.chromium.org/chromium/chromium/src/+/main:net/extras/sqlite/sqlite_persistent_cookie_store.cc)
+pub fn strip_host_hash<'a>(
+  plaintext: &'a [u8],
+  host_key: &str,
+  db_version: i64,
+) -> Result<&'a [u8], ChromeCookieError> {
+  if db_version < HOST_HASH_DB_VERSION {
+    return Ok(plaintext);
   }
+  let hash = Sha256::digest(host_key.as_bytes());
+  plaintext.strip_prefix(hash.as_slice()).ok_or_else(|| {
+    ChromeCookieError::DecryptionFailed(format!(
+      "value of a {} cookie does not start with the hash of its host, the password may be wrong",
+      host_key
+    ))
+  })
 }
 
-/// Decrypt cookies for macOS and Linux
-pub fn decrypt_chrome_cookie(
-  encrypted_value: &[u8],
-  password: &str,
+/// Text of a database value, `None` when it is not encrypted.
+fn cookie_value(
+  blob: &[u8],
+  host_key: &str,
+  db_version: i64,
+  decryptors: &Decryptors,
 ) -> Result<Option<String>, ChromeCookieError> {
-  #[cfg(target_os = "macos")]
-  {
-    decrypt_chrome_cookie_macos_legacy(encrypted_value, password)
+  match decryptors.decrypt(blob)? {
+    Some(plain) => Ok(cookie_text(strip_host_hash(&plain, host_key, db_version)?)),
+    None => Ok(None),
   }
-  #[cfg(target_os = "linux")]
-  {
-    decrypt_chrome_cookie_linux(encrypted_value, password)
+}
+
+/// Columns of the `cookies` table, each with its value in databases that predate it.
+const CHROME_COLUMNS: &[(&str, &str)] = &[
+  ("host_key", "host_key"),
+  ("name", "name"),
+  ("encrypted_value", "encrypted_value"),
+  ("value", "''"),
+  ("path", "'/'"),
+  ("expires_utc", "0"),
+  ("is_secure", "0"),
+  ("is_httponly", "0"),
+  ("samesite", "-1"),
+  ("top_frame_site_key", "''"),
+];
+
+/// Seconds since the Unix epoch of a Chrome time, microseconds since 1601, `None` for 0.
+fn chrome_time(time: i64) -> Option<i64> {
+  const UNIX_EPOCH_SECONDS: i64 = 11_644_473_600;
+  (time != 0).then(|| time / 1_000_000 - UNIX_EPOCH_SECONDS)
+}
+
+/// Cookies of the Chrome cookie database `filter` selects, in the order a request sends them.
+///
+/// Rows that cannot be decrypted, e.g. left over from an older key, are skipped so that they do
+/// not hide the others. The first such error is returned only when no cookie could be read.
+pub fn get_cookies_matching(
+  cookie_path: &str,
+  decryptors: &Decryptors,
+  filter: &CookieFilter,
+) -> Result<Vec<Cookie>, ChromeCookieError> {
+  let conn = open_cookie_db(cookie_path)?;
+  let db_version = cookie_db_version(&conn)?;
+  let columns = select_columns(&conn, "cookies", CHROME_COLUMNS)?;
+  // Only rows of candidate hosts are decrypted
+  let (condition, params) = filter.sql_condition("host_key");
+  let mut stmt = conn.prepare(&format!(
+    "SELECT {} FROM cookies WHERE {}",
+    columns, condition
+  ))?;
+  let mut rows = stmt.query(rusqlite::params_from_iter(params))?;
+  let now = unix_now();
+  let mut cookies = Vec::new();
+  let mut failure = None;
+  while let Some(row) = rows.next()? {
+    let mut cookie = Cookie {
+      host: row.get(0)?,
+      name: row.get(1)?,
+      value: String::new(),
+      path: row.get(4)?,
+      expires: chrome_time(row.get(5)?),
+      secure: row.get::<_, i64>(6)? != 0,
+      http_only: row.get::<_, i64>(7)? != 0,
+      same_site: match row.get::<_, i64>(8)? {
+        0 => SameSite::None,
+        1 => SameSite::Lax,
+        2 => SameSite::Strict,
+        _ => SameSite::Unspecified,
+      },
+      partition_key: Some(row.get::<_, String>(9)?).filter(|key| !key.is_empty()),
+    };
+    if !filter.matches(&cookie, now) {
+      continue;
+    }
+    let blob: Vec<u8> = row.get::<_, Option<Vec<u8>>>(2)?.unwrap_or_default();
+    cookie.value = match cookie_value(&blob, &cookie.host, db_version, decryptors) {
+      Ok(Some(value)) => value,
+      Ok(None) if blob.is_empty() => row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+      Ok(None) => String::from_utf8_lossy(&blob).to_string(),
+      Err(err) => {
+        failure.get_or_insert(err);
+        continue;
+      }
+    };
+    cookies.push(cookie);
   }
-  #[cfg(not(any(target_os = "macos", target_os = "linux")))]
-  {
-    Err(ChromeCookieError::UnsupportedPlatform)
+  match failure {
+    Some(err) if cookies.is_empty() => Err(err),
+    _ => {
+      sort_cookies(&mut cookies);
+      Ok(cookies)
+    }
   }
 }
 
+/// Value of the cookie `cookie_name` a request to `host` sends.
 pub fn get_cookie_value(
   cookie_path: &str,
-  password: &str,
+  decryptors: &Decryptors,
   host: &str,
   cookie_name: &str,
 ) -> Result<Option<String>, ChromeCookieError> {
-  let conn = Connection::open(cookie_path)?;
-  let mut stmt = conn
-    .prepare("SELECT encrypted_value FROM cookies WHERE host_key LIKE ? AND name = ?")
-    .map_err(ChromeCookieError::SqliteError)?;
-  let mut rows = stmt.query([host, cookie_name])?;
-  let encrypted_value: Vec<u8> = match rows.next()? {
-    Some(row) => row.get(0)?,
-    None => return Ok(None),
+  let filter = CookieFilter {
+    name: Some(cookie_name.to_string()),
+    ..CookieFilter::host(host)
   };
-  decrypt_chrome_cookie_macos_legacy(&encrypted_value, password)
+  let cookies = get_cookies_matching(cookie_path, decryptors, &filter)?;
+  Ok(cookies.into_iter().next().map(|cookie| cookie.value))
 }
 
+/// Unexpired cookies a request to `host` sends, e.g. `zhihu.com`.
 pub fn get_cookies_for_host(
   cookie_path: &str,
-  password: &str,
+  decryptors: &Decryptors,
   host: &str,
-) -> Result<Vec<(String, String)>, ChromeCookieError> {
-  let conn = Connection::open(cookie_path)?;
-  let mut stmt = conn.prepare("SELECT name, encrypted_value FROM cookies WHERE host_key LIKE ?")?;
-  let mut rows = stmt.query([host])?;
-  let mut result = Vec::new();
-  while let Some(row) = rows.next()? {
-    let name: String = row.get(0)?;
-    let blob: Vec<u8> = row.get(1)?;
-    let val = if blob.len() > 3 {
-      match decrypt_chrome_cookie_macos_legacy(&blob, password)? {
-        Some(v) => v,
-        None => String::from_utf8_lossy(&blob).to_string(),
-      }
-    } else {
-      String::from_utf8_lossy(&blob).to_string()
-    };
-    result.push((name, val));
-  }
-  Ok(result)
+) -> Result<Vec<Cookie>, ChromeCookieError> {
+  get_cookies_matching(cookie_path, decryptors, &CookieFilter::host(host))
 }
 
-/// Get all cookies from the Chrome cookie database
+/// Get all unexpired cookies from the Chrome cookie database
 pub fn get_cookies(
   cookie_path: &str,
-  password: &str,
-) -> Result<Vec<(String, String)>, ChromeCookieError> {
-  let conn = Connection::open(cookie_path)?;
-  let mut stmt = conn.prepare("SELECT name, encrypted_value FROM cookies")?;
-  let mut rows = stmt.query([])?;
-  let mut result = Vec::new();
-  while let Some(row) = rows.next()? {
-    let name: String = row.get(0)?;
-    let blob: Vec<u8> = row.get(1)?;
-    let val = if blob.len() > 3 {
-      match decrypt_chrome_cookie(&blob, password)? {
-        Some(v) => v,
-        none => String::from_utf8_lossy(&blob).to_string(),
-      }
-    } else {
-      String::from_utf8_lossy(&blob).to_string()
-    };
-    result.push((name, val));
+  decryptors: &Decryptors,
+) -> Result<Vec<Cookie>, ChromeCookieError> {
+  get_cookies_matching(cookie_path, decryptors, &CookieFilter::default())
+}
+
+/// Filter of Lua `{ path?, include_expired? }` options.
+fn filter_from_lua(
+  opts: Option<&mlua::Table>,
+  host: Option<String>,
+  name: Option<String>,
+) -> LuaResult<CookieFilter> {
+  let mut filter = CookieFilter {
+    host,
+    name,
+    ..CookieFilter::default()
+  };
+  if let Some(opts) = opts {
+    filter.path = opts.get("path")?;
+    filter.include_expired = opts
+      .get::<Option<bool>>("include_expired")?
+      .unwrap_or(false);
   }
-  Ok(result)
+  Ok(filter)
+}
+
+/// Lua list of `{ host, name, value, path, expires?, secure, http_only, same_site, partition_key? }`.
+fn cookies_to_lua(lua: &Lua, cookies: Vec<Cookie>) -> LuaResult<mlua::Table> {
+  let list = lua.create_table()?;
+  for cookie in cookies {
+    let entry = lua.create_table()?;
+    entry.set("host", cookie.host)?;
+    entry.set("name", cookie.name)?;
+    entry.set("value", cookie.value)?;
+    entry.set("path", cookie.path)?;
+    entry.set("expires", cookie.expires)?;
+    entry.set("secure", cookie.secure)?;
+    entry.set("http_only", cookie.http_only)?;
+    entry.set("same_site", cookie.same_site.as_str())?;
+    entry.set("partition_key", cookie.partition_key)?;
+    list.push(entry)?;
+  }
+  Ok(list)
+}
+
+/// Origin attributes of Lua `{ user_context_id?, first_party_domain?, partition_key? }` options,
+/// the default jar without them.
+fn origin_from_lua(opts: Option<&mlua::Table>) -> LuaResult<OriginAttributes> {
+  let Some(opts) = opts else {
+    return Ok(OriginAttributes::default());
+  };
+  Ok(OriginAttributes {
+    user_context_id: opts.get::<Option<u32>>("user_context_id")?.unwrap_or(0),
+    first_party_domain: opts
+      .get::<Option<String>>("first_party_domain")?
+      .unwrap_or_default(),
+    partition_key: opts
+      .get::<Option<String>>("partition_key")?
+      .unwrap_or_default(),
+  })
+}
+
+/// Decryptors for a Lua `password`, nil without a keyring, and `{ platform?, master_key? }`
+/// options.
+fn decryptors_from_lua(password: Option<&str>, opts: Option<mlua::Table>) -> LuaResult<Decryptors> {
+  let (mut platform, mut master_key) = (Platform::current(), None);
+  if let Some(opts) = opts {
+    if let Some(name) = opts.get::<Option<String>>("platform")? {
+      platform = Platform::parse(&name)
+        .ok_or_else(|| LuaError::runtime(format!("Unknown cookie database platform {}", name)))?;
+    }
+    master_key = opts.get::<Option<mlua::String>>("master_key")?;
+  }
+  let master_key = master_key.map(|key| key.as_bytes().to_vec());
+  Ok(Decryptors::new(platform, password, master_key.as_deref())?)
 }
 
 #[mlua::lua_module]
 fn chrome_cookie_lib(lua: &Lua) -> LuaResult<mlua::Table> {
   let exports = lua.create_table()?;
 
-  // decrypt_chrome_cookie(encrypted_value: Vec<u8>, password: String) -> Option<String>
+  // decrypt_chrome_cookie(encrypted_value: Vec<u8>, password: Option<String>, opts?: { platform?, master_key? })
+  //   -> Option<String>
   exports.set(
     "decrypt_chrome_cookie",
     lua.create_function(
-      |_, (encrypted_value, password): (Vec<u8>, String)| -> LuaResult<Option<String>> {
-        decrypt_chrome_cookie_macos_legacy(&encrypted_value, &password).map_err(LuaError::external)
+      |_,
+       (encrypted_value, password, opts): (Vec<u8>, Option<String>, Option<mlua::Table>)|
+       -> LuaResult<Option<String>> {
+        let decryptors = decryptors_from_lua(password.as_deref(), opts)?;
+        decrypt_chrome_cookie(&encrypted_value, &decryptors).map_err(LuaError::external)
       },
     )?,
   )?;
@@ -346,56 +511,223 @@ fn chrome_cookie_lib(lua: &Lua) -> LuaResult<mlua::Table> {
     "get_master_key",
     lua.create_function(|lua, ()| {
       get_master_key()
-        .map(|key| lua.create_string(&key).unwrap())
+        .map(|key| lua.create_string(key).unwrap())
         .map_err(LuaError::external)
     })?,
   )?;
 
-  // get_chrome_password() -> String
+  // get_chrome_password(explicit: Option<String>) -> Option<String>
   exports.set(
     "get_chrome_password",
-    lua.create_function(|_, ()| get_chrome_password().map_err(LuaError::external))?,
+    lua.create_function(|_, explicit: Option<String>| {
+      get_chrome_password(explicit.as_deref()).map_err(LuaError::external)
+    })?,
+  )?;
+
+  // browsers() -> { { id, name } }
+  exports.set(
+    "browsers",
+    lua.create_function(|lua, ()| {
+      let list = lua.create_table()?;
+      for browser in BROWSERS {
+        let entry = lua.create_table()?;
+        entry.set("id", browser.id)?;
+        entry.set("name", browser.name)?;
+        list.push(entry)?;
+      }
+      Ok(list)
+    })?,
+  )?;
+
+  // cookie_path(browser: String) -> String
+  exports.set(
+    "cookie_path",
+    lua.create_function(|_, id: String| {
+      let path = browser(&id)?.cookie_path()?;
+      Ok(path.to_string_lossy().into_owned())
+    })?,
+  )?;
+
+  // profiles(browser: String) -> { { name, dir, path, cookie_path?, last_used } }
+  exports.set(
+    "profiles",
+    lua.create_function(|lua, id: String| {
+      let list = lua.create_table()?;
+      for profile in browser(&id)?.profiles()? {
+        let entry = lua.create_table()?;
+        entry.set("name", profile.name)?;
+        entry.set("dir", profile.dir)?;
+        entry.set("path", profile.path.to_string_lossy())?;
+        entry.set(
+          "cookie_path",
+          profile
+            .cookie_path
+            .map(|path| path.to_string_lossy().into_owned()),
+        )?;
+        entry.set("last_used", profile.last_used)?;
+        list.push(entry)?;
+      }
+      Ok(list)
+    })?,
+  )?;
+
+  // get_browser_password(browser: String, explicit: Option<String>) -> Option<String>
+  exports.set(
+    "get_browser_password",
+    lua.create_function(|_, (id, explicit): (String, Option<String>)| {
+      Ok(get_browser_password(browser(&id)?, explicit.as_deref())?)
+    })?,
   )?;
 
-  // get_cookies(cookie_path: String, password: String) -> table
+  // get_cookies(cookie_path: String, password: Option<String>, opts?: table) -> { Cookie }
   exports.set(
     "get_cookies",
     lua.create_function(
-      |lua, (cookie_path, password): (String, String)| -> LuaResult<mlua::Table> {
-        let cookies = get_cookies(&cookie_path, &password).map_err(LuaError::external)?;
-        let table = lua.create_table()?;
-        for (name, val) in cookies {
-          table.set(name, val)?;
-        }
-        Ok(table)
+      |lua,
+       (cookie_path, password, opts): (String, Option<String>, Option<mlua::Table>)|
+       -> LuaResult<mlua::Table> {
+        let filter = filter_from_lua(opts.as_ref(), None, None)?;
+        let decryptors = decryptors_from_lua(password.as_deref(), opts)?;
+        let cookies =
+          get_cookies_matching(&cookie_path, &decryptors, &filter).map_err(LuaError::external)?;
+        cookies_to_lua(lua, cookies)
       },
     )?,
   )?;
 
-  // get_cookies_for_host(cookie_path: String, password: String, host: String) -> table
+  // get_cookies_for_host(cookie_path: String, password: Option<String>, host: String, opts?: table)
+  //   -> { Cookie }
   exports.set(
     "get_cookies_for_host",
     lua.create_function(
-      |lua, (cookie_path, password, host): (String, String, String)| -> LuaResult<mlua::Table> {
+      |lua,
+       (cookie_path, password, host, opts): (
+        String,
+        Option<String>,
+        String,
+        Option<mlua::Table>,
+      )|
+       -> LuaResult<mlua::Table> {
+        let filter = filter_from_lua(opts.as_ref(), Some(host), None)?;
+        let decryptors = decryptors_from_lua(password.as_deref(), opts)?;
         let cookies =
-          get_cookies_for_host(&cookie_path, &password, &host).map_err(LuaError::external)?;
-        let table = lua.create_table()?;
-        for (name, val) in cookies {
-          table.set(name, val)?;
-        }
-        Ok(table)
+          get_cookies_matching(&cookie_path, &decryptors, &filter).map_err(LuaError::external)?;
+        cookies_to_lua(lua, cookies)
       },
     )?,
   )?;
 
-  // get_cookie_value(cookie_path: String, password: String, host: String, name: String) -> Option<String>
+  // get_cookie_value(cookie_path: String, password: Option<String>, host: String, name: String, opts?: table)
+  //   -> Option<String>
   exports.set(
     "get_cookie_value",
     lua.create_function(
       |_,
-       (cookie_path, password, host, name): (String, String, String, String)|
+       (cookie_path, password, host, name, opts): (
+        String,
+        Option<String>,
+        String,
+        String,
+        Option<mlua::Table>,
+      )|
        -> LuaResult<Option<String>> {
-        get_cookie_value(&cookie_path, &password, &host, &name).map_err(LuaError::external)
+        let filter = filter_from_lua(opts.as_ref(), Some(host), Some(name))?;
+        let decryptors = decryptors_from_lua(password.as_deref(), opts)?;
+        let cookies =
+          get_cookies_matching(&cookie_path, &decryptors, &filter).map_err(LuaError::external)?;
+        Ok(cookies.into_iter().next().map(|cookie| cookie.value))
+      },
+    )?,
+  )?;
+
+  // firefox_profiles() -> { { name, path, default, cookie_path? } }
+  exports.set(
+    "firefox_profiles",
+    lua.create_function(|lua, ()| {
+      let list = lua.create_table()?;
+      for profile in firefox_profiles(&firefox_dir()?)? {
+        let entry = lua.create_table()?;
+        entry.set("name", profile.name)?;
+        entry.set("path", profile.path.to_string_lossy())?;
+        entry.set("default", profile.default)?;
+        entry.set(
+          "cookie_path",
+          profile
+            .cookie_path
+            .map(|path| path.to_string_lossy().into_owned()),
+        )?;
+        list.push(entry)?;
+      }
+      Ok(list)
+    })?,
+  )?;
+
+  // firefox_cookie_path() -> String
+  exports.set(
+    "firefox_cookie_path",
+    lua.create_function(|_, ()| Ok(firefox_cookie_path()?.to_string_lossy().into_owned()))?,
+  )?;
+
+  // get_firefox_cookies(cookie_path: String, opts?: table) -> { Cookie }
+  exports.set(
+    "get_firefox_cookies",
+    lua.create_function(|lua, (cookie_path, opts): (String, Option<mlua::Table>)| {
+      let filter = filter_from_lua(opts.as_ref(), None, None)?;
+      let origin = origin_from_lua(opts.as_ref())?;
+      cookies_to_lua(
+        lua,
+        get_firefox_cookies_matching(&cookie_path, &filter, &origin)?,
+      )
+    })?,
+  )?;
+
+  // get_firefox_cookies_for_host(cookie_path: String, host: String, opts?: table) -> { Cookie }
+  exports.set(
+    "get_firefox_cookies_for_host",
+    lua.create_function(
+      |lua, (cookie_path, host, opts): (String, String, Option<mlua::Table>)| {
+        let filter = filter_from_lua(opts.as_ref(), Some(host), None)?;
+        let origin = origin_from_lua(opts.as_ref())?;
+        cookies_to_lua(
+          lua,
+          get_firefox_cookies_matching(&cookie_path, &filter, &origin)?,
+        )
+      },
+    )?,
+  )?;
+
+  // firefox_sessions(cookie_path: String, host: String, opts?: table)
+  //   -> { { user_context_id, first_party_domain, partition_key, container?, cookies } }
+  exports.set(
+    "firefox_sessions",
+    lua.create_function(
+      |lua, (cookie_path, host, opts): (String, String, Option<mlua::Table>)| {
+        let filter = filter_from_lua(opts.as_ref(), Some(host), None)?;
+        let list = lua.create_table()?;
+        for session in firefox_sessions(&cookie_path, &filter)? {
+          let entry = lua.create_table()?;
+          entry.set("user_context_id", session.origin.user_context_id)?;
+          entry.set("first_party_domain", session.origin.first_party_domain)?;
+          entry.set("partition_key", session.origin.partition_key)?;
+          entry.set("container", session.container)?;
+          entry.set("cookies", cookies_to_lua(lua, session.cookies)?)?;
+          list.push(entry)?;
+        }
+        Ok(list)
+      },
+    )?,
+  )?;
+
+  // get_firefox_cookie_value(cookie_path: String, host: String, name: String, opts?: table)
+  //   -> Option<String>
+  exports.set(
+    "get_firefox_cookie_value",
+    lua.create_function(
+      |_, (cookie_path, host, name, opts): (String, String, String, Option<mlua::Table>)| {
+        let filter = filter_from_lua(opts.as_ref(), Some(host), Some(name))?;
+        let origin = origin_from_lua(opts.as_ref())?;
+        let cookies = get_firefox_cookies_matching(&cookie_path, &filter, &origin)?;
+        Ok(cookies.into_iter().next().map(|cookie| cookie.value))
       },
     )?,
   )?;
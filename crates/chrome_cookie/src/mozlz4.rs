@@ -0,0 +1,74 @@
+use crate::ChromeCookieError;
+
+/// Magic number of Mozilla's LZ4 files, e.g. `*.jsonlz4` of the session store.
+pub const MOZLZ4_MAGIC: &[u8] = b"mozLz40\0";
+
+/// Decompress a mozLz4 file: the magic number, the decompressed size as a little-endian `u32`
+/// and one LZ4 block.
+pub fn decompress_mozlz4(data: &[u8]) -> Result<Vec<u8>, ChromeCookieError> {
+  let rest = data
+    .strip_prefix(MOZLZ4_MAGIC)
+    .ok_or_else(|| ChromeCookieError::MozLz4("no mozLz4 magic number".into()))?;
+  let (size, block) = rest
+    .split_first_chunk::<4>()
+    .ok_or_else(|| ChromeCookieError::MozLz4("no decompressed size".into()))?;
+  decompress_block(block, u32::from_le_bytes(*size) as usize)
+}
+
+/// Decompress an LZ4 block of `size` bytes.
+/// ref: [lz4](https://github.com/lz4/lz4/blob/dev/doc/lz4_Block_format.md)
+fn decompress_block(block: &[u8], size: usize) -> Result<Vec<u8>, ChromeCookieError> {
+  let truncated = || ChromeCookieError::MozLz4("truncated LZ4 block".into());
+  // A byte of input yields at most 255 bytes, which bounds what a forged size allocates
+  let mut out = Vec::with_capacity(size.min(block.len().saturating_mul(255)));
+  let mut i = 0;
+  // Lengths of 15 continue in the following bytes, up to one below 255
+  let length = |nibble: u8, i: &mut usize| -> Result<usize, ChromeCookieError> {
+    let mut length = nibble as usize;
+    if nibble == 15 {
+      loop {
+        let byte = *block.get(*i).ok_or_else(truncated)?;
+        *i += 1;
+        length += byte as usize;
+        if byte != 255 {
+          break;
+        }
+      }
+    }
+    Ok(length)
+  };
+  while i < block.len() {
+    let token = block[i];
+    i += 1;
+    let literals = length(token >> 4, &mut i)?;
+    let literals = block.get(i..i + literals).ok_or_else(truncated)?;
+    out.extend_from_slice(literals);
+    i += literals.len();
+    // The last sequence has literals only
+    if i == block.len() {
+      break;
+    }
+    let offset = block.get(i..i + 2).ok_or_else(truncated)?;
+    let offset = u16::from_le_bytes([offset[0], offset[1]]) as usize;
+    i += 2;
+    if offset == 0 || offset > out.len() {
+      return Err(ChromeCookieError::MozLz4(format!(
+        "match offset {} is out of the output",
+        offset
+      )));
+    }
+    let start = out.len() - offset;
+    // Matches may overlap the bytes they produce, so they are copied one by one
+    for k in 0..length(token & 15, &mut i)? + 4 {
+      out.push(out[start + k]);
+    }
+  }
+  if out.len() != size {
+    return Err(ChromeCookieError::MozLz4(format!(
+      "{} bytes decompressed, expected {}",
+      out.len(),
+      size
+    )));
+  }
+  Ok(out)
+}
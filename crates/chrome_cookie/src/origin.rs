@@ -0,0 +1,124 @@
+use crate::ChromeCookieError;
+use std::path::Path;
+
+/// The origin attributes Firefox keys cookies with besides their host, so that containers,
+/// first-party isolation and partitioned (CHIPS) cookies each get their own jar.
+/// ref: [gecko](https://searchfox.org/mozilla-central/source/caps/OriginAttributes.cpp)
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OriginAttributes {
+  /// Multi-Account Container of the cookie, 0 outside of any.
+  pub user_context_id: u32,
+  /// Site the cookie is isolated to with `privacy.firstparty.isolate`.
+  pub first_party_domain: String,
+  /// Top-level site of a partitioned cookie, e.g. `(https,example.com)`.
+  pub partition_key: String,
+}
+
+fn percent_decode(text: &str) -> String {
+  let bytes = text.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let hex = bytes
+      .get(i + 1..i + 3)
+      .and_then(|hex| std::str::from_utf8(hex).ok())
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    match (bytes[i], hex) {
+      (b'%', Some(byte)) => {
+        out.push(byte);
+        i += 3;
+      }
+      (byte, _) => {
+        out.push(byte);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&out).into_owned()
+}
+
+impl OriginAttributes {
+  /// Attributes of the `originAttributes` column of `moz_cookies`, a suffix like
+  /// `^userContextId=1&partitionKey=%28https%2Cexample.com%29`, empty for the default jar.
+  pub fn parse(suffix: &str) -> Self {
+    let mut origin = Self::default();
+    for pair in suffix.trim_start_matches('^').split('&') {
+      let Some((key, value)) = pair.split_once('=') else {
+        continue;
+      };
+      let value = percent_decode(value);
+      match key {
+        "userContextId" => origin.user_context_id = value.parse().unwrap_or(0),
+        "firstPartyDomain" => origin.first_party_domain = value,
+        "partitionKey" => origin.partition_key = value,
+        _ => {}
+      }
+    }
+    origin
+  }
+
+  /// Attributes of the `originAttributes` object of a session store cookie.
+  pub fn from_json(value: &serde_json::Value) -> Self {
+    let text = |key: &str| value[key].as_str().unwrap_or_default().to_string();
+    Self {
+      user_context_id: value["userContextId"]
+        .as_u64()
+        .and_then(|id| u32::try_from(id).ok())
+        .unwrap_or(0),
+      first_party_domain: text("firstPartyDomain"),
+      partition_key: text("partitionKey"),
+    }
+  }
+
+  /// Whether this is the jar of ordinary browsing, outside of containers and isolation.
+  pub fn is_default(&self) -> bool {
+    *self == Self::default()
+  }
+}
+
+/// A Multi-Account Container of a Firefox profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+  pub user_context_id: u32,
+  pub name: String,
+}
+
+/// English names of the containers Firefox creates, which only store a localization ID.
+fn builtin_container_name(l10n_id: &str) -> Option<&'static str> {
+  match l10n_id {
+    "user-context-personal" => Some("Personal"),
+    "user-context-work" => Some("Work"),
+    "user-context-banking" => Some("Banking"),
+    "user-context-shopping" => Some("Shopping"),
+    _ => None,
+  }
+}
+
+/// Containers of the profile directory `profile` from its `containers.json`, none if it has
+/// not created the file yet. Internal containers Firefox hides from the user are skipped.
+pub fn containers(profile: &Path) -> Result<Vec<Container>, ChromeCookieError> {
+  let text = match std::fs::read_to_string(profile.join("containers.json")) {
+    Ok(text) => text,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => return Err(e.into()),
+  };
+  let json: serde_json::Value = serde_json::from_str(&text)?;
+  Ok(
+    json["identities"]
+      .as_array()
+      .into_iter()
+      .flatten()
+      .filter(|identity| identity["public"].as_bool().unwrap_or(true))
+      .filter_map(|identity| {
+        let user_context_id = u32::try_from(identity["userContextId"].as_u64()?).ok()?;
+        let name = identity["name"]
+          .as_str()
+          .or_else(|| identity["l10nID"].as_str().and_then(builtin_container_name))?;
+        Some(Container {
+          user_context_id,
+          name: name.to_string(),
+        })
+      })
+      .collect(),
+  )
+}
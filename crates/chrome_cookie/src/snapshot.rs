@@ -0,0 +1,150 @@
+use crate::ChromeCookieError;
+use rusqlite::{Connection, OpenFlags};
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Age after which a snapshot is left over from a process killed before removing it.
+const STALE_SNAPSHOT: Duration = Duration::from_secs(60 * 60);
+
+/// A temporary directory removed with everything in it when dropped.
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.0);
+  }
+}
+
+/// A cookie database opened without taking the locks of the browser that writes it.
+///
+/// Derefs to the [`Connection`] of a snapshot, or of the live file when it cannot be copied.
+pub struct CookieDb {
+  conn: Connection,
+  // Dropped after `conn`, so the snapshot is closed before it is removed
+  snapshot: Option<TempDir>,
+}
+
+impl CookieDb {
+  /// Directory of the snapshot, `None` when the live file is read.
+  pub fn snapshot_dir(&self) -> Option<&Path> {
+    self.snapshot.as_ref().map(|dir| dir.0.as_path())
+  }
+}
+
+impl Deref for CookieDb {
+  type Target = Connection;
+
+  fn deref(&self) -> &Connection {
+    &self.conn
+  }
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `Cookies-wal`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  let mut path = OsString::from(path);
+  path.push(suffix);
+  path.into()
+}
+
+/// Directory snapshots are made in, private to the user: the runtime directory, which is
+/// cleared on logout, or the cache directory.
+fn snapshots_dir() -> PathBuf {
+  dirs::runtime_dir()
+    .or_else(dirs::cache_dir)
+    .unwrap_or_else(std::env::temp_dir)
+    .join("chrome_cookie")
+}
+
+/// Remove the snapshots in `dir` that processes killed before dropping them left behind.
+fn remove_stale(dir: &Path) {
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return;
+  };
+  for entry in entries.flatten() {
+    let stale = entry
+      .metadata()
+      .and_then(|metadata| metadata.modified())
+      .ok()
+      .and_then(|modified| modified.elapsed().ok())
+      .is_some_and(|age| age > STALE_SNAPSHOT);
+    if stale {
+      let _ = std::fs::remove_dir_all(entry.path());
+    }
+  }
+}
+
+/// Create `dir` and its missing parents readable by the user alone, as the copies hold
+/// plaintext cookies of Firefox.
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+  let mut builder = std::fs::DirBuilder::new();
+  builder.recursive(true);
+  #[cfg(unix)]
+  std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+  builder.create(dir)
+}
+
+/// Copy the database at `path` and its `-wal` and `-shm` files into a new private directory,
+/// returning the directory and the path of the copy.
+fn snapshot(path: &Path) -> Result<(TempDir, PathBuf), ChromeCookieError> {
+  let snapshots = snapshots_dir();
+  remove_stale(&snapshots);
+  let dir = snapshots.join(format!(
+    "{}-{:016x}",
+    std::process::id(),
+    rand::random::<u64>()
+  ));
+  create_private_dir(&dir)?;
+  let dir = TempDir(dir);
+  let copy = dir.0.join(path.file_name().unwrap_or("Cookies".as_ref()));
+  std::fs::copy(path, &copy)?;
+  // The write-ahead log holds the cookies the browser has not checkpointed yet
+  for suffix in ["-wal", "-shm"] {
+    match std::fs::copy(with_suffix(path, suffix), with_suffix(&copy, suffix)) {
+      Ok(_) => {}
+      Err(e) if e.kind() == ErrorKind::NotFound => {}
+      Err(e) => return Err(e.into()),
+    }
+  }
+  Ok((dir, copy))
+}
+
+/// `file:` URI of `path` opening it read-only with `immutable=1`, which takes no locks.
+fn immutable_uri(path: &Path) -> String {
+  let mut uri = String::from("file:");
+  for c in path.to_string_lossy().chars() {
+    match c {
+      '%' | '?' | '#' => uri.push_str(&format!("%{:02X}", c as u32)),
+      c => uri.push(c),
+    }
+  }
+  uri.push_str("?immutable=1");
+  uri
+}
+
+/// Open a cookie database the browser may be holding open.
+///
+/// The database is read from a snapshot together with its `-wal` and `-shm` files, so cookies
+/// not yet checkpointed are seen. Snapshots are made in a directory only the user can read. When it cannot be copied, e.g. on a full disk, the live file
+/// is opened with `immutable=1` instead, which misses them.
+pub fn open_cookie_db(path: impl AsRef<Path>) -> Result<CookieDb, ChromeCookieError> {
+  let path = path.as_ref();
+  match snapshot(path) {
+    Ok((dir, copy)) => Ok(CookieDb {
+      conn: Connection::open(copy)?,
+      snapshot: Some(dir),
+    }),
+    Err(ChromeCookieError::IoError(e)) if e.kind() == ErrorKind::NotFound && !path.exists() => {
+      Err(e.into())
+    }
+    Err(_) => Ok(CookieDb {
+      conn: Connection::open_with_flags(
+        immutable_uri(path),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+      )?,
+      snapshot: None,
+    }),
+  }
+}
@@ -1,6 +1,6 @@
 use chrome_cookie::{
   decrypt_chrome_cookie_linux, decrypt_chrome_cookie_macos_legacy, derive_linux_key,
-  encrypt::encrypt_aes128_cbc_pkcs7, encrypt::generate_random_iv,
+  encrypt::encrypt_aes128_cbc_pkcs7,
 };
 
 #[cfg(test)]
@@ -109,7 +109,7 @@ mod tests_mac {
     let salt = b"saltysalt";
     let iterations = 1003;
     let mut key = [0u8; 16];
-    pbkdf2::<Hmac<Sha1>>(password.as_bytes(), salt, iterations, &mut key);
+    pbkdf2::<Hmac<Sha1>>(password.as_bytes(), salt, iterations, &mut key).unwrap();
 
     let iv = [b' '; 16];
     let ciphertext = encrypt_aes128_cbc_pkcs7(&key, &iv, &plaintext);
@@ -125,3 +125,1244 @@ mod tests_mac {
     assert_eq!(decrypted.as_bytes(), &plaintext);
   }
 }
+
+#[cfg(test)]
+mod tests_key_provider {
+  use super::*;
+  use chrome_cookie::{
+    linux_key_providers, resolve_password, ChromeCookieError, FixedPassword, KeyProvider,
+    LinuxKeys, V10_PASSWORD,
+  };
+  use std::cell::Cell;
+
+  /// Stand-in for a keyring, counting the lookups.
+  struct Keyring {
+    password: Option<&'static str>,
+    fails: bool,
+    lookups: Cell<usize>,
+  }
+
+  impl Keyring {
+    fn new(password: Option<&'static str>) -> Box<Self> {
+      Box::new(Keyring {
+        password,
+        fails: false,
+        lookups: Cell::new(0),
+      })
+    }
+  }
+
+  impl KeyProvider for Keyring {
+    fn name(&self) -> &str {
+      "stand-in"
+    }
+
+    fn password(&self) -> Result<Option<String>, ChromeCookieError> {
+      self.lookups.set(self.lookups.get() + 1);
+      if self.fails {
+        return Err(ChromeCookieError::CommandFailed("locked".into()));
+      }
+      Ok(self.password.map(str::to_string))
+    }
+  }
+
+  fn encrypted(prefix: &[u8], password: &str, plaintext: &[u8]) -> Vec<u8> {
+    let mut value = prefix.to_vec();
+    value.extend(encrypt_aes128_cbc_pkcs7(
+      &derive_linux_key(password),
+      &[b' '; 16],
+      plaintext,
+    ));
+    value
+  }
+
+  #[test]
+  fn test_resolve_password_order() {
+    let providers: Vec<Box<dyn KeyProvider>> = vec![
+      Keyring::new(None),
+      Keyring::new(Some("first")),
+      Keyring::new(Some("second")),
+    ];
+    assert_eq!(
+      resolve_password(&providers).unwrap().as_deref(),
+      Some("first")
+    );
+
+    // A failing keyring does not hide the next one
+    let mut failing = Keyring::new(None);
+    failing.fails = true;
+    let providers: Vec<Box<dyn KeyProvider>> = vec![failing, Keyring::new(Some("next"))];
+    assert_eq!(
+      resolve_password(&providers).unwrap().as_deref(),
+      Some("next")
+    );
+
+    let mut failing = Keyring::new(None);
+    failing.fails = true;
+    let providers: Vec<Box<dyn KeyProvider>> = vec![Keyring::new(None), failing];
+    match resolve_password(&providers) {
+      Err(ChromeCookieError::CommandFailed(message)) => assert!(message.starts_with("stand-in")),
+      other => panic!("unexpected result: {:?}", other),
+    }
+    // Every failure is reported, e.g. each lookup program that is not installed
+    let (mut first, mut second) = (Keyring::new(None), Keyring::new(None));
+    first.fails = true;
+    second.fails = true;
+    let providers: Vec<Box<dyn KeyProvider>> = vec![first, second];
+    match resolve_password(&providers) {
+      Err(ChromeCookieError::CommandFailed(message)) => {
+        assert_eq!(
+          message,
+          "stand-in: Command failed: locked; stand-in: Command failed: locked"
+        )
+      }
+      other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(resolve_password(&[Keyring::new(None)]).unwrap(), None);
+  }
+
+  #[test]
+  fn test_explicit_password_comes_first() {
+    let providers = linux_key_providers(Some("from config"));
+    assert_eq!(providers[0].name(), "fixed");
+    assert_eq!(
+      providers[0].password().unwrap().as_deref(),
+      Some("from config")
+    );
+    assert!(providers[1..].iter().all(|p| p.name() != "fixed"));
+    assert!(linux_key_providers(None)
+      .iter()
+      .all(|p| p.name() != "fixed"));
+  }
+
+  #[test]
+  fn test_linux_keys_by_version() {
+    let keys =
+      LinuxKeys::resolve(&[Keyring::new(None), Box::new(FixedPassword("zxqfb".into()))]).unwrap();
+    // v10 cookies always use the fixed password, v11 ones the keyring's
+    let v10 = encrypted(b"v10", V10_PASSWORD, b"z_c0 v10");
+    let v11 = encrypted(b"v11", "zxqfb", b"z_c0 v11");
+    assert_eq!(keys.decrypt(&v10).unwrap().as_deref(), Some("z_c0 v10"));
+    assert_eq!(keys.decrypt(&v11).unwrap().as_deref(), Some("z_c0 v11"));
+    assert_eq!(keys.decrypt(b"plain").unwrap(), None);
+
+    // Without a keyring only v10 cookies decrypt
+    let keys = LinuxKeys::resolve(&[Keyring::new(None)]).unwrap();
+    assert_eq!(keys, LinuxKeys::new(None));
+    assert_eq!(keys.decrypt(&v10).unwrap().as_deref(), Some("z_c0 v10"));
+    assert!(matches!(
+      keys.decrypt(&v11),
+      Err(ChromeCookieError::DecryptionFailed(_))
+    ));
+  }
+}
+
+#[cfg(test)]
+mod tests_host_hash {
+  use super::*;
+  use chrome_cookie::{
+    cookie_db_version, get_cookie_value, get_cookies_for_host, strip_host_hash, ChromeCookieError,
+    Decryptors, Platform,
+  };
+  use hmac::Hmac;
+  use pbkdf2::pbkdf2;
+  use rusqlite::Connection;
+  use sha1::Sha1;
+  use sha2::{Digest, Sha256};
+  use std::path::PathBuf;
+
+  const PASSWORD: &str = "mock_password";
+
+  fn encrypt_macos(plaintext: &[u8]) -> Vec<u8> {
+    encrypt_macos_with(PASSWORD, plaintext)
+  }
+
+  fn encrypt_macos_with(password: &str, plaintext: &[u8]) -> Vec<u8> {
+    let mut key = [0u8; 16];
+    pbkdf2::<Hmac<Sha1>>(password.as_bytes(), b"saltysalt", 1003, &mut key).unwrap();
+    let mut value = b"v10".to_vec();
+    value.extend(encrypt_aes128_cbc_pkcs7(&key, &[b' '; 16], plaintext));
+    value
+  }
+
+  /// Cookie database of the given `meta.version`, values hashed with their host from 24 on.
+  fn cookie_db(name: &str, version: Option<i64>, cookies: &[(&str, &str, &str)]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+      "chrome_cookie_{}_{}.sqlite",
+      name,
+      std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let conn = Connection::open(&path).unwrap();
+    conn
+      .execute_batch("CREATE TABLE cookies (host_key TEXT, name TEXT, encrypted_value BLOB)")
+      .unwrap();
+    if let Some(version) = version {
+      conn
+        .execute_batch("CREATE TABLE meta (key LONGVARCHAR PRIMARY KEY, value LONGVARCHAR)")
+        .unwrap();
+      conn
+        .execute(
+          "INSERT INTO meta VALUES ('version', ?)",
+          [version.to_string()],
+        )
+        .unwrap();
+    }
+    for (host, name, value) in cookies {
+      let mut plaintext = Vec::new();
+      if version.unwrap_or(0) >= 24 {
+        plaintext.extend_from_slice(&Sha256::digest(host.as_bytes()));
+      }
+      plaintext.extend_from_slice(value.as_bytes());
+      conn
+        .execute(
+          "INSERT INTO cookies VALUES (?, ?, ?)",
+          rusqlite::params![host, name, encrypt_macos(&plaintext)],
+        )
+        .unwrap();
+    }
+    path
+  }
+
+  #[test]
+  fn test_strip_host_hash() {
+    let mut plaintext = Sha256::digest(b".zhihu.com").to_vec();
+    plaintext.extend_from_slice(b"token");
+    assert_eq!(
+      strip_host_hash(&plaintext, ".zhihu.com", 24).unwrap(),
+      b"token"
+    );
+    assert_eq!(
+      strip_host_hash(b"token", ".zhihu.com", 23).unwrap(),
+      b"token"
+    );
+    // Hashed with another host, or decrypted with a wrong key
+    assert!(matches!(
+      strip_host_hash(&plaintext, "www.zhihu.com", 24),
+      Err(ChromeCookieError::DecryptionFailed(_))
+    ));
+  }
+
+  #[test]
+  fn test_cookie_db_version() {
+    let path = cookie_db("version", Some(24), &[]);
+    assert_eq!(
+      cookie_db_version(&Connection::open(&path).unwrap()).unwrap(),
+      24
+    );
+    let legacy = cookie_db("no_meta", None, &[]);
+    assert_eq!(
+      cookie_db_version(&Connection::open(&legacy).unwrap()).unwrap(),
+      0
+    );
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(legacy).unwrap();
+  }
+
+  #[test]
+  fn test_values_without_host_hash() {
+    let cookies = [
+      (".zhihu.com", "z_c0", "2|1:0|token"),
+      (".zhihu.com", "d_c0", "device"),
+    ];
+    for (name, version) in [("modern", Some(24)), ("old", Some(23)), ("oldest", None)] {
+      let path = cookie_db(name, version, &cookies);
+      let path = path.to_str().unwrap();
+      let decryptors = Decryptors::new(Platform::MacOs, Some(PASSWORD), None).unwrap();
+      let mut values: Vec<_> = get_cookies_for_host(path, &decryptors, "zhihu.com")
+        .unwrap()
+        .into_iter()
+        .map(|cookie| (cookie.name, cookie.value))
+        .collect();
+      values.sort();
+      assert_eq!(
+        values,
+        vec![
+          ("d_c0".to_string(), "device".to_string()),
+          ("z_c0".to_string(), "2|1:0|token".to_string()),
+        ],
+        "meta version {:?}",
+        version
+      );
+      assert_eq!(
+        get_cookie_value(path, &decryptors, ".zhihu.com", "z_c0")
+          .unwrap()
+          .as_deref(),
+        Some("2|1:0|token")
+      );
+      std::fs::remove_file(path).unwrap();
+    }
+  }
+
+  #[test]
+  fn test_undecryptable_row_is_skipped() {
+    let path = cookie_db("stale", Some(24), &[(".zhihu.com", "z_c0", "2|1:0|token")]);
+    // Encrypted with another password, e.g. by a previous keyring
+    let conn = Connection::open(&path).unwrap();
+    let mut stale = Sha256::digest(b".zhihu.com").to_vec();
+    stale.extend_from_slice(b"old");
+    conn
+      .execute(
+        "INSERT INTO cookies VALUES ('.zhihu.com', 'stale', ?)",
+        [encrypt_macos_with("old password", &stale)],
+      )
+      .unwrap();
+    let path = path.to_str().unwrap();
+    let decryptors = Decryptors::new(Platform::MacOs, Some(PASSWORD), None).unwrap();
+
+    let cookies = get_cookies_for_host(path, &decryptors, "zhihu.com").unwrap();
+    let names: Vec<_> = cookies.iter().map(|cookie| cookie.name.as_str()).collect();
+    assert_eq!(names, ["z_c0"]);
+    assert_eq!(
+      get_cookie_value(path, &decryptors, "zhihu.com", "z_c0")
+        .unwrap()
+        .as_deref(),
+      Some("2|1:0|token")
+    );
+    // The requested cookie itself fails
+    assert!(matches!(
+      get_cookie_value(path, &decryptors, "zhihu.com", "stale"),
+      Err(ChromeCookieError::DecryptionFailed(_))
+    ));
+    std::fs::remove_file(path).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod tests_decryptors {
+  use super::*;
+  use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm,
+  };
+  use chrome_cookie::{
+    decrypt_chrome_cookie, ChromeCookieError, CookieDecryptor, Decryptors, LinuxKeys, MacCbc,
+    Platform, V10_PASSWORD,
+  };
+  use hmac::Hmac;
+  use pbkdf2::pbkdf2;
+  use sha1::Sha1;
+
+  const MASTER_KEY: [u8; 32] = [7; 32];
+
+  fn cbc(prefix: &[u8], key: &[u8; 16], plaintext: &[u8]) -> Vec<u8> {
+    let mut value = prefix.to_vec();
+    value.extend(encrypt_aes128_cbc_pkcs7(key, &[b' '; 16], plaintext));
+    value
+  }
+
+  fn macos_key(password: &str) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    pbkdf2::<Hmac<Sha1>>(password.as_bytes(), b"saltysalt", 1003, &mut key).unwrap();
+    key
+  }
+
+  fn gcm(prefix: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let nonce = [3u8; 12];
+    let cipher = Aes256Gcm::new_from_slice(&MASTER_KEY).unwrap();
+    let mut value = prefix.to_vec();
+    value.extend_from_slice(&nonce);
+    value.extend(cipher.encrypt(&nonce.into(), plaintext).unwrap());
+    value
+  }
+
+  fn decrypt(decryptors: &Decryptors, value: &[u8]) -> Option<String> {
+    decrypt_chrome_cookie(value, decryptors).unwrap()
+  }
+
+  #[test]
+  fn test_v10_depends_on_platform() {
+    let linux = Decryptors::new(Platform::Linux, Some("keyring"), None).unwrap();
+    let macos = Decryptors::new(Platform::MacOs, Some("keychain"), None).unwrap();
+    let windows = Decryptors::new(Platform::Windows, None, Some(&MASTER_KEY)).unwrap();
+
+    let linux_v10 = cbc(b"v10", &derive_linux_key(V10_PASSWORD), b"linux v10");
+    let linux_v11 = cbc(b"v11", &derive_linux_key("keyring"), b"linux v11");
+    let macos_v10 = cbc(b"v10", &macos_key("keychain"), b"macos v10");
+    let windows_v10 = gcm(b"v10", b"windows v10");
+    assert_eq!(decrypt(&linux, &linux_v10).as_deref(), Some("linux v10"));
+    assert_eq!(decrypt(&linux, &linux_v11).as_deref(), Some("linux v11"));
+    assert_eq!(decrypt(&macos, &macos_v10).as_deref(), Some("macos v10"));
+    assert_eq!(
+      decrypt(&windows, &windows_v10).as_deref(),
+      Some("windows v10")
+    );
+    assert_eq!(
+      decrypt(&windows, &gcm(b"v20", b"app bound")).as_deref(),
+      Some("app bound")
+    );
+
+    // The same prefix means another scheme elsewhere
+    assert!(decrypt_chrome_cookie(&macos_v10, &linux).is_err());
+    assert!(decrypt_chrome_cookie(&windows_v10, &macos).is_err());
+    assert!(matches!(
+      decrypt_chrome_cookie(&linux_v11, &macos),
+      Err(ChromeCookieError::DecryptionFailed(_))
+    ));
+    assert_eq!(decrypt(&linux, b"plain value"), None);
+  }
+
+  #[test]
+  fn test_without_keyring_password() {
+    // Linux browsers without a keyring only have v10 cookies
+    let linux = Decryptors::new(Platform::Linux, None, None).unwrap();
+    let v10 = cbc(b"v10", &derive_linux_key(V10_PASSWORD), b"linux v10");
+    assert_eq!(decrypt(&linux, &v10).as_deref(), Some("linux v10"));
+    match decrypt_chrome_cookie(&cbc(b"v11", &derive_linux_key("keyring"), b"v11"), &linux) {
+      Err(ChromeCookieError::DecryptionFailed(message)) => {
+        assert!(message.contains("keyring password"), "{}", message)
+      }
+      other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(matches!(
+      Decryptors::new(Platform::MacOs, None, None),
+      Err(ChromeCookieError::DecryptionFailed(_))
+    ));
+  }
+
+  #[test]
+  fn test_gcm_needs_master_key() {
+    let windows = Decryptors::new(Platform::Windows, None, None).unwrap();
+    match decrypt_chrome_cookie(&gcm(b"v10", b"value"), &windows) {
+      Err(ChromeCookieError::DecryptionFailed(message)) => assert!(message.contains("master key")),
+      other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(Decryptors::new(Platform::Windows, None, Some(&[0; 5])).is_err());
+    assert!(Decryptors::new(Platform::Windows, None, Some(&[0; 16]))
+      .unwrap()
+      .decrypt(&gcm(b"v10", b"value"))
+      .is_err());
+  }
+
+  #[test]
+  fn test_select_by_prefix() {
+    let linux = Decryptors::linux(LinuxKeys::new(None));
+    assert_eq!(linux.platform(), Platform::Linux);
+    assert!(linux.select(b"v10data").unwrap().is_some());
+    assert!(linux.select(b"v1").unwrap().is_none());
+    assert!(linux.select(b"plain").unwrap().is_none());
+    assert!(linux.select(b"v20data").is_err());
+    let value = cbc(b"v10", &macos_key("pw"), b"direct");
+    assert_eq!(MacCbc::new("pw").decrypt(&value).unwrap(), b"direct");
+    assert_eq!(Platform::parse("macos"), Some(Platform::MacOs));
+    assert_eq!(Platform::parse("beos"), None);
+  }
+}
+
+#[cfg(test)]
+mod tests_browsers {
+  use chrome_cookie::{browser, ChromeCookieError, Platform, BROWSERS};
+  use std::path::{Path, PathBuf};
+
+  #[test]
+  fn test_registry() {
+    for id in ["chrome", "chromium", "brave", "edge", "vivaldi", "opera"] {
+      assert_eq!(browser(id).unwrap().id, id);
+    }
+    assert_eq!(browser("brave").unwrap().safe_storage, "Brave Safe Storage");
+    match browser("netscape") {
+      Err(e @ ChromeCookieError::UnknownBrowser(_)) => {
+        assert!(e.to_string().contains("chrome, chromium, brave"), "{}", e)
+      }
+      other => panic!("unexpected result: {:?}", other),
+    }
+    let mut ids: Vec<_> = BROWSERS.iter().map(|browser| browser.id).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), BROWSERS.len());
+  }
+
+  #[test]
+  fn test_user_data_dirs() {
+    let home = Path::new("/home/me");
+    assert_eq!(
+      browser("chrome")
+        .unwrap()
+        .user_data_dirs(home, Platform::MacOs),
+      vec![PathBuf::from(
+        "/home/me/Library/Application Support/Google/Chrome"
+      )]
+    );
+    let chromium = browser("chromium")
+      .unwrap()
+      .user_data_dirs(home, Platform::Linux);
+    assert!(chromium[0].ends_with("chromium"));
+    assert_eq!(
+      chromium[1..],
+      [
+        PathBuf::from("/home/me/.var/app/org.chromium.Chromium/config/chromium"),
+        PathBuf::from("/home/me/snap/chromium/common/chromium"),
+      ]
+    );
+    assert!(browser("brave")
+      .unwrap()
+      .user_data_dirs(home, Platform::Linux)[0]
+      .ends_with("BraveSoftware/Brave-Browser"));
+  }
+
+  #[test]
+  fn test_key_providers() {
+    let names = |providers: Vec<Box<dyn chrome_cookie::KeyProvider>>| {
+      providers
+        .iter()
+        .map(|p| p.name().to_string())
+        .collect::<Vec<_>>()
+    };
+    let vivaldi = browser("vivaldi").unwrap();
+    assert_eq!(
+      names(vivaldi.key_providers(Some("pw"))),
+      ["fixed", "secret-service", "kwallet"]
+    );
+    assert_eq!(
+      names(vivaldi.key_providers(None)),
+      ["secret-service", "kwallet"]
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests_profiles {
+  use chrome_cookie::{profile_cookie_path, profiles};
+  use std::fs;
+  use std::path::PathBuf;
+
+  fn user_data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "chrome_cookie_profiles_{}_{}",
+      name,
+      std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn test_profiles_from_local_state() {
+    let dir = user_data_dir("local_state");
+    fs::write(
+      dir.join("Local State"),
+      r#"{"profile": {"last_used": "Profile 2", "info_cache": {
+        "Default": {"name": "Person 1"},
+        "Profile 10": {"name": "Old"},
+        "Profile 2": {"name": "Zhihu"},
+        "Profile 3": {"name": "Work"}
+      }}}"#,
+    )
+    .unwrap();
+    // Recent versions keep cookies under `Network`
+    fs::create_dir_all(dir.join("Profile 2/Network")).unwrap();
+    fs::write(dir.join("Profile 2/Network/Cookies"), "").unwrap();
+    fs::create_dir_all(dir.join("Default")).unwrap();
+    fs::write(dir.join("Default/Cookies"), "").unwrap();
+    fs::create_dir_all(dir.join("Profile 3")).unwrap();
+
+    let found = profiles(&dir).unwrap();
+    let dirs: Vec<_> = found.iter().map(|p| p.dir.as_str()).collect();
+    assert_eq!(dirs, ["Profile 2", "Default", "Profile 3", "Profile 10"]);
+    assert_eq!(found[0].name, "Zhihu");
+    assert!(found[0].last_used && !found[1].last_used);
+    assert_eq!(
+      found[0].cookie_path,
+      Some(dir.join("Profile 2/Network/Cookies"))
+    );
+    assert_eq!(found[1].cookie_path, Some(dir.join("Default/Cookies")));
+    assert_eq!(found[2].cookie_path, None);
+    assert_eq!(found[2].path, dir.join("Profile 3"));
+
+    // `Network/Cookies` wins over a stale `Cookies` left by an older version
+    fs::create_dir_all(dir.join("Default/Network")).unwrap();
+    fs::write(dir.join("Default/Network/Cookies"), "").unwrap();
+    assert_eq!(
+      profile_cookie_path(&dir.join("Default")),
+      Some(dir.join("Default/Network/Cookies"))
+    );
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_profiles_without_local_state() {
+    let dir = user_data_dir("fresh");
+    assert!(profiles(&dir).unwrap().is_empty());
+    fs::create_dir_all(dir.join("Default")).unwrap();
+    let found = profiles(&dir).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+      (found[0].dir.as_str(), found[0].last_used),
+      ("Default", true)
+    );
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod tests_snapshot {
+  use super::*;
+  use chrome_cookie::{get_cookies_for_host, open_cookie_db, Decryptors, LinuxKeys};
+  use rusqlite::Connection;
+  use std::fs;
+
+  #[test]
+  fn test_read_while_browser_holds_lock() {
+    let dir = std::env::temp_dir().join(format!("chrome_cookie_snapshot_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("Cookies");
+
+    // Like Chrome: WAL journal, exclusive locking, changes left in the WAL
+    let browser = Connection::open(&path).unwrap();
+    browser
+      .execute_batch(
+        "PRAGMA locking_mode = EXCLUSIVE;
+         PRAGMA journal_mode = WAL;
+         PRAGMA wal_autocheckpoint = 0;
+         CREATE TABLE cookies (host_key TEXT, name TEXT, encrypted_value BLOB);",
+      )
+      .unwrap();
+    let mut value = b"v10".to_vec();
+    value.extend(encrypt_aes128_cbc_pkcs7(
+      &derive_linux_key("peanuts"),
+      &[b' '; 16],
+      b"2|1:0|token",
+    ));
+    browser
+      .execute(
+        "INSERT INTO cookies VALUES ('.zhihu.com', 'z_c0', ?)",
+        [value],
+      )
+      .unwrap();
+    assert!(dir.join("Cookies-wal").is_file());
+    let reader = Connection::open(&path).unwrap();
+    reader.busy_timeout(std::time::Duration::ZERO).unwrap();
+    assert!(reader
+      .query_row("SELECT count(*) FROM cookies", [], |row| row
+        .get::<_, i64>(0))
+      .is_err());
+
+    let decryptors = Decryptors::linux(LinuxKeys::new(None));
+    assert_eq!(
+      get_cookies_for_host(path.to_str().unwrap(), &decryptors, "zhihu.com").unwrap()[0].value,
+      "2|1:0|token"
+    );
+    assert!(open_cookie_db(dir.join("Missing")).is_err());
+    drop(browser);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_snapshot_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("chrome_cookie_private_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("cookies.sqlite");
+    Connection::open(&path)
+      .unwrap()
+      .execute_batch("CREATE TABLE moz_cookies (name TEXT, value TEXT, host TEXT)")
+      .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+    let db = open_cookie_db(&path).unwrap();
+    let snapshot = db.snapshot_dir().unwrap().to_path_buf();
+    let mode = fs::metadata(&snapshot).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700, "{:o}", mode);
+    drop(db);
+    assert!(!snapshot.exists());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod tests_firefox {
+  use chrome_cookie::{
+    firefox_dirs, firefox_profiles, get_firefox_cookie_value, get_firefox_cookies_for_host,
+    OriginAttributes, Platform,
+  };
+  use rusqlite::Connection;
+  use std::fs;
+  use std::path::{Path, PathBuf};
+
+  fn firefox_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "chrome_cookie_firefox_{}_{}",
+      name,
+      std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn test_firefox_dirs() {
+    let home = Path::new("/home/user");
+    assert_eq!(
+      firefox_dirs(home, Platform::Linux),
+      [
+        home.join(".mozilla/firefox"),
+        home.join(".var/app/org.mozilla.firefox/.mozilla/firefox"),
+        home.join("snap/firefox/common/.mozilla/firefox"),
+      ]
+    );
+    assert_eq!(
+      firefox_dirs(home, Platform::MacOs),
+      [home.join("Library/Application Support/Firefox")]
+    );
+  }
+
+  #[test]
+  fn test_firefox_profiles() {
+    let dir = firefox_dir("profiles");
+    let custom = dir.join("custom");
+    fs::write(
+      dir.join("profiles.ini"),
+      format!(
+        "[General]
+StartWithLastProfile=1
+
+; Old default, before per-install profiles
+[Profile0]
+Name=default
+IsRelative=1
+Path=Profiles/abcd.default
+Default=1
+
+[Profile1]
+Name=work
+IsRelative=0
+Path={}
+
+[Profile2]
+Name=dev-edition-default
+IsRelative=1
+Path=Profiles/efgh.dev-edition-default
+
+[Profile3]
+Name=default-release
+IsRelative=1
+Path=Profiles/ijkl.default-release
+
+[Install4F96D1932A9F858E]
+Default=Profiles/ijkl.default-release
+Locked=1
+",
+        custom.display()
+      ),
+    )
+    .unwrap();
+    fs::write(
+      dir.join("installs.ini"),
+      "[4F96D1932A9F858E]\nDefault=Profiles/ijkl.default-release\nLocked=1\n\n\
+       [46F1D8B0A0C49B2D]\nDefault=Profiles/efgh.dev-edition-default\n",
+    )
+    .unwrap();
+    fs::create_dir_all(dir.join("Profiles/ijkl.default-release")).unwrap();
+    fs::write(dir.join("Profiles/ijkl.default-release/cookies.sqlite"), "").unwrap();
+    fs::create_dir_all(&custom).unwrap();
+    fs::write(custom.join("cookies.sqlite"), "").unwrap();
+
+    let found = firefox_profiles(&dir).unwrap();
+    let names: Vec<_> = found.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(
+      names,
+      ["default-release", "dev-edition-default", "default", "work"]
+    );
+    let defaults: Vec<_> = found.iter().map(|p| p.default).collect();
+    assert_eq!(defaults, [true, true, true, false]);
+    assert_eq!(
+      found[0].cookie_path,
+      Some(dir.join("Profiles/ijkl.default-release/cookies.sqlite"))
+    );
+    assert_eq!(found[1].cookie_path, None);
+    assert_eq!(found[3].path, custom);
+    assert_eq!(found[3].cookie_path, Some(custom.join("cookies.sqlite")));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_firefox_cookies() {
+    let dir = firefox_dir("cookies");
+    let path = dir.join("cookies.sqlite");
+    let conn = Connection::open(&path).unwrap();
+    conn
+      .execute_batch(
+        "CREATE TABLE moz_cookies (
+           id INTEGER PRIMARY KEY, name TEXT, value TEXT, host TEXT, lastAccessed INTEGER,
+           originAttributes TEXT NOT NULL DEFAULT ''
+         );
+         INSERT INTO moz_cookies (name, value, host, lastAccessed) VALUES
+           ('z_c0', '2|1:0|token', '.zhihu.com', 1700000000000000),
+           ('d_c0', 'device', '.zhihu.com', 1700000000000000),
+           ('SID', 'other', '.example.com', 1700000000000000);",
+      )
+      .unwrap();
+    drop(conn);
+    let path = path.to_str().unwrap();
+
+    let mut cookies: Vec<_> =
+      get_firefox_cookies_for_host(path, "zhihu.com", &OriginAttributes::default())
+        .unwrap()
+        .into_iter()
+        .map(|cookie| (cookie.name, cookie.value))
+        .collect();
+    cookies.sort();
+    assert_eq!(
+      cookies,
+      vec![
+        ("d_c0".to_string(), "device".to_string()),
+        ("z_c0".to_string(), "2|1:0|token".to_string()),
+      ]
+    );
+    assert_eq!(
+      get_firefox_cookie_value(path, ".zhihu.com", "z_c0", &OriginAttributes::default())
+        .unwrap()
+        .as_deref(),
+      Some("2|1:0|token")
+    );
+    assert_eq!(
+      get_firefox_cookie_value(path, ".zhihu.com", "SID", &OriginAttributes::default()).unwrap(),
+      None
+    );
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod tests_session_store {
+  use chrome_cookie::{
+    decompress_mozlz4, get_firefox_cookie_value, get_firefox_cookies_for_host, session_cookies,
+    ChromeCookieError, OriginAttributes, MOZLZ4_MAGIC,
+  };
+  use rusqlite::Connection;
+  use std::fs;
+
+  /// mozLz4 file of `data` as a single run of literals.
+  fn mozlz4(data: &[u8]) -> Vec<u8> {
+    let mut out = MOZLZ4_MAGIC.to_vec();
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    if data.len() < 15 {
+      out.push((data.len() as u8) << 4);
+    } else {
+      out.push(0xf0);
+      let mut rest = data.len() - 15;
+      while rest >= 255 {
+        out.push(255);
+        rest -= 255;
+      }
+      out.push(rest as u8);
+    }
+    out.extend_from_slice(data);
+    out
+  }
+
+  #[test]
+  fn test_decompress_mozlz4() {
+    let text = vec![b'x'; 300];
+    assert_eq!(decompress_mozlz4(&mozlz4(&text)).unwrap(), text);
+    // "abc", then a match of 9 bytes 3 back overlapping its own output, then "!"
+    let mut data = MOZLZ4_MAGIC.to_vec();
+    data.extend_from_slice(&13u32.to_le_bytes());
+    data.extend_from_slice(&[0x35, b'a', b'b', b'c', 3, 0, 0x10, b'!']);
+    assert_eq!(decompress_mozlz4(&data).unwrap(), b"abcabcabcabc!");
+
+    assert!(matches!(
+      decompress_mozlz4(b"notlz4\0\0\0\0\0\0"),
+      Err(ChromeCookieError::MozLz4(_))
+    ));
+    // Match before the start of the output
+    let mut data = MOZLZ4_MAGIC.to_vec();
+    data.extend_from_slice(&8u32.to_le_bytes());
+    data.extend_from_slice(&[0x10, b'a', 5, 0]);
+    assert!(matches!(
+      decompress_mozlz4(&data),
+      Err(ChromeCookieError::MozLz4(_))
+    ));
+    let truncated = mozlz4(&text);
+    assert!(decompress_mozlz4(&truncated[..truncated.len() - 1]).is_err());
+  }
+
+  #[test]
+  fn test_session_cookies_merged() {
+    let dir = std::env::temp_dir().join(format!("chrome_cookie_session_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sessionstore-backups")).unwrap();
+    let path = dir.join("cookies.sqlite");
+    Connection::open(&path)
+      .unwrap()
+      .execute_batch(
+        "CREATE TABLE moz_cookies (
+           id INTEGER PRIMARY KEY, name TEXT, value TEXT, host TEXT, lastAccessed INTEGER,
+           originAttributes TEXT NOT NULL DEFAULT ''
+         );
+         INSERT INTO moz_cookies (name, value, host, lastAccessed) VALUES
+           ('d_c0', 'device', '.zhihu.com', 1000000),
+           ('z_c0', 'logged-out', '.zhihu.com', 1000000),
+           ('q_c1', 'future', '.zhihu.com', 9000000000000000000);",
+      )
+      .unwrap();
+    let session = r#"{
+      "windows": [{"cookies": [{"host": ".zhihu.com", "name": "old_window", "value": "1", "path": "/"}]}],
+      "cookies": [
+        {"host": ".zhihu.com", "name": "z_c0", "value": "2|1:0|token", "path": "/"},
+        {"host": ".zhihu.com", "name": "q_c1", "value": "stale", "path": "/"},
+        {"host": ".example.com", "name": "SID", "value": "other", "path": "/"}
+      ]
+    }"#;
+    let store = dir.join("sessionstore-backups/recovery.jsonlz4");
+    fs::write(&store, mozlz4(session.as_bytes())).unwrap();
+
+    let cookies = session_cookies(&store).unwrap();
+    let names: Vec<_> = cookies.iter().map(|c| c.cookie.name.as_str()).collect();
+    assert_eq!(names, ["z_c0", "q_c1", "SID", "old_window"]);
+
+    let path = path.to_str().unwrap();
+    // Written after login, the session store wins over an older database row
+    assert_eq!(
+      get_firefox_cookie_value(path, ".zhihu.com", "z_c0", &OriginAttributes::default())
+        .unwrap()
+        .as_deref(),
+      Some("2|1:0|token")
+    );
+    let mut cookies: Vec<_> =
+      get_firefox_cookies_for_host(path, "www.zhihu.com", &OriginAttributes::default())
+        .unwrap()
+        .into_iter()
+        .map(|cookie| (cookie.name, cookie.value))
+        .collect();
+    cookies.sort();
+    assert_eq!(
+      cookies,
+      [
+        ("d_c0", "device"),
+        ("old_window", "1"),
+        ("q_c1", "future"),
+        ("z_c0", "2|1:0|token"),
+      ]
+      .map(|(name, value)| (name.to_string(), value.to_string()))
+    );
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod tests_containers {
+  use chrome_cookie::{
+    containers, firefox_sessions, get_firefox_cookie_value, Container, CookieFilter,
+    OriginAttributes,
+  };
+  use rusqlite::Connection;
+  use std::fs;
+
+  #[test]
+  fn test_parse_origin_attributes() {
+    assert!(OriginAttributes::parse("").is_default());
+    assert_eq!(
+      OriginAttributes::parse(
+        "^firstPartyDomain=zhihu.com&partitionKey=%28https%2Cexample.com%29&userContextId=2"
+      ),
+      OriginAttributes {
+        user_context_id: 2,
+        first_party_domain: "zhihu.com".to_string(),
+        partition_key: "(https,example.com)".to_string(),
+      }
+    );
+    // Attributes cookies do not depend on are ignored
+    assert!(OriginAttributes::parse("^privateBrowsingId=1").is_default());
+    assert_eq!(
+      OriginAttributes::from_json(&serde_json::json!({"userContextId": 3, "firstPartyDomain": ""})),
+      OriginAttributes {
+        user_context_id: 3,
+        ..Default::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_firefox_sessions() {
+    let dir = std::env::temp_dir().join(format!("chrome_cookie_containers_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    assert!(containers(&dir).unwrap().is_empty());
+    fs::write(
+      dir.join("containers.json"),
+      r#"{"version": 5, "identities": [
+        {"userContextId": 1, "public": true, "l10nID": "user-context-personal"},
+        {"userContextId": 2, "public": true, "name": "Zhihu"},
+        {"userContextId": 4294967295, "public": false, "name": "userContextIdInternal.thumbnail"}
+      ]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+      containers(&dir).unwrap(),
+      [
+        Container {
+          user_context_id: 1,
+          name: "Personal".to_string()
+        },
+        Container {
+          user_context_id: 2,
+          name: "Zhihu".to_string()
+        },
+      ]
+    );
+
+    let path = dir.join("cookies.sqlite");
+    Connection::open(&path)
+      .unwrap()
+      .execute_batch(
+        "CREATE TABLE moz_cookies (
+           id INTEGER PRIMARY KEY, name TEXT, value TEXT, host TEXT, lastAccessed INTEGER,
+           originAttributes TEXT NOT NULL DEFAULT ''
+         );
+         INSERT INTO moz_cookies (name, value, host, originAttributes) VALUES
+           ('z_c0', 'work', '.zhihu.com', '^userContextId=2'),
+           ('d_c0', 'work-device', '.zhihu.com', '^userContextId=2'),
+           ('d_c0', 'device', '.zhihu.com', ''),
+           ('z_c0', 'embedded', '.zhihu.com', '^partitionKey=%28https%2Cexample.com%29'),
+           ('z_c0', 'unnamed', '.zhihu.com', '^userContextId=7');",
+      )
+      .unwrap();
+    let path = path.to_str().unwrap();
+
+    let sessions = firefox_sessions(path, &CookieFilter::host("zhihu.com")).unwrap();
+    let summary: Vec<_> = sessions
+      .iter()
+      .map(|s| {
+        let z_c0 = s.cookies.iter().find(|cookie| cookie.name == "z_c0");
+        (
+          s.origin.user_context_id,
+          s.container.as_deref(),
+          z_c0.map(|cookie| cookie.value.as_str()),
+        )
+      })
+      .collect();
+    assert_eq!(
+      summary,
+      [
+        (0, None, None),
+        (0, None, Some("embedded")),
+        (2, Some("Zhihu"), Some("work")),
+        (7, None, Some("unnamed")),
+      ]
+    );
+    assert!(sessions[0].origin.is_default());
+    assert_eq!(sessions[1].origin.partition_key, "(https,example.com)");
+
+    // Cookies of other jars are not mixed into the default one
+    assert_eq!(
+      get_firefox_cookie_value(path, ".zhihu.com", "z_c0", &OriginAttributes::default()).unwrap(),
+      None
+    );
+    let zhihu = OriginAttributes {
+      user_context_id: 2,
+      ..Default::default()
+    };
+    assert_eq!(
+      get_firefox_cookie_value(path, ".zhihu.com", "d_c0", &zhihu)
+        .unwrap()
+        .as_deref(),
+      Some("work-device")
+    );
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod tests_cookie_matching {
+  use chrome_cookie::{
+    domain_match, get_cookie_value, get_cookies_matching, get_firefox_cookies_for_host, path_match,
+    Cookie, CookieFilter, Decryptors, OriginAttributes, Platform, SameSite,
+  };
+  use rusqlite::Connection;
+
+  fn cookie(host: &str, name: &str, path: &str, expires: Option<i64>) -> Cookie {
+    Cookie {
+      host: host.to_string(),
+      name: name.to_string(),
+      value: String::new(),
+      path: path.to_string(),
+      expires,
+      secure: false,
+      http_only: false,
+      same_site: SameSite::Unspecified,
+      partition_key: None,
+    }
+  }
+
+  #[test]
+  fn test_domain_match() {
+    assert!(domain_match(".zhihu.com", "zhihu.com"));
+    assert!(domain_match(".zhihu.com", "www.zhihu.com"));
+    assert!(domain_match(".zhihu.com", ".zhihu.com"));
+    assert!(domain_match(".ZHIHU.com", "WWW.zhihu.com"));
+    assert!(domain_match("www.zhihu.com", "www.zhihu.com"));
+    // Host-only cookies are not sent to subdomains
+    assert!(!domain_match("zhihu.com", "www.zhihu.com"));
+    assert!(!domain_match(".zhihu.com", "notzhihu.com"));
+    assert!(!domain_match(".zhihu.com", "zhihu.com.evil"));
+    assert!(!domain_match("www.zhihu.com", "zhihu.com"));
+    assert!(domain_match("127.0.0.1", "127.0.0.1"));
+    assert!(!domain_match(".0.0.1", "127.0.0.1"));
+  }
+
+  #[test]
+  fn test_path_match() {
+    assert!(path_match("/", "/"));
+    assert!(path_match("/", "/question/1"));
+    assert!(path_match("/api", "/api"));
+    assert!(path_match("/api", "/api/v4"));
+    assert!(path_match("/api/", "/api/v4"));
+    assert!(!path_match("/api", "/apix"));
+    assert!(!path_match("/api/v4", "/api"));
+  }
+
+  #[test]
+  fn test_filter() {
+    let now = 1_700_000_000;
+    let filter = CookieFilter::host("www.zhihu.com");
+    assert!(filter.matches(&cookie(".zhihu.com", "z_c0", "/", None), now));
+    assert!(filter.matches(&cookie(".zhihu.com", "z_c0", "/", Some(now + 1)), now));
+    assert!(!filter.matches(&cookie(".zhihu.com", "z_c0", "/", Some(now)), now));
+    assert!(!filter.matches(&cookie("zhuanlan.zhihu.com", "z_c0", "/", None), now));
+    let filter = CookieFilter {
+      include_expired: true,
+      ..filter
+    };
+    assert!(filter.matches(&cookie(".zhihu.com", "z_c0", "/", Some(now - 1)), now));
+    let filter = CookieFilter {
+      path: Some("/api/v4".to_string()),
+      name: Some("z_c0".to_string()),
+      ..CookieFilter::default()
+    };
+    assert!(filter.matches(&cookie("example.com", "z_c0", "/api", None), now));
+    assert!(!filter.matches(&cookie("example.com", "z_c0", "/question", None), now));
+    assert!(!filter.matches(&cookie("example.com", "d_c0", "/api", None), now));
+  }
+
+  #[test]
+  fn test_chrome_cookie_records() {
+    let path = std::env::temp_dir().join(format!(
+      "chrome_cookie_records_{}.sqlite",
+      std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    // Chrome times are microseconds since 1601, 13_000_000_000_000_000 is in 2012
+    Connection::open(&path)
+      .unwrap()
+      .execute_batch(
+        "CREATE TABLE cookies (
+           host_key TEXT, name TEXT, value TEXT, encrypted_value BLOB, path TEXT,
+           expires_utc INTEGER, is_secure INTEGER, is_httponly INTEGER, samesite INTEGER,
+           top_frame_site_key TEXT
+         );
+         INSERT INTO cookies VALUES
+           ('.zhihu.com', 'z_c0', 'domain', x'', '/', 0, 1, 1, 0, ''),
+           ('www.zhihu.com', 'z_c0', 'host', x'', '/', 0, 1, 0, 1, ''),
+           ('www.zhihu.com', '_xsrf', 'api', x'', '/api', 0, 0, 0, 2, ''),
+           ('.zhihu.com', 'q_c1', 'expired', x'', '/', 13000000000000000, 0, 0, -1, ''),
+           ('.zhihu.com', 'd_c0', 'embedded', x'', '/', 0, 1, 0, 0, 'https://example.com'),
+           ('zhuanlan.zhihu.com', 'z_c0', 'sibling', x'', '/', 0, 0, 0, -1, ''),
+           ('.notzhihu.com', 'z_c0', 'other', x'', '/', 0, 0, 0, -1, '');",
+      )
+      .unwrap();
+    let path = path.to_str().unwrap();
+    let decryptors = Decryptors::new(Platform::Linux, None, None).unwrap();
+
+    let cookies = get_cookies_matching(
+      path,
+      &decryptors,
+      &CookieFilter {
+        path: Some("/api/v4".to_string()),
+        ..CookieFilter::host("www.zhihu.com")
+      },
+    )
+    .unwrap();
+    let values: Vec<_> = cookies.iter().map(|c| c.value.as_str()).collect();
+    // Sent in order, longer paths first, then host-only cookies before domain cookies
+    assert_eq!(values, ["api", "host", "domain", "embedded"]);
+    assert_eq!(cookies[2].host, ".zhihu.com");
+    assert!(cookies[2].secure && cookies[2].http_only);
+    assert_eq!(cookies[0].same_site, SameSite::Strict);
+    assert_eq!(cookies[1].same_site, SameSite::Lax);
+    assert_eq!(cookies[2].same_site, SameSite::None);
+    assert_eq!(cookies[2].expires, None);
+    assert_eq!(
+      cookies[3].partition_key.as_deref(),
+      Some("https://example.com")
+    );
+
+    let expired = get_cookies_matching(
+      path,
+      &decryptors,
+      &CookieFilter {
+        name: Some("q_c1".to_string()),
+        include_expired: true,
+        ..CookieFilter::host("zhihu.com")
+      },
+    )
+    .unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].expires, Some(1_355_526_400));
+    assert_eq!(expired[0].same_site, SameSite::Unspecified);
+    assert_eq!(
+      get_cookie_value(path, &decryptors, "zhihu.com", "q_c1").unwrap(),
+      None
+    );
+    // `www.zhihu.com` overrides the domain cookie of the same name
+    assert_eq!(
+      get_cookie_value(path, &decryptors, "www.zhihu.com", "z_c0")
+        .unwrap()
+        .as_deref(),
+      Some("host")
+    );
+    assert_eq!(
+      get_cookie_value(path, &decryptors, "zhihu.com", "z_c0")
+        .unwrap()
+        .as_deref(),
+      Some("domain")
+    );
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn test_firefox_cookie_records() {
+    let path = std::env::temp_dir().join(format!(
+      "chrome_cookie_firefox_records_{}.sqlite",
+      std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    // Expiry is in seconds, or milliseconds in Firefox 128 onwards
+    Connection::open(&path)
+      .unwrap()
+      .execute_batch(
+        "CREATE TABLE moz_cookies (
+           id INTEGER PRIMARY KEY, originAttributes TEXT NOT NULL DEFAULT '', name TEXT,
+           value TEXT, host TEXT, path TEXT, expiry INTEGER, lastAccessed INTEGER,
+           isSecure INTEGER, isHttpOnly INTEGER, sameSite INTEGER
+         );
+         INSERT INTO moz_cookies
+           (name, value, host, path, expiry, lastAccessed, isSecure, isHttpOnly, sameSite)
+         VALUES
+           ('z_c0', 'token', '.zhihu.com', '/', 4102444800000, 0, 1, 1, 1),
+           ('d_c0', 'device', '.zhihu.com', '/', 4102444800, 0, 0, 0, 0),
+           ('q_c1', 'expired', '.zhihu.com', '/', 1000000000, 0, 0, 0, 0),
+           ('z_c0', 'other', 'zhuanlan.zhihu.com', '/', 4102444800, 0, 0, 0, 0);",
+      )
+      .unwrap();
+    let path = path.to_str().unwrap();
+
+    let cookies =
+      get_firefox_cookies_for_host(path, "www.zhihu.com", &OriginAttributes::default()).unwrap();
+    let mut names: Vec<_> = cookies.iter().map(|c| c.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, ["d_c0", "z_c0"]);
+    let z_c0 = cookies.iter().find(|c| c.name == "z_c0").unwrap();
+    assert_eq!(z_c0.value, "token");
+    assert_eq!(z_c0.expires, Some(4_102_444_800));
+    assert!(z_c0.secure && z_c0.http_only);
+    assert_eq!(z_c0.same_site, SameSite::Lax);
+    std::fs::remove_file(path).unwrap();
+  }
+}
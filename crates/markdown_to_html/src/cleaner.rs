@@ -166,6 +166,9 @@ fn unwrap_p_tag(parent: &Handle, p_node: &Handle, child: &Handle) {
     // Update child's parent reference
     child.parent.set(Some(Rc::downgrade(parent)));
     parent_children[pos] = child.clone();
+    // Detach the child from the dropped `<p>`: `RcDom` nodes drain their descendants on drop,
+    // which would otherwise empty e.g. the text of an unwrapped `<a>` or `<span>`
+    p_node.children.borrow_mut().clear();
   }
 }
 
@@ -178,30 +181,28 @@ fn clean_text_nodes(node: &Handle) {
         *contents_mut = contents_mut.trim_end_matches('\n').into();
       }
     }
-    NodeData::Element { name, .. } => {
-      if name.local.as_ref() == "code" {
-        // Special handling for `<code>`: remove leading and trailing `\n`
-        if let Some(parent_weak) = node.parent.take() {
-          if let Some(parent) = parent_weak.upgrade() {
-            let children = parent.children.borrow();
-            let mut found = false;
-            for sibling in children.iter() {
-              if found {
-                if let NodeData::Text { contents } = &sibling.data {
-                  let mut contents_mut = contents.borrow_mut();
-                  if contents_mut.starts_with('\n') {
-                    *contents_mut = contents_mut.trim_start_matches('\n').into();
-                  }
+    NodeData::Element { name, .. } if name.local.as_ref() == "code" => {
+      // Special handling for `<code>`: remove leading and trailing `\n`
+      if let Some(parent_weak) = node.parent.take() {
+        if let Some(parent) = parent_weak.upgrade() {
+          let children = parent.children.borrow();
+          let mut found = false;
+          for sibling in children.iter() {
+            if found {
+              if let NodeData::Text { contents } = &sibling.data {
+                let mut contents_mut = contents.borrow_mut();
+                if contents_mut.starts_with('\n') {
+                  *contents_mut = contents_mut.trim_start_matches('\n').into();
                 }
-                break;
               }
+              break;
+            }
 
-              if Rc::ptr_eq(sibling, node) {
-                found = true;
-              }
+            if Rc::ptr_eq(sibling, node) {
+              found = true;
             }
-            node.parent.set(Some(parent_weak));
           }
+          node.parent.set(Some(parent_weak));
         }
       }
     }
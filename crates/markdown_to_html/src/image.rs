@@ -0,0 +1,251 @@
+use crate::escape_html;
+use pulldown_cmark::{CowStr, Event, TagEnd};
+
+/// Display width of an image in a Zhihu article (`data-size`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImageSize {
+  Small,
+  #[default]
+  Normal,
+  Large,
+}
+
+impl ImageSize {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "small" => Some(ImageSize::Small),
+      "normal" => Some(ImageSize::Normal),
+      "large" => Some(ImageSize::Large),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      ImageSize::Small => "small",
+      ImageSize::Normal => "normal",
+      ImageSize::Large => "large",
+    }
+  }
+}
+
+/// Watermark applied by Zhihu (`data-watermark`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Watermark {
+  #[default]
+  Watermark,
+  /// Show the original image without watermark.
+  Original,
+}
+
+impl Watermark {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "watermark" | "true" | "yes" => Some(Watermark::Watermark),
+      "original" | "nowatermark" | "false" | "no" => Some(Watermark::Original),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Watermark::Watermark => "watermark",
+      Watermark::Original => "original",
+    }
+  }
+}
+
+/// Document-wide image settings, overridden per image by [`ImageAttrs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageDefaults {
+  pub size: ImageSize,
+  pub watermark: Watermark,
+  /// Use the alt text as caption when the image has no explicit caption.
+  pub caption_from_alt: bool,
+}
+
+impl Default for ImageDefaults {
+  fn default() -> Self {
+    Self {
+      size: ImageSize::default(),
+      watermark: Watermark::default(),
+      caption_from_alt: true,
+    }
+  }
+}
+
+/// Per-image attributes, from the image title or a `{...}` block right after the image.
+///
+/// Both accept the keywords `small`, `normal`, `large`, `watermark` and `nowatermark`, and
+/// `key=value` pairs for `size`, `watermark` and `caption` (values may be double-quoted):
+/// `![alt](a.png "large nowatermark")`, `![alt](a.png){size=small caption="图 1"}`. A title that
+/// is not made of attributes only is used as the caption.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImageAttrs {
+  pub size: Option<ImageSize>,
+  pub watermark: Option<Watermark>,
+  pub caption: Option<String>,
+}
+
+impl ImageAttrs {
+  /// Parse an image title.
+  pub fn from_title(title: &str) -> Self {
+    if title.trim().is_empty() {
+      return Self::default();
+    }
+    let mut warnings = Vec::new();
+    match Self::parse(title, &mut warnings) {
+      Some(attrs) if warnings.is_empty() => attrs,
+      _ => Self {
+        caption: Some(title.to_string()),
+        ..Self::default()
+      },
+    }
+  }
+
+  /// Parse the inside of an attribute block, reporting unknown attributes to `warnings`.
+  pub fn from_block(block: &str, warnings: &mut Vec<String>) -> Self {
+    Self::parse(block, warnings).unwrap_or_default()
+  }
+
+  /// Attributes set here take precedence over those of `other`.
+  pub fn or(self, other: Self) -> Self {
+    Self {
+      size: self.size.or(other.size),
+      watermark: self.watermark.or(other.watermark),
+      caption: self.caption.or(other.caption),
+    }
+  }
+
+  fn parse(input: &str, warnings: &mut Vec<String>) -> Option<Self> {
+    let mut attrs = Self::default();
+    for token in tokenize(input)? {
+      match token {
+        (key, None) => {
+          if let Some(size) = ImageSize::parse(&key) {
+            attrs.size = Some(size);
+          } else if let Some(watermark) = Watermark::parse(&key) {
+            attrs.watermark = Some(watermark);
+          } else {
+            warnings.push(format!("Unknown image attribute `{}`", key));
+          }
+        }
+        (key, Some(value)) => match key.as_str() {
+          "size" => match ImageSize::parse(&value) {
+            Some(size) => attrs.size = Some(size),
+            None => warnings.push(format!(
+              "Unknown image size `{}`, use small, normal or large",
+              value
+            )),
+          },
+          "watermark" => match Watermark::parse(&value) {
+            Some(watermark) => attrs.watermark = Some(watermark),
+            None => warnings.push(format!(
+              "Unknown watermark `{}`, use watermark or original",
+              value
+            )),
+          },
+          "caption" => attrs.caption = Some(value),
+          _ => warnings.push(format!("Unknown image attribute `{}`", key)),
+        },
+      }
+    }
+    Some(attrs)
+  }
+}
+
+/// Split `size=small caption="a b" large` into `(key, value)` tokens, `None` if unbalanced.
+fn tokenize(input: &str) -> Option<Vec<(String, Option<String>)>> {
+  let mut tokens = Vec::new();
+  let mut chars = input.chars().peekable();
+  loop {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    if chars.peek().is_none() {
+      return Some(tokens);
+    }
+    let mut key = String::new();
+    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+      key.push(c);
+    }
+    if chars.next_if_eq(&'=').is_none() {
+      tokens.push((key, None));
+      continue;
+    }
+    let mut value = String::new();
+    if chars.next_if_eq(&'"').is_some() {
+      loop {
+        match chars.next()? {
+          '"' => break,
+          c => value.push(c),
+        }
+      }
+    } else {
+      while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+        value.push(c);
+      }
+    }
+    tokens.push((key, Some(value)));
+  }
+}
+
+/// Take the `{...}` attribute block written right after each image off the following text.
+///
+/// Returns the block contents per image, in document order.
+pub(crate) fn take_attribute_blocks(events: &mut [Event<'_>]) -> Vec<Option<String>> {
+  let mut blocks = Vec::new();
+  for i in 0..events.len() {
+    if !matches!(events[i], Event::End(TagEnd::Image)) {
+      continue;
+    }
+    let texts = events[i + 1..]
+      .iter()
+      .take_while(|event| matches!(event, Event::Text(_)))
+      .count();
+    let following: String = events[i + 1..i + 1 + texts]
+      .iter()
+      .map(|event| match event {
+        Event::Text(text) => text.as_ref(),
+        _ => "",
+      })
+      .collect();
+    let block = following.strip_prefix('{').and_then(|rest| {
+      rest
+        .find('}')
+        .map(|end| (rest[..end].to_string(), &rest[end + 1..]))
+    });
+    match block {
+      Some((block, rest)) => {
+        let rest = rest.to_string();
+        for (n, event) in events[i + 1..i + 1 + texts].iter_mut().enumerate() {
+          *event = Event::Text(if n == 0 {
+            CowStr::from(rest.clone())
+          } else {
+            "".into()
+          });
+        }
+        blocks.push(Some(block));
+      }
+      None => blocks.push(None),
+    }
+  }
+  blocks
+}
+
+/// Render an image with Zhihu's caption, size and watermark attributes.
+pub fn image_html(src: &str, alt: &str, attrs: &ImageAttrs, defaults: &ImageDefaults) -> String {
+  let caption = match &attrs.caption {
+    Some(caption) => caption.as_str(),
+    None if defaults.caption_from_alt => alt,
+    None => "",
+  };
+  let src = escape_html(src);
+  format!(
+    "<img src=\"{}\" alt=\"{}\" data-caption=\"{}\" data-size=\"{}\" data-watermark=\"{}\" data-original-src=\"{}\" data-watermark-src=\"\" data-private-watermark-src=\"\" />",
+    src,
+    escape_html(alt),
+    escape_html(caption),
+    attrs.size.unwrap_or(defaults.size).as_str(),
+    attrs.watermark.unwrap_or(defaults.watermark).as_str(),
+    src
+  )
+}
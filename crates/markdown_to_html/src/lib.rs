@@ -1,12 +1,26 @@
 mod cleaner;
+mod image;
+mod links;
+mod math;
+mod meta;
+mod toc;
 use cleaner::clean_html_structure;
+use image::take_attribute_blocks;
+pub use image::{image_html, ImageAttrs, ImageDefaults, ImageSize, Watermark};
+pub use links::{note_links, LinkResolver, LinkTarget, ZHIHU_ARTICLE_URL};
+pub use math::{EquationImage, MathKind, MathRenderer, MathStyle, ZtextMath};
+use meta::take_front_matter;
+pub use meta::{article_id, front_matter, ArticleMeta};
 use mlua::{Lua, Result};
-use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
+use pulldown_cmark::{
+  html, CodeBlockKind, CowStr, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd,
+};
+use std::collections::HashMap;
+pub use toc::{outline, toc_html, Heading, TocMode};
 
 pub trait EventProcessor {
-  fn process_inline_math(&self, text: &str) -> Event<'static>;
-  fn process_display_math(&self, text: &str) -> Event<'static>;
-  fn process_image_start(&mut self, dest_url: &str) -> Event<'static>;
+  fn process_math(&self, text: &str, kind: MathKind) -> Event<'static>;
+  fn process_image_start(&mut self, dest_url: &str, title: &str) -> Event<'static>;
   fn process_image_alt_text(&mut self, text: &str) -> Event<'static>;
   fn process_image_end(&mut self) -> Event<'static>;
   fn process_code_block_start(&mut self, kind: CodeBlockKind) -> Event<'static>;
@@ -14,12 +28,61 @@ pub trait EventProcessor {
   fn process_soft_break(&self, in_code_block: bool) -> Event<'static>;
   fn process_text(&self, text: &str, in_code_block: bool) -> Event<'static>;
   fn process_table(&self, headers: Vec<&str>, rows: Vec<Vec<&str>>) -> Event<'static>;
+  fn process_link_start(
+    &mut self,
+    link_type: LinkType,
+    dest_url: &str,
+    title: &str,
+  ) -> Event<'static>;
+  fn process_link_end(&mut self) -> Event<'static>;
+  fn process_heading_start(&mut self, level: HeadingLevel) -> Event<'static>;
+}
+
+/// Per-document settings on top of the pulldown-cmark [`Options`].
+#[derive(Default, Clone)]
+pub struct ConvertConfig {
+  /// Note key → Zhihu article id, see [`LinkResolver`].
+  pub links: HashMap<String, String>,
+  pub toc: TocMode,
+  pub math: MathStyle,
+  pub images: ImageDefaults,
+}
+
+/// Result of a conversion: the Zhihu-flavored HTML and anything the writer should know about.
+#[derive(Debug, Default)]
+pub struct Conversion {
+  pub html: String,
+  pub warnings: Vec<String>,
+  pub outline: Vec<Heading>,
+  /// Whether Zhihu should render its own table of contents for the article.
+  pub table_of_contents: bool,
+  /// Settings from the front matter, which is left out of the HTML.
+  pub meta: ArticleMeta,
+}
+
+/// Escape text for use in HTML content and double-quoted attribute values.
+pub(crate) fn escape_html(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      _ => escaped.push(c),
+    }
+  }
+  escaped
 }
 
 #[derive(Default)]
 pub struct ImageState {
   pub url: Option<String>,
   pub alt: Option<String>,
+  pub title: Option<String>,
+  /// `{...}` attribute blocks following each image of the document, see [`ImageAttrs`].
+  pub blocks: Vec<Option<String>>,
+  pub next: usize,
 }
 
 #[derive(Default)]
@@ -39,6 +102,20 @@ pub struct TableState {
   pub collecting: bool,
 }
 
+#[derive(Default)]
+pub struct HeadingState {
+  /// Anchor ids of the document's headings in order, see [`outline`].
+  pub ids: Vec<String>,
+  pub next: usize,
+}
+
+#[derive(Default)]
+pub struct LinkState {
+  /// Set while inside a link to a local note that could not be resolved; the link is then
+  /// rendered as plain text instead of a broken relative path.
+  pub suppressed: bool,
+}
+
 impl TableState {
   fn reset(&mut self) {
     *self = Self::default();
@@ -86,6 +163,18 @@ pub struct MarkdownEventProcessor {
   pub image_state: ImageState,
   pub code_block_state: CodeBlockState,
   pub table_state: TableState,
+  pub link_state: LinkState,
+  pub link_resolver: LinkResolver,
+  pub heading_state: HeadingState,
+  pub math_renderer: Box<dyn MathRenderer>,
+  pub image_defaults: ImageDefaults,
+  pub warnings: Vec<String>,
+}
+
+impl Default for MarkdownEventProcessor {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl MarkdownEventProcessor {
@@ -94,6 +183,21 @@ impl MarkdownEventProcessor {
       image_state: ImageState::default(),
       code_block_state: CodeBlockState::default(),
       table_state: TableState::default(),
+      link_state: LinkState::default(),
+      link_resolver: LinkResolver::default(),
+      heading_state: HeadingState::default(),
+      math_renderer: MathStyle::default().renderer(),
+      image_defaults: ImageDefaults::default(),
+      warnings: Vec::new(),
+    }
+  }
+
+  pub fn with_config(config: &ConvertConfig) -> Self {
+    Self {
+      link_resolver: LinkResolver::new(config.links.clone()),
+      math_renderer: config.math.renderer(),
+      image_defaults: config.images.clone(),
+      ..Self::new()
     }
   }
 
@@ -115,31 +219,14 @@ impl MarkdownEventProcessor {
 }
 
 impl EventProcessor for MarkdownEventProcessor {
-  fn process_inline_math(&self, text: &str) -> Event<'static> {
-    let eq = text.replace(['\n', '\r'], "");
-    Event::Html(
-      format!(
-        "<img eeimg=\"1\" src=\"//www.zhihu.com/equation?tex={}\" alt=\"{}\"/>",
-        eq, eq
-      )
-      .into(),
-    )
-  }
-
-  fn process_display_math(&self, text: &str) -> Event<'static> {
-    let eq = text.replace(['\n', '\r'], "");
-    Event::Html(
-      format!(
-        "<img eeimg=\"1\" src=\"//www.zhihu.com/equation?tex={}\\\\\" alt=\"{}\\\\\"/>",
-        eq, eq
-      )
-      .into(),
-    )
-  }
-
-  fn process_image_start(&mut self, dest_url: &str) -> Event<'static> {
+  fn process_math(&self, text: &str, kind: MathKind) -> Event<'static> {
+    Event::Html(self.math_renderer.render(text, kind).into())
+  }
+
+  fn process_image_start(&mut self, dest_url: &str, title: &str) -> Event<'static> {
     self.image_state.url = Some(dest_url.to_string());
     self.image_state.alt = Some(String::new());
+    self.image_state.title = Some(title.to_string());
     Event::Text("".into())
   }
 
@@ -152,14 +239,21 @@ impl EventProcessor for MarkdownEventProcessor {
 
   fn process_image_end(&mut self) -> Event<'static> {
     let dest_url = self.image_state.url.take().unwrap();
-    let caption = self.image_state.alt.take().unwrap_or_default();
-    Event::Html(
-            format!(
-                "<img src=\"{}\" data-caption=\"{}\" data-size=\"normal\" data-watermark=\"watermark\" data-original-src=\"{}\" data-watermark-src=\"\" data-private-watermark-src=\"\" />",
-                dest_url, caption, dest_url
-            )
-            .into(),
-        )
+    let alt = self.image_state.alt.take().unwrap_or_default();
+    let title = self.image_state.title.take().unwrap_or_default();
+    let block = self
+      .image_state
+      .blocks
+      .get(self.image_state.next)
+      .cloned()
+      .flatten();
+    self.image_state.next += 1;
+    let attrs = match block {
+      Some(block) => ImageAttrs::from_block(&block, &mut self.warnings),
+      None => ImageAttrs::default(),
+    }
+    .or(ImageAttrs::from_title(&title));
+    Event::Html(image_html(&dest_url, &alt, &attrs, &self.image_defaults).into())
   }
 
   fn process_code_block_start(&mut self, kind: CodeBlockKind) -> Event<'static> {
@@ -219,18 +313,86 @@ impl EventProcessor for MarkdownEventProcessor {
     table_html.push_str("</tbody></table>");
     Event::Html(table_html.into())
   }
+
+  fn process_link_start(
+    &mut self,
+    link_type: LinkType,
+    dest_url: &str,
+    title: &str,
+  ) -> Event<'static> {
+    let dest_url = match self.link_resolver.resolve(link_type, dest_url) {
+      LinkTarget::External => dest_url.to_string(),
+      LinkTarget::Article(url) => url,
+      LinkTarget::Unresolved(key) => {
+        self.warnings.push(format!(
+          "Unresolved link to local note `{}`: it has no Zhihu article yet",
+          key
+        ));
+        self.link_state.suppressed = true;
+        return Event::Text("".into());
+      }
+    };
+    let link_type = match link_type {
+      LinkType::WikiLink { .. } => LinkType::Inline,
+      other => other,
+    };
+    Event::Start(Tag::Link {
+      link_type,
+      dest_url: dest_url.into(),
+      title: title.to_string().into(),
+      id: "".into(),
+    })
+  }
+
+  fn process_link_end(&mut self) -> Event<'static> {
+    if std::mem::take(&mut self.link_state.suppressed) {
+      Event::Text("".into())
+    } else {
+      Event::End(TagEnd::Link)
+    }
+  }
+
+  fn process_heading_start(&mut self, level: HeadingLevel) -> Event<'static> {
+    let id = self.heading_state.ids.get(self.heading_state.next).cloned();
+    self.heading_state.next += 1;
+    Event::Start(Tag::Heading {
+      level,
+      id: id.map(CowStr::from),
+      classes: Vec::new(),
+      attrs: Vec::new(),
+    })
+  }
 }
 
 pub fn markdown_to_html(input: &str, options: Options) -> String {
-  let parser = Parser::new_ext(input, options);
-  let mut processor = MarkdownEventProcessor::new();
+  convert(input, options, &ConvertConfig::default()).html
+}
 
-  let parser = parser.map(move |event| match event {
-    Event::InlineMath(text) => processor.process_inline_math(&text),
-    Event::DisplayMath(text) => processor.process_display_math(&text),
-    Event::Start(Tag::Image { dest_url, .. }) => processor.process_image_start(&dest_url),
+/// Convert Markdown to Zhihu-flavored HTML with per-document settings.
+pub fn convert(input: &str, options: Options, config: &ConvertConfig) -> Conversion {
+  let mut events: Vec<Event> = Parser::new_ext(input, options).collect();
+  let outline = outline(input, options);
+  let mut processor = MarkdownEventProcessor::with_config(config);
+  let meta = take_front_matter(&mut events, &mut processor.warnings);
+  processor.heading_state.ids = outline.iter().map(|h| h.id.clone()).collect();
+  processor.image_state.blocks = take_attribute_blocks(&mut events);
+
+  let parser = events.into_iter().map(|event| match event {
+    Event::InlineMath(text) => processor.process_math(&text, MathKind::Inline),
+    Event::DisplayMath(text) => processor.process_math(&text, MathKind::Display),
+    Event::Start(Tag::Image {
+      dest_url, title, ..
+    }) => processor.process_image_start(&dest_url, &title),
     Event::Text(text) if processor.is_collecting_image() => processor.process_image_alt_text(&text),
     Event::End(TagEnd::Image) => processor.process_image_end(),
+    Event::Start(Tag::Link {
+      link_type,
+      dest_url,
+      title,
+      ..
+    }) => processor.process_link_start(link_type, &dest_url, &title),
+    Event::End(TagEnd::Link) => processor.process_link_end(),
+    Event::Start(Tag::Heading { level, .. }) => processor.process_heading_start(level),
     Event::Start(Tag::CodeBlock(kind)) => processor.process_code_block_start(kind),
     Event::End(TagEnd::CodeBlock) => processor.process_code_block_end(),
     Event::SoftBreak => processor.process_soft_break(processor.in_code_block()),
@@ -272,10 +434,67 @@ pub fn markdown_to_html(input: &str, options: Options) -> String {
   });
 
   let mut html_output = String::new();
+  if config.toc == TocMode::Block {
+    html_output.push_str(&toc_html(&outline));
+  }
   html::push_html(&mut html_output, parser);
 
   let out = clean_html_structure(&html_output);
-  out.replace("<br>\n", "")
+  Conversion {
+    html: out.replace("<br>\n", ""),
+    warnings: processor.warnings,
+    table_of_contents: config.toc == TocMode::Zhihu,
+    outline,
+    meta,
+  }
+}
+
+fn image_defaults_from_lua(table: &mlua::Table) -> Result<ImageDefaults> {
+  let mut defaults = ImageDefaults::default();
+  if let Some(size) = table.get::<Option<String>>("size")? {
+    defaults.size = ImageSize::parse(&size).ok_or_else(|| {
+      mlua::Error::runtime(format!(
+        "Unknown image size `{}`, use small, normal or large",
+        size
+      ))
+    })?;
+  }
+  if let Some(watermark) = table.get::<Option<bool>>("watermark")? {
+    defaults.watermark = if watermark {
+      Watermark::Watermark
+    } else {
+      Watermark::Original
+    };
+  }
+  if let Some(caption_from_alt) = table.get::<Option<bool>>("caption_from_alt")? {
+    defaults.caption_from_alt = caption_from_alt;
+  }
+  Ok(defaults)
+}
+
+fn meta_to_lua(lua: &Lua, meta: ArticleMeta) -> Result<mlua::Table> {
+  let table = lua.create_table()?;
+  table.set("title", meta.title)?;
+  table.set("id", meta.id)?;
+  table.set("topics", meta.topics)?;
+  table.set("column", meta.column)?;
+  table.set("cover", meta.cover)?;
+  table.set("comment_permission", meta.comment_permission)?;
+  table.set("original", meta.original)?;
+  Ok(table)
+}
+
+fn outline_to_lua(lua: &Lua, outline: Vec<Heading>) -> Result<mlua::Table> {
+  let table = lua.create_table()?;
+  for heading in outline {
+    let entry = lua.create_table()?;
+    entry.set("level", heading.level)?;
+    entry.set("text", heading.text)?;
+    entry.set("line", heading.line)?;
+    entry.set("id", heading.id)?;
+    table.push(entry)?;
+  }
+  Ok(table)
 }
 
 #[mlua::lua_module]
@@ -285,10 +504,79 @@ fn markdown_to_html_lib(lua: &Lua) -> Result<mlua::Table> {
     | Options::ENABLE_TABLES
     | Options::ENABLE_TASKLISTS
     | Options::ENABLE_FOOTNOTES
-    | Options::ENABLE_MATH;
+    | Options::ENABLE_MATH
+    | Options::ENABLE_WIKILINKS
+    | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+    | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
   exports.set(
     "md_to_html",
     lua.create_function(move |_, markdown: String| Ok(markdown_to_html(&markdown, options)))?,
   )?;
+
+  // convert(markdown: String, opts: { links?: table<string, string>, toc?: "off"|"block"|"zhihu",
+  //   math?: "image"|"ztext", images?: { size?, watermark?: boolean, caption_from_alt?: boolean } }?)
+  //   -> { html, warnings, outline, table_of_contents, meta }
+  exports.set(
+    "convert",
+    lua.create_function(
+      move |lua, (markdown, opts): (String, Option<mlua::Table>)| {
+        let mut config = ConvertConfig::default();
+        if let Some(opts) = opts {
+          config.links = opts
+            .get::<Option<HashMap<String, String>>>("links")?
+            .unwrap_or_default();
+          if let Some(toc) = opts.get::<Option<String>>("toc")? {
+            config.toc = TocMode::parse(&toc).ok_or_else(|| {
+              mlua::Error::runtime(format!(
+                "Unknown toc mode `{}`, use off, block or zhihu",
+                toc
+              ))
+            })?;
+          }
+          if let Some(images) = opts.get::<Option<mlua::Table>>("images")? {
+            config.images = image_defaults_from_lua(&images)?;
+          }
+          if let Some(math) = opts.get::<Option<String>>("math")? {
+            config.math = MathStyle::parse(&math).ok_or_else(|| {
+              mlua::Error::runtime(format!("Unknown math style `{}`, use image or ztext", math))
+            })?;
+          }
+        }
+        let conversion = convert(&markdown, options, &config);
+        let result = lua.create_table()?;
+        result.set("html", conversion.html)?;
+        result.set("warnings", conversion.warnings)?;
+        result.set("outline", outline_to_lua(lua, conversion.outline)?)?;
+        result.set("table_of_contents", conversion.table_of_contents)?;
+        result.set("meta", meta_to_lua(lua, conversion.meta)?)?;
+        Ok(result)
+      },
+    )?,
+  )?;
+
+  // front_matter(markdown: String) -> meta?, warnings?
+  exports.set(
+    "front_matter",
+    lua.create_function(
+      move |lua, markdown: String| match front_matter(&markdown, options) {
+        Some((meta, warnings)) => Ok((Some(meta_to_lua(lua, meta)?), Some(warnings))),
+        None => Ok((None, None)),
+      },
+    )?,
+  )?;
+
+  // outline(markdown: String) -> { level, text, line, id }[]
+  exports.set(
+    "outline",
+    lua.create_function(move |lua, markdown: String| {
+      outline_to_lua(lua, outline(&markdown, options))
+    })?,
+  )?;
+
+  // note_links(markdown: String) -> string[]
+  exports.set(
+    "note_links",
+    lua.create_function(move |_, markdown: String| Ok(note_links(&markdown, options)))?,
+  )?;
   Ok(exports)
 }
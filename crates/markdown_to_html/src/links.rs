@@ -0,0 +1,113 @@
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag};
+use std::collections::HashMap;
+
+/// Prefix of a published Zhihu column article.
+pub const ZHIHU_ARTICLE_URL: &str = "https://zhuanlan.zhihu.com/p/";
+
+/// Where a link destination should point after resolution.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkTarget {
+  /// Not a link to a local note (web URL, anchor, image, ...), leave it untouched.
+  External,
+  /// A local note with a known Zhihu article.
+  Article(String),
+  /// A local note that has not been uploaded yet, carrying its lookup key.
+  Unresolved(String),
+}
+
+/// Resolve links between local notes to their Zhihu article URLs.
+///
+/// Both relative Markdown links (`[上一篇](./part1.md)`) and wikilinks (`[[part1]]`) are reduced to
+/// a note key, i.e. the path relative to the current note with `./`, the `.md` extension and any
+/// `#fragment` removed. The caller provides the key → article id map, e.g. filled by the Lua side
+/// from the `buf_id` store.
+#[derive(Debug, Default, Clone)]
+pub struct LinkResolver {
+  pub ids: HashMap<String, String>,
+}
+
+impl LinkResolver {
+  pub fn new(ids: HashMap<String, String>) -> Self {
+    Self { ids }
+  }
+
+  pub fn resolve(&self, link_type: LinkType, dest_url: &str) -> LinkTarget {
+    let key = match link_type {
+      LinkType::WikiLink { .. } => wikilink_key(dest_url),
+      _ => note_key(dest_url),
+    };
+    match key {
+      Some(key) => match self.ids.get(&key) {
+        Some(id) => LinkTarget::Article(format!("{}{}", ZHIHU_ARTICLE_URL, id)),
+        None => LinkTarget::Unresolved(key),
+      },
+      None => LinkTarget::External,
+    }
+  }
+}
+
+/// Key of a relative `.md` link, or `None` if the destination is not a local note.
+pub fn note_key(dest_url: &str) -> Option<String> {
+  if dest_url.is_empty() || dest_url.starts_with('#') || dest_url.starts_with('/') {
+    return None;
+  }
+  if dest_url.contains("://") || dest_url.starts_with("mailto:") {
+    return None;
+  }
+  // Notes are looked up as `<key>.md`, so other extensions are not notes
+  let path = strip_fragment(dest_url);
+  let stem = path.strip_suffix(".md")?;
+  normalize(&percent_decode_str(stem).decode_utf8_lossy())
+}
+
+/// Key of a wikilink target, `[[part1]]`, `[[series/part1.md#小节]]`.
+pub fn wikilink_key(dest_url: &str) -> Option<String> {
+  let path = strip_fragment(dest_url).trim();
+  let stem = path.strip_suffix(".md").unwrap_or(path);
+  normalize(stem)
+}
+
+/// Collect the keys of all local note links in `input`, in order of first appearance.
+pub fn note_links(input: &str, options: Options) -> Vec<String> {
+  let mut keys: Vec<String> = Vec::new();
+  for event in Parser::new_ext(input, options) {
+    if let Event::Start(Tag::Link {
+      link_type,
+      dest_url,
+      ..
+    }) = event
+    {
+      if let LinkTarget::Unresolved(key) = LinkResolver::default().resolve(link_type, &dest_url) {
+        if !keys.contains(&key) {
+          keys.push(key);
+        }
+      }
+    }
+  }
+  keys
+}
+
+fn strip_fragment(dest_url: &str) -> &str {
+  let end = dest_url.find(['#', '?']).unwrap_or(dest_url.len());
+  &dest_url[..end]
+}
+
+/// Fold `.` and `..` components so that `./a/../b` and `b` share a key.
+fn normalize(path: &str) -> Option<String> {
+  let mut parts: Vec<&str> = Vec::new();
+  for part in path.split('/') {
+    match part {
+      "" | "." => {}
+      ".." if parts.last().is_some_and(|last| *last != "..") => {
+        parts.pop();
+      }
+      _ => parts.push(part),
+    }
+  }
+  if parts.is_empty() {
+    None
+  } else {
+    Some(parts.join("/"))
+  }
+}
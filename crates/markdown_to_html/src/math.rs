@@ -0,0 +1,77 @@
+use crate::escape_html;
+
+/// Whether a formula was written inline (`$...$`) or as a display block (`$$...$$`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathKind {
+  Inline,
+  Display,
+}
+
+/// Strategy turning a TeX formula into Zhihu's math markup.
+pub trait MathRenderer {
+  fn render(&self, tex: &str, kind: MathKind) -> String;
+}
+
+/// Zhihu marks display formulas by a trailing `\\` in the TeX source, in both markups. This is
+/// also what `util/html_md.py` looks for when syncing an article back.
+const DISPLAY_MARKER: &str = "\\\\";
+
+fn zhihu_tex(tex: &str, kind: MathKind) -> String {
+  let tex = tex.replace(['\n', '\r'], " ");
+  let tex = tex.trim();
+  match kind {
+    MathKind::Inline => tex.to_string(),
+    MathKind::Display => format!("{}{}", tex, DISPLAY_MARKER),
+  }
+}
+
+/// `<img eeimg="1" src="//www.zhihu.com/equation?tex=...">`, the legacy equation image.
+pub struct EquationImage;
+
+impl MathRenderer for EquationImage {
+  fn render(&self, tex: &str, kind: MathKind) -> String {
+    let tex = escape_html(&zhihu_tex(tex, kind));
+    format!(
+      "<img eeimg=\"1\" src=\"//www.zhihu.com/equation?tex={}\" alt=\"{}\"/>",
+      tex, tex
+    )
+  }
+}
+
+/// `<span class="ztext-math" data-eeimg="1" data-tex="...">`, the markup of Zhihu's newer editor.
+pub struct ZtextMath;
+
+impl MathRenderer for ZtextMath {
+  fn render(&self, tex: &str, kind: MathKind) -> String {
+    let tex = escape_html(&zhihu_tex(tex, kind));
+    format!(
+      "<span class=\"ztext-math\" data-eeimg=\"1\" data-tex=\"{}\">{}</span>",
+      tex, tex
+    )
+  }
+}
+
+/// Built-in math renderers, selectable from the configuration.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MathStyle {
+  #[default]
+  EquationImage,
+  Ztext,
+}
+
+impl MathStyle {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "image" => Some(MathStyle::EquationImage),
+      "ztext" => Some(MathStyle::Ztext),
+      _ => None,
+    }
+  }
+
+  pub fn renderer(self) -> Box<dyn MathRenderer> {
+    match self {
+      MathStyle::EquationImage => Box::new(EquationImage),
+      MathStyle::Ztext => Box::new(ZtextMath),
+    }
+  }
+}
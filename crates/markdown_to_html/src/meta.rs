@@ -0,0 +1,201 @@
+use pulldown_cmark::{Event, MetadataBlockKind, Options, Parser, Tag, TagEnd};
+use serde_json::Value;
+
+/// Comment permissions understood by Zhihu when publishing.
+const COMMENT_PERMISSIONS: [&str; 4] = ["anyone", "followee", "censor", "nobody"];
+
+/// Front matter keys written by the zhihu_obsidian plugin, with the key each stands for.
+const OBSIDIAN_KEYS: [(&str, &str); 5] = [
+  ("zhihu-link", "link"),
+  ("zhihu-title", "title"),
+  ("zhihu-topics", "topics"),
+  ("zhihu-column", "column"),
+  ("zhihu-cover", "cover"),
+];
+
+/// Article settings from the front matter of a note, YAML between `---` lines or TOML between
+/// `+++` lines at the very top of the document.
+///
+/// The keys of [zhihu_obsidian](https://github.com/dongguaguaguagua/zhihu_obsidian) are understood
+/// as well, so that a note published from Obsidian updates the same article: `zhihu-title`,
+/// `zhihu-topics`, `zhihu-column`, `zhihu-cover`, and `zhihu-link` (or `link`), the article URL
+/// the id is taken from. An explicit `id` wins over a link.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ArticleMeta {
+  pub title: Option<String>,
+  /// Zhihu draft or article id the note is published as.
+  pub id: Option<String>,
+  /// Topic ids or names.
+  pub topics: Vec<String>,
+  /// Column id.
+  pub column: Option<String>,
+  /// Cover image, a local path or URL.
+  pub cover: Option<String>,
+  /// `anyone`, `followee`, `censor` or `nobody`.
+  pub comment_permission: Option<String>,
+  /// Declare the article as original content.
+  pub original: Option<bool>,
+}
+
+impl ArticleMeta {
+  /// Parse the content of a metadata block, reporting syntax errors and unknown or mistyped keys
+  /// to `warnings`.
+  pub fn parse(kind: MetadataBlockKind, text: &str, warnings: &mut Vec<String>) -> Self {
+    let parsed = match kind {
+      MetadataBlockKind::YamlStyle => {
+        serde_yaml::from_str::<Value>(text).map_err(|e| format!("Invalid YAML front matter: {}", e))
+      }
+      MetadataBlockKind::PlusesStyle => {
+        toml::from_str::<Value>(text).map_err(|e| format!("Invalid TOML front matter: {}", e))
+      }
+    };
+    let fields = match parsed {
+      Ok(Value::Object(fields)) => fields,
+      Ok(Value::Null) => return Self::default(),
+      Ok(_) => {
+        warnings.push("Front matter should be a table of keys".to_string());
+        return Self::default();
+      }
+      Err(warning) => {
+        warnings.push(warning);
+        return Self::default();
+      }
+    };
+
+    let mut meta = Self::default();
+    let mut link_id = None;
+    for (key, value) in fields {
+      // Publication time recorded by zhihu_obsidian, nothing to configure.
+      if key == "zhihu-created-at" {
+        continue;
+      }
+      let native = OBSIDIAN_KEYS
+        .iter()
+        .find(|(obsidian, _)| *obsidian == key)
+        .map_or(key.as_str(), |(_, native)| native);
+      match native {
+        "link" => {
+          link_id = string(&key, &value, warnings).and_then(|link| {
+            let id = article_id(&link);
+            if id.is_none() {
+              warnings.push(format!("No Zhihu article id in link `{}`", link));
+            }
+            id
+          })
+        }
+        "title" => meta.title = string(&key, &value, warnings),
+        "id" => meta.id = string(&key, &value, warnings),
+        "topics" => meta.topics = strings(&key, &value, warnings),
+        "column" => meta.column = string(&key, &value, warnings),
+        "cover" => meta.cover = string(&key, &value, warnings),
+        "comment_permission" => {
+          meta.comment_permission = string(&key, &value, warnings).filter(|permission| {
+            let known = COMMENT_PERMISSIONS.contains(&permission.as_str());
+            if !known {
+              warnings.push(format!(
+                "Unknown comment permission `{}`, use anyone, followee, censor or nobody",
+                permission
+              ));
+            }
+            known
+          })
+        }
+        "original" => match value {
+          Value::Bool(original) => meta.original = Some(original),
+          _ => warnings.push(format!("Front matter key `{}` should be a boolean", key)),
+        },
+        _ => warnings.push(format!("Unknown front matter key `{}`", key)),
+      }
+    }
+    meta.id = meta.id.or(link_id);
+    meta
+  }
+}
+
+/// Id of an article link, `https://zhuanlan.zhihu.com/p/123456` (or its `/edit` page), or the
+/// bare id.
+pub fn article_id(link: &str) -> Option<String> {
+  const ARTICLE_PATH: &str = "zhihu.com/p/";
+  let link = link.trim();
+  let id = match link.find(ARTICLE_PATH) {
+    Some(start) => link[start + ARTICLE_PATH.len()..]
+      .split(['/', '?', '#'])
+      .next()
+      .unwrap_or_default(),
+    None => link,
+  };
+  (!id.is_empty() && id.chars().all(|c| c.is_ascii_digit())).then(|| id.to_string())
+}
+
+/// A string, or a number such as an id written without quotes.
+fn string(key: &str, value: &Value, warnings: &mut Vec<String>) -> Option<String> {
+  match value {
+    Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+    Value::Number(n) => Some(n.to_string()),
+    Value::Null => None,
+    _ => {
+      warnings.push(format!("Front matter key `{}` should be a string", key));
+      None
+    }
+  }
+}
+
+/// A list of strings, or a single comma-separated string.
+fn strings(key: &str, value: &Value, warnings: &mut Vec<String>) -> Vec<String> {
+  match value {
+    Value::Array(items) => items
+      .iter()
+      .filter_map(|item| string(key, item, warnings))
+      .collect(),
+    Value::String(s) => s
+      .split([',', '，'])
+      .map(str::trim)
+      .filter(|s| !s.is_empty())
+      .map(str::to_string)
+      .collect(),
+    _ => string(key, value, warnings).into_iter().collect(),
+  }
+}
+
+/// Remove the front matter events from `events`, returning the parsed metadata.
+pub(crate) fn take_front_matter(
+  events: &mut Vec<Event<'_>>,
+  warnings: &mut Vec<String>,
+) -> ArticleMeta {
+  let Some(Event::Start(Tag::MetadataBlock(kind))) = events.first() else {
+    return ArticleMeta::default();
+  };
+  let kind = *kind;
+  let end = events
+    .iter()
+    .position(|event| matches!(event, Event::End(TagEnd::MetadataBlock(_))))
+    .unwrap_or(events.len() - 1);
+  let text: String = events
+    .drain(..=end)
+    .filter_map(|event| match event {
+      Event::Text(text) => Some(text.into_string()),
+      _ => None,
+    })
+    .collect();
+  ArticleMeta::parse(kind, &text, warnings)
+}
+
+/// Parse the front matter of `input`, `None` if it has none.
+pub fn front_matter(input: &str, options: Options) -> Option<(ArticleMeta, Vec<String>)> {
+  let mut parser = Parser::new_ext(input, options);
+  let first = parser.next()?;
+  if !matches!(first, Event::Start(Tag::MetadataBlock(_))) {
+    return None;
+  }
+  let mut events = vec![first];
+  for event in parser {
+    let end = matches!(event, Event::End(TagEnd::MetadataBlock(_)));
+    events.push(event);
+    if end {
+      break;
+    }
+  }
+  let mut warnings = Vec::new();
+  let meta = take_front_matter(&mut events, &mut warnings);
+  Some((meta, warnings))
+}
@@ -0,0 +1,142 @@
+use crate::escape_html;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// How the table of contents of an article is produced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TocMode {
+  /// No table of contents.
+  #[default]
+  Off,
+  /// Emit a list of heading links at the top of the article.
+  Block,
+  /// Let Zhihu render its own table of contents (`table_of_contents` in the drafts API).
+  Zhihu,
+}
+
+impl TocMode {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "off" => Some(TocMode::Off),
+      "block" => Some(TocMode::Block),
+      "zhihu" => Some(TocMode::Zhihu),
+      _ => None,
+    }
+  }
+}
+
+/// One entry of the heading outline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+  pub level: u8,
+  pub text: String,
+  /// 1-based line of the heading in the Markdown source.
+  pub line: usize,
+  /// Anchor id emitted on the heading element.
+  pub id: String,
+}
+
+/// Collect the heading outline of `input`, with unique anchor ids.
+pub fn outline(input: &str, options: Options) -> Vec<Heading> {
+  let mut headings = Vec::new();
+  let mut current: Option<Heading> = None;
+  // Headings come in source order, so lines are counted from the previous one on
+  let (mut line, mut counted) = (1, 0);
+
+  for (event, range) in Parser::new_ext(input, options).into_offset_iter() {
+    match event {
+      Event::Start(Tag::Heading { level, id, .. }) => {
+        line += input[counted..range.start].matches('\n').count();
+        counted = range.start;
+        current = Some(Heading {
+          level: level as u8,
+          text: String::new(),
+          line,
+          id: id.map(|id| id.to_string()).unwrap_or_default(),
+        });
+      }
+      Event::End(TagEnd::Heading(_)) => {
+        if let Some(mut heading) = current.take() {
+          heading.text = heading.text.trim().to_string();
+          headings.push(heading);
+        }
+      }
+      Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => {
+        if let Some(heading) = current.as_mut() {
+          heading.text.push_str(&text);
+        }
+      }
+      Event::SoftBreak | Event::HardBreak => {
+        if let Some(heading) = current.as_mut() {
+          heading.text.push(' ');
+        }
+      }
+      _ => {}
+    }
+  }
+  // Ids are generated once every explicit `{#id}` is known, so none of them is taken twice
+  for i in 0..headings.len() {
+    if headings[i].id.is_empty() {
+      headings[i].id = unique_id(&slugify(&headings[i].text), &headings);
+    }
+  }
+  headings
+}
+
+/// Render the outline as nested lists of anchor links.
+pub fn toc_html(headings: &[Heading]) -> String {
+  let Some(base) = headings.iter().map(|h| h.level).min() else {
+    return String::new();
+  };
+  let mut html = String::from("<ul>");
+  let mut depth = base;
+  for heading in headings {
+    while depth < heading.level {
+      html.push_str("<ul>");
+      depth += 1;
+    }
+    while depth > heading.level {
+      html.push_str("</ul>");
+      depth -= 1;
+    }
+    html.push_str(&format!(
+      "<li><a href=\"#{}\">{}</a></li>",
+      escape_html(&heading.id),
+      escape_html(&heading.text)
+    ));
+  }
+  while depth > base {
+    html.push_str("</ul>");
+    depth -= 1;
+  }
+  html.push_str("</ul>");
+  html
+}
+
+/// Lowercase the heading text and join words with `-`, keeping CJK characters as they are.
+fn slugify(text: &str) -> String {
+  let mut slug = String::new();
+  for c in text.chars() {
+    if c.is_alphanumeric() {
+      slug.extend(c.to_lowercase());
+    } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+      slug.push('-');
+    }
+  }
+  let slug = slug.trim_matches('-');
+  if slug.is_empty() {
+    "section".to_string()
+  } else {
+    slug.to_string()
+  }
+}
+
+fn unique_id(slug: &str, headings: &[Heading]) -> String {
+  let taken = |id: &str| headings.iter().any(|h| h.id == id);
+  if !taken(slug) {
+    return slug.to_string();
+  }
+  (1..)
+    .map(|n| format!("{}-{}", slug, n))
+    .find(|id| !taken(id))
+    .unwrap()
+}
@@ -1,6 +1,9 @@
-use markdown_to_html::markdown_to_html;
+use markdown_to_html::{
+  article_id, convert, front_matter, markdown_to_html, note_links, outline, ArticleMeta,
+  ConvertConfig, ImageDefaults, ImageSize, MathStyle, TocMode, Watermark,
+};
 use pulldown_cmark::Options;
-use std::{fs, process::Output};
+use std::collections::HashMap;
 
 fn zhihu_options() -> Options {
   Options::ENABLE_STRIKETHROUGH
@@ -8,6 +11,9 @@ fn zhihu_options() -> Options {
     | Options::ENABLE_TASKLISTS
     | Options::ENABLE_FOOTNOTES
     | Options::ENABLE_MATH
+    | Options::ENABLE_WIKILINKS
+    | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+    | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS
 }
 
 fn assert_md_html(input: &str, expected_output: &str) {
@@ -29,6 +35,34 @@ fn test_markdown_to_html_math_formula() {
   assert_md_html(input, expected_output);
 }
 
+#[test]
+fn test_markdown_to_html_display_math_formula() {
+  let input = "$$\na < b\n$$";
+  let expected_output = r#"<html><head></head><body><img eeimg="1" src="//www.zhihu.com/equation?tex=a < b\\" alt="a < b\\"></body></html>"#;
+  assert_md_html(input, expected_output);
+}
+
+#[test]
+fn test_markdown_to_html_ztext_math() {
+  let input = "公式$x^2$与\n\n$$\\int_0^1 f$$";
+  let config = ConvertConfig {
+    math: MathStyle::Ztext,
+    ..Default::default()
+  };
+  let expected_output = r#"<html><head></head><body><p>公式<span class="ztext-math" data-eeimg="1" data-tex="x^2">x^2</span>与</p><span class="ztext-math" data-eeimg="1" data-tex="\int_0^1 f\\">\int_0^1 f\\</span></body></html>"#;
+  assert_eq!(
+    convert(input, zhihu_options(), &config).html,
+    expected_output
+  );
+}
+
+#[test]
+fn test_markdown_to_html_standalone_link() {
+  let input = "[Zhihu on Neovim](https://github.com/pxwg/zhihu_neovim)";
+  let expected_output = r#"<html><head></head><body><a href="https://github.com/pxwg/zhihu_neovim">Zhihu on Neovim</a></body></html>"#;
+  assert_md_html(input, expected_output);
+}
+
 #[test]
 fn test_markdown_to_html_blockquote() {
   let input = "> **数学**是人类智慧的结晶，\n> Math is the language of the universe,\n>\n> --Paul Halmos\n> **现在的技术**使得数学计算变得更加高效，我们 *what can do in seconds*。";
@@ -56,3 +90,246 @@ fn test_markdown_to_html_unordered_list_nested() {
   let expected_output = r#"<html><head></head><body><ul><li>一级测试</li><ul><li>测试一下！</li><li>测试二级列表</li><ul><li>三级列表测试</li><ul><li>四级列表测试</li></ul></ul></ul><li>这是一个测试</li><li>hellbchqwleld</li><li>snwebqw</li></ul></body></html>"#;
   assert_md_html(input, expected_output);
 }
+
+fn series_config() -> ConvertConfig {
+  ConvertConfig {
+    links: HashMap::from([
+      ("part1".to_string(), "1900000000000000001".to_string()),
+      (
+        "series/part2".to_string(),
+        "1900000000000000002".to_string(),
+      ),
+    ]),
+    ..Default::default()
+  }
+}
+
+#[test]
+fn test_markdown_to_html_note_links_resolved() {
+  let input = "[上一篇](./part1.md)与[下一篇](series/part2.md#小节)，见 [[part1|第一篇]]。";
+  let expected_output = r#"<html><head></head><body><p><a href="https://zhuanlan.zhihu.com/p/1900000000000000001">上一篇</a>与<a href="https://zhuanlan.zhihu.com/p/1900000000000000002">下一篇</a>，见 <a href="https://zhuanlan.zhihu.com/p/1900000000000000001">第一篇</a>。</p></body></html>"#;
+  let conversion = convert(input, zhihu_options(), &series_config());
+  assert_eq!(conversion.html, expected_output);
+  assert!(conversion.warnings.is_empty());
+}
+
+#[test]
+fn test_markdown_to_html_note_links_unresolved() {
+  let input = "[上一篇](./part0.md)，[[draft]]，[主页](https://www.zhihu.com)";
+  let expected_output = r#"<html><head></head><body><p>上一篇，draft，<a href="https://www.zhihu.com">主页</a></p></body></html>"#;
+  let conversion = convert(input, zhihu_options(), &series_config());
+  assert_eq!(conversion.html, expected_output);
+  assert_eq!(conversion.warnings.len(), 2);
+  assert!(conversion.warnings[0].contains("`part0`"));
+  assert!(conversion.warnings[1].contains("`draft`"));
+}
+
+#[test]
+fn test_note_links_keys() {
+  let input = "[a](./a.md) [b](../b.md#x) [[c]] [a again](a.md) [web](https://a.md) [img](#a.md) [d](d.markdown)";
+  assert_eq!(note_links(input, zhihu_options()), vec!["a", "../b", "c"]);
+}
+
+#[test]
+fn test_outline_ids_avoid_explicit_ids_below() {
+  let input = "## Usage\n\n## Usage\n\n## Details {#usage-1}\n";
+  let headings = outline(input, zhihu_options() | Options::ENABLE_HEADING_ATTRIBUTES);
+  let ids: Vec<_> = headings.iter().map(|h| h.id.as_str()).collect();
+  assert_eq!(ids, vec!["usage", "usage-2", "usage-1"]);
+}
+
+#[test]
+fn test_outline_levels_lines_and_ids() {
+  let input = "# 引言\n\n正文\n\n## Basic Usage\n\n### `code` 与 $x$\n\n## Basic Usage\n";
+  let headings = outline(input, zhihu_options());
+  let summary: Vec<_> = headings
+    .iter()
+    .map(|h| (h.level, h.text.as_str(), h.line, h.id.as_str()))
+    .collect();
+  assert_eq!(
+    summary,
+    vec![
+      (1, "引言", 1, "引言"),
+      (2, "Basic Usage", 5, "basic-usage"),
+      (3, "code 与 x", 7, "code-与-x"),
+      (2, "Basic Usage", 9, "basic-usage-1"),
+    ]
+  );
+}
+
+#[test]
+fn test_markdown_to_html_toc_block() {
+  let input = "## 第一节\n\n内容\n\n### 小节\n\n## 第二节";
+  let config = ConvertConfig {
+    toc: TocMode::Block,
+    ..Default::default()
+  };
+  let expected_output = r##"<html><head></head><body><ul><li><a href="#第一节">第一节</a></li><ul><li><a href="#小节">小节</a></li></ul><li><a href="#第二节">第二节</a></li></ul><h2 id="第一节">第一节</h2><p>内容</p><h3 id="小节">小节</h3><h2 id="第二节">第二节</h2></body></html>"##;
+  let conversion = convert(input, zhihu_options(), &config);
+  assert_eq!(conversion.html, expected_output);
+  assert!(!conversion.table_of_contents);
+}
+
+#[test]
+fn test_markdown_to_html_toc_zhihu_flag() {
+  let config = ConvertConfig {
+    toc: TocMode::Zhihu,
+    ..Default::default()
+  };
+  let conversion = convert("## 第一节", zhihu_options(), &config);
+  assert_eq!(
+    conversion.html,
+    r#"<html><head></head><body><h2 id="第一节">第一节</h2></body></html>"#
+  );
+  assert!(conversion.table_of_contents);
+  assert_eq!(conversion.outline.len(), 1);
+}
+
+#[test]
+fn test_markdown_to_html_image_default() {
+  let input = "![示意图](a.png)";
+  let expected_output = r#"<html><head></head><body><img src="a.png" alt="示意图" data-caption="示意图" data-size="normal" data-watermark="watermark" data-original-src="a.png" data-watermark-src="" data-private-watermark-src=""></body></html>"#;
+  assert_md_html(input, expected_output);
+}
+
+#[test]
+fn test_markdown_to_html_image_title_attributes() {
+  let input = "![示意图](a.png \"large nowatermark\")\n\n![流程](b.png \"图 2：流程\")";
+  let expected_output = r#"<html><head></head><body><img src="a.png" alt="示意图" data-caption="示意图" data-size="large" data-watermark="original" data-original-src="a.png" data-watermark-src="" data-private-watermark-src=""><img src="b.png" alt="流程" data-caption="图 2：流程" data-size="normal" data-watermark="watermark" data-original-src="b.png" data-watermark-src="" data-private-watermark-src=""></body></html>"#;
+  assert_md_html(input, expected_output);
+}
+
+#[test]
+fn test_markdown_to_html_image_attribute_block() {
+  let input = "图片![示意图](a.png \"large\"){size=small caption=\"图 1\" color=red}后文";
+  let expected_output = r#"<html><head></head><body><p>图片<img src="a.png" alt="示意图" data-caption="图 1" data-size="small" data-watermark="watermark" data-original-src="a.png" data-watermark-src="" data-private-watermark-src="">后文</p></body></html>"#;
+  let conversion = convert(input, zhihu_options(), &ConvertConfig::default());
+  assert_eq!(conversion.html, expected_output);
+  assert_eq!(conversion.warnings, vec!["Unknown image attribute `color`"]);
+}
+
+#[test]
+fn test_markdown_to_html_image_defaults() {
+  let config = ConvertConfig {
+    images: ImageDefaults {
+      size: ImageSize::Large,
+      watermark: Watermark::Original,
+      caption_from_alt: false,
+    },
+    ..Default::default()
+  };
+  let expected_output = r#"<html><head></head><body><img src="a.png" alt="示意图" data-caption="" data-size="large" data-watermark="original" data-original-src="a.png" data-watermark-src="" data-private-watermark-src=""></body></html>"#;
+  let conversion = convert("![示意图](a.png)", zhihu_options(), &config);
+  assert_eq!(conversion.html, expected_output);
+}
+
+#[test]
+fn test_yaml_front_matter() {
+  let input = "---\ntitle: 标题\nid: 1919810\ntopics:\n  - Rust\n  - 19550517\ncolumn: c_1234\ncover: ./cover.png\ncomment_permission: censor\noriginal: true\ntags: [note]\n---\n\n正文";
+  let conversion = convert(input, zhihu_options(), &ConvertConfig::default());
+  assert_eq!(
+    conversion.html,
+    "<html><head></head><body><p>正文</p></body></html>"
+  );
+  assert_eq!(
+    conversion.meta,
+    ArticleMeta {
+      title: Some("标题".to_string()),
+      id: Some("1919810".to_string()),
+      topics: vec!["Rust".to_string(), "19550517".to_string()],
+      column: Some("c_1234".to_string()),
+      cover: Some("./cover.png".to_string()),
+      comment_permission: Some("censor".to_string()),
+      original: Some(true),
+    }
+  );
+  assert_eq!(conversion.warnings, vec!["Unknown front matter key `tags`"]);
+}
+
+#[test]
+fn test_toml_front_matter() {
+  let input = "+++\ntitle = \"标题\"\ntopics = \"Rust, 编程\"\ncomment_permission = \"everyone\"\noriginal = \"yes\"\n+++\n\n正文";
+  let (meta, warnings) = front_matter(input, zhihu_options()).unwrap();
+  assert_eq!(meta.title.as_deref(), Some("标题"));
+  assert_eq!(meta.topics, vec!["Rust", "编程"]);
+  assert_eq!(meta.comment_permission, None);
+  assert_eq!(meta.original, None);
+  assert_eq!(
+    warnings,
+    vec![
+      "Unknown comment permission `everyone`, use anyone, followee, censor or nobody",
+      "Front matter key `original` should be a boolean",
+    ]
+  );
+}
+
+#[test]
+fn test_front_matter_absent_or_invalid() {
+  assert!(front_matter("# 标题\n\n正文", zhihu_options()).is_none());
+  let (meta, warnings) = front_matter("---\ntitle: [\n---\n", zhihu_options()).unwrap();
+  assert_eq!(meta, ArticleMeta::default());
+  assert!(warnings[0].starts_with("Invalid YAML front matter"));
+}
+
+#[test]
+fn test_zhihu_obsidian_front_matter() {
+  let input = "---\nzhihu-title: 标题\nzhihu-topics:\n  - Rust\nzhihu-link: https://zhuanlan.zhihu.com/p/1919810\nzhihu-created-at: 2025-01-01T00:00:00+08:00\nzhihu-column: c_1234\nzhihu-cover: ./cover.png\n---\n\n正文";
+  let (meta, warnings) = front_matter(input, zhihu_options()).unwrap();
+  assert_eq!(
+    meta,
+    ArticleMeta {
+      title: Some("标题".to_string()),
+      id: Some("1919810".to_string()),
+      topics: vec!["Rust".to_string()],
+      column: Some("c_1234".to_string()),
+      cover: Some("./cover.png".to_string()),
+      ..ArticleMeta::default()
+    }
+  );
+  assert!(warnings.is_empty(), "{:?}", warnings);
+
+  // Only the plugin's own keys have a `zhihu-` form
+  let input = "---\nzhihu-id: 42\nzhihu-original: true\nzhihu-toc: true\n---\n";
+  let (meta, warnings) = front_matter(input, zhihu_options()).unwrap();
+  assert_eq!(meta, ArticleMeta::default());
+  assert_eq!(
+    warnings,
+    vec![
+      "Unknown front matter key `zhihu-id`",
+      "Unknown front matter key `zhihu-original`",
+      "Unknown front matter key `zhihu-toc`",
+    ]
+  );
+}
+
+#[test]
+fn test_front_matter_id_wins_over_link() {
+  let input = "---\nid: 42\nlink: https://zhuanlan.zhihu.com/p/1919810\n---\n";
+  let (meta, _) = front_matter(input, zhihu_options()).unwrap();
+  assert_eq!(meta.id.as_deref(), Some("42"));
+
+  let (meta, warnings) = front_matter(
+    "---\nzhihu-link: https://www.zhihu.com\n---\n",
+    zhihu_options(),
+  )
+  .unwrap();
+  assert_eq!(meta.id, None);
+  assert_eq!(
+    warnings,
+    vec!["No Zhihu article id in link `https://www.zhihu.com`"]
+  );
+}
+
+#[test]
+fn test_article_id() {
+  assert_eq!(
+    article_id("https://zhuanlan.zhihu.com/p/1919810").as_deref(),
+    Some("1919810")
+  );
+  assert_eq!(
+    article_id("https://zhuanlan.zhihu.com/p/1919810/edit?x=1").as_deref(),
+    Some("1919810")
+  );
+  assert_eq!(article_id(" 1919810 ").as_deref(), Some("1919810"));
+  assert_eq!(article_id("https://www.zhihu.com/question/1"), None);
+}
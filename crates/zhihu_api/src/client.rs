@@ -0,0 +1,240 @@
+use crate::types::{
+  Draft, DraftCreated, ImageUpload, PublishSettings, Published, Topic, UploadToken,
+};
+use crate::ZhihuApiError;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use zhihu_image::{http_date, ImageInfo, OssRequest, ZHIHU_PICS_UPLOAD_URL};
+
+pub(crate) const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/115.0.0.0 Safari/537.36";
+/// User agent of the OSS SDK in Zhihu's web editor, part of the upload signature.
+pub const OSS_USER_AGENT: &str = "aliyun-sdk-js/6.8.0 Firefox 137.0 on OS X 10.15";
+const ACCEPT_LANGUAGE: &str = "zh-CN,zh;q=0.8,zh-TW;q=0.7,zh-HK;q=0.5,en-US;q=0.3,en;q=0.2";
+
+/// Base URLs of the Zhihu services, pointed at a local server in tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+  /// Column and drafts API, `https://zhuanlan.zhihu.com`.
+  pub zhuanlan: String,
+  /// Image API, `https://api.zhihu.com`.
+  pub api: String,
+  /// OSS upload host of the image bucket.
+  pub upload: String,
+}
+
+impl Default for Endpoints {
+  fn default() -> Self {
+    Self {
+      zhuanlan: "https://zhuanlan.zhihu.com".to_string(),
+      api: "https://api.zhihu.com".to_string(),
+      upload: ZHIHU_PICS_UPLOAD_URL.to_string(),
+    }
+  }
+}
+
+impl Endpoints {
+  /// All services served from one base URL, e.g. `http://127.0.0.1:8080`.
+  pub fn single(base: &str) -> Self {
+    let base = base.trim_end_matches('/').to_string();
+    Self {
+      zhuanlan: base.clone(),
+      api: base.clone(),
+      upload: base,
+    }
+  }
+}
+
+/// Client of the Zhihu web API, authenticated by the browser cookies.
+pub struct ZhihuClient {
+  endpoints: Endpoints,
+  cookie: String,
+  agent: ureq::Agent,
+}
+
+impl ZhihuClient {
+  pub fn new(cookie: &str) -> Self {
+    Self::with_endpoints(cookie, Endpoints::default())
+  }
+
+  pub fn with_endpoints(cookie: &str, endpoints: Endpoints) -> Self {
+    Self {
+      endpoints,
+      cookie: cookie.to_string(),
+      agent: ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(30))
+        .build(),
+    }
+  }
+
+  pub fn endpoints(&self) -> &Endpoints {
+    &self.endpoints
+  }
+
+  /// Request to the drafts API, with the headers of the web editor.
+  fn zhuanlan(&self, method: &str, path: &str) -> ureq::Request {
+    self
+      .agent
+      .request(method, &format!("{}{}", self.endpoints.zhuanlan, path))
+      .set("User-Agent", BROWSER_USER_AGENT)
+      .set("Cookie", &self.cookie)
+      .set("x-requested-with", "fetch")
+  }
+
+  /// Create a draft, returning its id.
+  pub fn create_draft(&self, draft: &Draft) -> Result<String, ZhihuApiError> {
+    let response = self
+      .zhuanlan("POST", "/api/articles/drafts")
+      .send_json(draft)?;
+    Ok(parse_json::<DraftCreated>(response)?.id)
+  }
+
+  pub fn update_draft(&self, draft_id: &str, draft: &Draft) -> Result<(), ZhihuApiError> {
+    self
+      .zhuanlan("PATCH", &format!("/api/articles/{}/draft", draft_id))
+      .send_json(draft)?;
+    Ok(())
+  }
+
+  /// Publish a draft: attach its topics, then publish it with the column, comment permission and
+  /// original content declaration of `settings`.
+  pub fn publish_draft(
+    &self,
+    draft_id: &str,
+    settings: &PublishSettings,
+  ) -> Result<Published, ZhihuApiError> {
+    for topic in &settings.topics {
+      let topic = self.resolve_topic(topic)?;
+      self
+        .zhuanlan("POST", &format!("/api/articles/{}/topics", draft_id))
+        .send_json(&topic)?;
+    }
+    let body = serde_json::json!({
+      "column": settings.column.as_ref().map(|id| serde_json::json!({ "id": id })),
+      "commentPermission": settings.comment_permission,
+      "disclaimer_type": "none",
+      "disclaimer_status": "close",
+      "is_original": settings.original,
+      "table_of_contents_enabled": settings.table_of_contents,
+      "commercial_report_info": { "commercial_types": [] },
+      "canReward": false,
+    });
+    let response = self
+      .zhuanlan("PUT", &format!("/api/articles/{}/publish", draft_id))
+      .send_json(body)?;
+    let mut published = parse_json::<Published>(response)?;
+    if published.url.is_empty() {
+      published.url = format!("{}/p/{}", self.endpoints.zhuanlan, published.id);
+    }
+    Ok(published)
+  }
+
+  /// Topics whose name matches `name`, best match first.
+  pub fn search_topics(&self, name: &str) -> Result<Vec<Topic>, ZhihuApiError> {
+    let response = self
+      .zhuanlan("GET", "/api/autocomplete/topics")
+      .query("token", name)
+      .query("max_matches", "5")
+      .call()?;
+    parse_json(response)
+  }
+
+  /// A topic given by id is used as is, a name is looked up and must match exactly.
+  fn resolve_topic(&self, topic: &str) -> Result<Topic, ZhihuApiError> {
+    if !topic.is_empty() && topic.chars().all(|c| c.is_ascii_digit()) {
+      return Ok(Topic {
+        id: topic.to_string(),
+        name: String::new(),
+      });
+    }
+    self
+      .search_topics(topic)?
+      .into_iter()
+      .find(|found| found.name == topic)
+      .ok_or_else(|| ZhihuApiError::UnknownTopic(topic.to_string()))
+  }
+
+  /// Register an image hash, telling whether the image must still be uploaded.
+  pub fn image_upload(&self, image_hash: &str) -> Result<ImageUpload, ZhihuApiError> {
+    let response = self
+      .agent
+      .post(&format!("{}/images", self.endpoints.api))
+      .set("Accept-Language", ACCEPT_LANGUAGE)
+      .set("Cookie", &self.cookie)
+      .send_json(serde_json::json!({ "image_hash": image_hash, "source": "article" }))?;
+    parse_json(response)
+  }
+
+  /// `PUT` the image content to the bucket with the token from [`ZhihuClient::image_upload`].
+  pub fn upload_image(
+    &self,
+    bytes: &[u8],
+    info: &ImageInfo,
+    token: &UploadToken,
+  ) -> Result<(), ZhihuApiError> {
+    let date = http_date();
+    let mime_type = info.format.mime_type();
+    let request = OssRequest::zhihu_image_upload(
+      &info.hash,
+      mime_type,
+      &date,
+      &token.access_token,
+      OSS_USER_AGENT,
+    );
+    self
+      .agent
+      .put(&format!("{}/v2-{}", self.endpoints.upload, info.hash))
+      .set("User-Agent", OSS_USER_AGENT)
+      .set("Content-Type", mime_type)
+      .set("x-oss-date", &date)
+      .set("x-oss-user-agent", OSS_USER_AGENT)
+      .set("x-oss-security-token", &token.access_token)
+      .set(
+        "Authorization",
+        &request.authorization(&token.access_id, &token.access_key),
+      )
+      .send_bytes(bytes)?;
+    Ok(())
+  }
+
+  /// Download the HTML page of a published article.
+  pub fn download_article(&self, url: &str) -> Result<String, ZhihuApiError> {
+    let html = self
+      .agent
+      .get(url)
+      .set("User-Agent", BROWSER_USER_AGENT)
+      .set(
+        "Accept",
+        "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+      )
+      .set("Accept-Language", ACCEPT_LANGUAGE)
+      .set("Cookie", &self.cookie)
+      .call()?
+      .into_string()?;
+    check_article_page(&html)?;
+    Ok(html)
+  }
+}
+
+fn parse_json<T: DeserializeOwned>(response: ureq::Response) -> Result<T, ZhihuApiError> {
+  let body = response.into_string()?;
+  serde_json::from_str(&body).map_err(|e| ZhihuApiError::InvalidResponse(e.to_string()))
+}
+
+/// Zhihu answers 200 with an error page in several cases, tell them apart by their content.
+fn check_article_page(html: &str) -> Result<(), ZhihuApiError> {
+  if html.is_empty() {
+    Err(ZhihuApiError::InvalidResponse(
+      "empty article page".to_string(),
+    ))
+  } else if html.contains("知乎，让每一次点击都充满意义") || html.contains("zh-zse-ck")
+  {
+    Err(ZhihuApiError::AntiCrawler)
+  } else if html.contains("有问题，就会有答案打开知乎App在「我的页」右上角打开扫一扫其他扫码方式")
+  {
+    Err(ZhihuApiError::LoginRequired)
+  } else if html.contains("你似乎来到了没有知识存在的荒原") {
+    Err(ZhihuApiError::NotFound)
+  } else {
+    Ok(())
+  }
+}
@@ -0,0 +1,85 @@
+use std::{error::Error, fmt};
+use zhihu_image::ZhihuImageError;
+
+#[derive(Debug)]
+pub enum ZhihuApiError {
+  /// The server answered with a non-2xx status, `message` is Zhihu's `error.message` if any.
+  Status {
+    status: u16,
+    message: String,
+  },
+  /// The request did not get a response (DNS, connection, TLS, ...).
+  Transport(String),
+  /// The response body is not what the endpoint should return.
+  InvalidResponse(String),
+  IoError(std::io::Error),
+  ImageError(ZhihuImageError),
+  /// Zhihu served its anti-crawler page instead of the article.
+  AntiCrawler,
+  /// The article is only visible when logged in.
+  LoginRequired,
+  NotFound,
+  /// No topic has exactly this name.
+  UnknownTopic(String),
+}
+
+impl fmt::Display for ZhihuApiError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ZhihuApiError::Status { status, message } => write!(f, "HTTP {}: {}", status, message),
+      ZhihuApiError::Transport(e) => write!(f, "Request failed: {}", e),
+      ZhihuApiError::InvalidResponse(e) => write!(f, "Invalid response: {}", e),
+      ZhihuApiError::IoError(e) => write!(f, "IO error: {}", e),
+      ZhihuApiError::ImageError(e) => write!(f, "Image error: {}", e),
+      ZhihuApiError::AntiCrawler => write!(
+        f,
+        "Anti-crawler page returned. Valid cookies required or IP might be blocked."
+      ),
+      ZhihuApiError::LoginRequired => write!(f, "Cookies are required to access the article."),
+      ZhihuApiError::NotFound => write!(f, "The page does not exist."),
+      ZhihuApiError::UnknownTopic(name) => write!(f, "No topic named `{}`", name),
+    }
+  }
+}
+
+impl Error for ZhihuApiError {}
+impl From<std::io::Error> for ZhihuApiError {
+  fn from(err: std::io::Error) -> Self {
+    ZhihuApiError::IoError(err)
+  }
+}
+
+impl From<ZhihuImageError> for ZhihuApiError {
+  fn from(err: ZhihuImageError) -> Self {
+    ZhihuApiError::ImageError(err)
+  }
+}
+
+impl From<ureq::Error> for ZhihuApiError {
+  fn from(err: ureq::Error) -> Self {
+    match err {
+      ureq::Error::Status(status, response) => {
+        let body = response.into_string().unwrap_or_default();
+        ZhihuApiError::Status {
+          status,
+          message: error_message(&body),
+        }
+      }
+      ureq::Error::Transport(transport) => ZhihuApiError::Transport(transport.to_string()),
+    }
+  }
+}
+
+impl From<ZhihuApiError> for mlua::Error {
+  fn from(err: ZhihuApiError) -> Self {
+    mlua::Error::external(err)
+  }
+}
+
+/// `{"error": {"message": "..."}}` is how Zhihu reports API errors, fall back to the raw body.
+fn error_message(body: &str) -> String {
+  serde_json::from_str::<serde_json::Value>(body)
+    .ok()
+    .and_then(|json| json["error"]["message"].as_str().map(str::to_string))
+    .unwrap_or_else(|| body.chars().take(200).collect())
+}
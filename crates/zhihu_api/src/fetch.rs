@@ -0,0 +1,57 @@
+use crate::client::BROWSER_USER_AGENT;
+use std::{io::Read, time::Duration};
+use zhihu_image::{Fetcher, ZhihuImageError};
+
+/// Images larger than this are refused, far above what Zhihu accepts.
+const MAX_IMAGE_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Downloads images from third-party hosts, without the Zhihu cookies.
+pub struct HttpFetcher {
+  agent: ureq::Agent,
+}
+
+impl Default for HttpFetcher {
+  fn default() -> Self {
+    Self {
+      agent: ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(30))
+        .user_agent(BROWSER_USER_AGENT)
+        .build(),
+    }
+  }
+}
+
+impl HttpFetcher {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl Fetcher for HttpFetcher {
+  fn fetch(&self, url: &str) -> Result<Vec<u8>, ZhihuImageError> {
+    let response = self
+      .agent
+      .get(url)
+      .set("Accept", "image/*,*/*;q=0.8")
+      .call()
+      .map_err(|e| match e {
+        ureq::Error::Status(status, _) => {
+          ZhihuImageError::Fetch(format!("HTTP {} for {}", status, url))
+        }
+        ureq::Error::Transport(transport) => ZhihuImageError::Fetch(transport.to_string()),
+      })?;
+    let mut bytes = Vec::new();
+    response
+      .into_reader()
+      .take(MAX_IMAGE_BYTES + 1)
+      .read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_IMAGE_BYTES {
+      return Err(ZhihuImageError::Fetch(format!(
+        "{} is larger than {} MiB",
+        url,
+        MAX_IMAGE_BYTES / 1024 / 1024
+      )));
+    }
+    Ok(bytes)
+  }
+}
@@ -0,0 +1,110 @@
+mod client;
+mod error;
+mod fetch;
+mod types;
+pub use client::{Endpoints, ZhihuClient, OSS_USER_AGENT};
+pub use error::ZhihuApiError;
+pub use fetch::HttpFetcher;
+use mlua::{Lua, LuaSerdeExt, Result as LuaResult, SerializeOptions, UserData, UserDataMethods};
+pub use types::{
+  CommentPermission, Draft, DraftCreated, ImageUpload, PublishSettings, Published, Topic,
+  UploadFile, UploadToken,
+};
+use zhihu_image::{localize_image, read_image_info};
+
+impl UserData for ZhihuClient {
+  fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+    // client:create_draft(draft: { title, content, table_of_contents?, delta_time? }) -> String
+    methods.add_method("create_draft", |lua, this, draft: mlua::Value| {
+      Ok(this.create_draft(&lua.from_value(draft)?)?)
+    });
+
+    // client:update_draft(draft_id: String, draft: table)
+    methods.add_method(
+      "update_draft",
+      |lua, this, (draft_id, draft): (String, mlua::Value)| {
+        Ok(this.update_draft(&draft_id, &lua.from_value(draft)?)?)
+      },
+    );
+
+    // client:publish_draft(draft_id: String, settings: { column?, topics?, comment_permission?,
+    //   original?, table_of_contents? }) -> { id, url }
+    methods.add_method(
+      "publish_draft",
+      |lua, this, (draft_id, settings): (String, mlua::Value)| {
+        let published = this.publish_draft(&draft_id, &lua.from_value(settings)?)?;
+        lua.to_value(&published)
+      },
+    );
+
+    // client:search_topics(name: String) -> { { id, name } }
+    methods.add_method("search_topics", |lua, this, name: String| {
+      lua.to_value(&this.search_topics(&name)?)
+    });
+
+    // client:image_upload(image_hash: String) -> { upload_vendor, upload_token?, upload_file }
+    methods.add_method("image_upload", |lua, this, image_hash: String| {
+      let upload = this.image_upload(&image_hash)?;
+      lua.to_value_with(
+        &upload,
+        SerializeOptions::new().serialize_none_to_null(false),
+      )
+    });
+
+    // client:upload_image(path: String, upload_token: table)
+    methods.add_method(
+      "upload_image",
+      |lua, this, (path, token): (String, mlua::Value)| {
+        let bytes = std::fs::read(&path).map_err(ZhihuApiError::from)?;
+        let info = read_image_info(&path).map_err(ZhihuApiError::from)?;
+        Ok(this.upload_image(&bytes, &info, &lua.from_value(token)?)?)
+      },
+    );
+
+    // client:download_article(url: String) -> String
+    methods.add_method("download_article", |_, this, url: String| {
+      Ok(this.download_article(&url)?)
+    });
+  }
+}
+
+#[mlua::lua_module]
+fn zhihu_api_lib(lua: &Lua) -> LuaResult<mlua::Table> {
+  let exports = lua.create_table()?;
+
+  // client(cookie: String, endpoints?: { zhuanlan?, api?, upload? }) -> ZhihuClient
+  exports.set(
+    "client",
+    lua.create_function(
+      |_, (cookie, endpoints): (String, Option<mlua::Table>)| -> LuaResult<ZhihuClient> {
+        let mut base = Endpoints::default();
+        if let Some(endpoints) = endpoints {
+          if let Some(url) = endpoints.get::<Option<String>>("zhuanlan")? {
+            base.zhuanlan = url;
+          }
+          if let Some(url) = endpoints.get::<Option<String>>("api")? {
+            base.api = url;
+          }
+          if let Some(url) = endpoints.get::<Option<String>>("upload")? {
+            base.upload = url;
+          }
+        }
+        Ok(ZhihuClient::with_endpoints(&cookie, base))
+      },
+    )?,
+  )?;
+
+  // fetch_image(uri: String, out_dir: String) -> String?
+  // Download a remote image or decode a data URI into `out_dir`, nil for local paths and images
+  // Zhihu already serves.
+  exports.set(
+    "fetch_image",
+    lua.create_function(|_, (uri, out_dir): (String, String)| {
+      let path =
+        localize_image(&uri, &out_dir, &HttpFetcher::new()).map_err(ZhihuApiError::from)?;
+      Ok(path.map(|path| path.to_string_lossy().into_owned()))
+    })?,
+  )?;
+
+  Ok(exports)
+}
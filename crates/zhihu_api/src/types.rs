@@ -0,0 +1,140 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Body of the draft create and update requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Draft {
+  pub title: String,
+  pub content: String,
+  pub table_of_contents: bool,
+  /// Seconds since the last save, as sent by the web editor.
+  pub delta_time: u32,
+  pub can_reward: bool,
+  /// Cover image URL.
+  #[serde(
+    rename = "titleImage",
+    alias = "title_image",
+    skip_serializing_if = "String::is_empty"
+  )]
+  pub title_image: String,
+}
+
+impl Draft {
+  pub fn new(title: &str, content: &str) -> Self {
+    Self {
+      title: title.to_string(),
+      content: content.to_string(),
+      ..Self::default()
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DraftCreated {
+  #[serde(deserialize_with = "string_or_number")]
+  pub id: String,
+}
+
+/// STS credentials to `PUT` an image to Zhihu's OSS bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadToken {
+  pub access_id: String,
+  /// Access key secret.
+  pub access_key: String,
+  /// Security token, sent as `x-oss-security-token`.
+  pub access_token: String,
+  #[serde(default)]
+  pub access_timestamp: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadFile {
+  #[serde(deserialize_with = "string_or_number")]
+  pub image_id: String,
+  #[serde(default)]
+  pub object_key: String,
+  /// 1 if Zhihu already has an image with this hash, 2 if it must be uploaded.
+  pub state: i64,
+}
+
+impl UploadFile {
+  pub fn needs_upload(&self) -> bool {
+    self.state == 2
+  }
+}
+
+/// Response of `POST /images`, which registers an image hash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageUpload {
+  #[serde(default)]
+  pub upload_vendor: String,
+  /// Absent when the image is already known.
+  pub upload_token: Option<UploadToken>,
+  pub upload_file: UploadFile,
+}
+
+/// Who may comment on a published article.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentPermission {
+  #[default]
+  Anyone,
+  /// Only people the author follows.
+  Followee,
+  /// Comments are held for review.
+  Censor,
+  Nobody,
+}
+
+impl CommentPermission {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "anyone" => Some(CommentPermission::Anyone),
+      "followee" => Some(CommentPermission::Followee),
+      "censor" => Some(CommentPermission::Censor),
+      "nobody" => Some(CommentPermission::Nobody),
+      _ => None,
+    }
+  }
+}
+
+/// How a draft is published.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PublishSettings {
+  /// Column (专栏) id to publish into, the author's own articles if `None`.
+  pub column: Option<String>,
+  /// Topic ids, or names looked up with [`crate::ZhihuClient::search_topics`].
+  pub topics: Vec<String>,
+  pub comment_permission: CommentPermission,
+  /// Declare the article as original content (原创声明).
+  pub original: bool,
+  pub table_of_contents: bool,
+}
+
+/// A topic (话题) that can be attached to an article.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Topic {
+  #[serde(deserialize_with = "string_or_number")]
+  pub id: String,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Published {
+  #[serde(deserialize_with = "string_or_number")]
+  pub id: String,
+  #[serde(default)]
+  pub url: String,
+}
+
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+  match serde_json::Value::deserialize(deserializer)? {
+    serde_json::Value::String(s) => Ok(s),
+    serde_json::Value::Number(n) => Ok(n.to_string()),
+    other => Err(serde::de::Error::custom(format!(
+      "expected a string or number, got {}",
+      other
+    ))),
+  }
+}
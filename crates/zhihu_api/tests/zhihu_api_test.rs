@@ -0,0 +1,381 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Response, Server};
+use zhihu_api::{
+  CommentPermission, Draft, Endpoints, HttpFetcher, PublishSettings, UploadToken, ZhihuApiError,
+  ZhihuClient, OSS_USER_AGENT,
+};
+use zhihu_image::{content_hash, image_info, localize_image, Fetcher, OssRequest, ZhihuImageError};
+
+#[derive(Debug, Clone)]
+struct Recorded {
+  method: String,
+  path: String,
+  headers: Vec<(String, String)>,
+  body: Vec<u8>,
+}
+
+impl Recorded {
+  fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|(key, _)| key.eq_ignore_ascii_case(name))
+      .map(|(_, value)| value.as_str())
+  }
+
+  fn json(&self) -> serde_json::Value {
+    serde_json::from_slice(&self.body).unwrap()
+  }
+}
+
+/// Local stand-in for the Zhihu services: answers `(method, path)` routes with canned responses
+/// and records every request it receives.
+struct MockServer {
+  server: Arc<Server>,
+  url: String,
+  requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl MockServer {
+  fn start(routes: Vec<(&'static str, &'static str, u16, &'static str)>) -> Self {
+    let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let (incoming, recorded) = (server.clone(), requests.clone());
+    thread::spawn(move || {
+      for mut request in incoming.incoming_requests() {
+        let mut body = Vec::new();
+        request.as_reader().read_to_end(&mut body).unwrap();
+        let method = request.method().as_str().to_string();
+        let path = request.url().to_string();
+        let route = routes
+          .iter()
+          .find(|(m, p, _, _)| *m == method && *p == path);
+        recorded.lock().unwrap().push(Recorded {
+          method,
+          path,
+          headers: request
+            .headers()
+            .iter()
+            .map(|h| (h.field.to_string(), h.value.to_string()))
+            .collect(),
+          body,
+        });
+        let response = match route {
+          Some((_, _, status, body)) => Response::from_string(*body)
+            .with_status_code(*status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").unwrap()),
+          None => Response::from_string("").with_status_code(404),
+        };
+        request.respond(response).unwrap();
+      }
+    });
+    Self {
+      server,
+      url,
+      requests,
+    }
+  }
+
+  fn client(&self) -> ZhihuClient {
+    ZhihuClient::with_endpoints("z_c0=token", Endpoints::single(&self.url))
+  }
+
+  fn requests(&self) -> Vec<Recorded> {
+    self.requests.lock().unwrap().clone()
+  }
+}
+
+impl Drop for MockServer {
+  fn drop(&mut self) {
+    self.server.unblock();
+  }
+}
+
+fn png() -> Vec<u8> {
+  let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+  bytes.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 2, 8, 6, 0, 0, 0, 0, 0, 0, 0]);
+  bytes
+}
+
+#[test]
+fn test_create_draft() {
+  let server = MockServer::start(vec![(
+    "POST",
+    "/api/articles/drafts",
+    200,
+    r#"{"id": 1919810, "title": "标题"}"#,
+  )]);
+  let draft = Draft {
+    table_of_contents: true,
+    ..Draft::new("标题", "<p>内容</p>")
+  };
+  assert_eq!(server.client().create_draft(&draft).unwrap(), "1919810");
+
+  let requests = server.requests();
+  assert_eq!(requests.len(), 1);
+  assert_eq!(requests[0].header("Cookie"), Some("z_c0=token"));
+  assert_eq!(requests[0].header("x-requested-with"), Some("fetch"));
+  let body = requests[0].json();
+  assert_eq!(body["title"], "标题");
+  assert_eq!(body["content"], "<p>内容</p>");
+  assert_eq!(body["table_of_contents"], true);
+  assert_eq!(body["delta_time"], 0);
+}
+
+#[test]
+fn test_update_draft() {
+  let server = MockServer::start(vec![("PATCH", "/api/articles/42/draft", 200, "{}")]);
+  let draft = Draft {
+    delta_time: 30,
+    ..Draft::new("标题", "<p>新内容</p>")
+  };
+  server.client().update_draft("42", &draft).unwrap();
+
+  let requests = server.requests();
+  assert_eq!(requests[0].method, "PATCH");
+  assert_eq!(requests[0].path, "/api/articles/42/draft");
+  assert_eq!(requests[0].json()["content"], "<p>新内容</p>");
+  assert_eq!(requests[0].json()["delta_time"], 30);
+}
+
+#[test]
+fn test_status_error_message() {
+  let server = MockServer::start(vec![(
+    "POST",
+    "/api/articles/drafts",
+    401,
+    r#"{"error": {"code": 100, "message": "请求参数异常，请升级客户端后重试"}}"#,
+  )]);
+  match server.client().create_draft(&Draft::new("t", "c")) {
+    Err(ZhihuApiError::Status { status, message }) => {
+      assert_eq!(status, 401);
+      assert_eq!(message, "请求参数异常，请升级客户端后重试");
+    }
+    other => panic!("unexpected result: {:?}", other),
+  }
+}
+
+#[test]
+fn test_invalid_response() {
+  let server = MockServer::start(vec![("POST", "/api/articles/drafts", 200, "<html>")]);
+  assert!(matches!(
+    server.client().create_draft(&Draft::new("t", "c")),
+    Err(ZhihuApiError::InvalidResponse(_))
+  ));
+}
+
+#[test]
+fn test_transport_error() {
+  let client = ZhihuClient::with_endpoints("", Endpoints::single("http://127.0.0.1:1"));
+  assert!(matches!(
+    client.create_draft(&Draft::new("t", "c")),
+    Err(ZhihuApiError::Transport(_))
+  ));
+}
+
+#[test]
+fn test_image_upload_and_put() {
+  let server = MockServer::start(vec![
+    (
+      "POST",
+      "/images",
+      200,
+      r#"{"upload_vendor": "aliyun", "upload_token": {"access_id": "STS.id", "access_key": "secret", "access_token": "CAIS+token", "access_timestamp": 1700000000}, "upload_file": {"image_id": 1234, "object_key": "v2-abc", "state": 2}}"#,
+    ),
+    ("PUT", "/v2-b578be334f41b634aeb5af6c3ac4674f", 200, ""),
+  ]);
+  let bytes = png();
+  let info = image_info(&bytes).unwrap();
+  let client = server.client();
+
+  let upload = client.image_upload(&info.hash).unwrap();
+  assert_eq!(upload.upload_file.image_id, "1234");
+  assert!(upload.upload_file.needs_upload());
+  let token: UploadToken = upload.upload_token.unwrap();
+  assert_eq!(token.access_id, "STS.id");
+
+  let requests = server.requests();
+  assert_eq!(requests[0].json()["image_hash"], info.hash.as_str());
+  assert_eq!(requests[0].json()["source"], "article");
+
+  assert_eq!(info.hash, "b578be334f41b634aeb5af6c3ac4674f");
+  client.upload_image(&bytes, &info, &token).unwrap();
+  let put = &server.requests()[1];
+  assert_eq!(put.method, "PUT");
+  assert_eq!(put.body, bytes);
+  assert_eq!(put.header("Content-Type"), Some("image/png"));
+  assert_eq!(put.header("x-oss-security-token"), Some("CAIS+token"));
+  let date = put.header("x-oss-date").unwrap();
+  let expected =
+    OssRequest::zhihu_image_upload(&info.hash, "image/png", date, "CAIS+token", OSS_USER_AGENT)
+      .authorization("STS.id", "secret");
+  assert_eq!(put.header("Authorization"), Some(expected.as_str()));
+}
+
+#[test]
+fn test_image_already_uploaded() {
+  let server = MockServer::start(vec![(
+    "POST",
+    "/images",
+    200,
+    r#"{"upload_file": {"image_id": "v2-1", "object_key": "v2-abc", "state": 1}}"#,
+  )]);
+  let upload = server.client().image_upload("abc").unwrap();
+  assert!(!upload.upload_file.needs_upload());
+  assert!(upload.upload_token.is_none());
+}
+
+#[test]
+fn test_download_article() {
+  let server = MockServer::start(vec![
+    (
+      "GET",
+      "/p/1",
+      200,
+      "<html><div class=\"Post-RichText\">正文</div></html>",
+    ),
+    (
+      "GET",
+      "/p/2",
+      200,
+      "<html><script>zh-zse-ck</script></html>",
+    ),
+    (
+      "GET",
+      "/p/3",
+      200,
+      "<html>你似乎来到了没有知识存在的荒原</html>",
+    ),
+  ]);
+  let client = server.client();
+  let html = client
+    .download_article(&format!("{}/p/1", server.url))
+    .unwrap();
+  assert!(html.contains("正文"));
+  assert!(matches!(
+    client.download_article(&format!("{}/p/2", server.url)),
+    Err(ZhihuApiError::AntiCrawler)
+  ));
+  assert!(matches!(
+    client.download_article(&format!("{}/p/3", server.url)),
+    Err(ZhihuApiError::NotFound)
+  ));
+}
+
+#[test]
+fn test_publish_draft() {
+  let server = MockServer::start(vec![
+    (
+      "GET",
+      "/api/autocomplete/topics?token=Rust&max_matches=5",
+      200,
+      r#"[{"id": "19551292", "name": "Rust（编程语言）"}, {"id": 20031813, "name": "Rust"}]"#,
+    ),
+    ("POST", "/api/articles/42/topics", 200, "{}"),
+    (
+      "PUT",
+      "/api/articles/42/publish",
+      200,
+      r#"{"id": 42, "url": "https://zhuanlan.zhihu.com/p/42"}"#,
+    ),
+  ]);
+  let settings = PublishSettings {
+    column: Some("c_1234".to_string()),
+    topics: vec!["Rust".to_string(), "19550517".to_string()],
+    comment_permission: CommentPermission::Censor,
+    original: true,
+    table_of_contents: false,
+  };
+  let published = server.client().publish_draft("42", &settings).unwrap();
+  assert_eq!(published.id, "42");
+  assert_eq!(published.url, "https://zhuanlan.zhihu.com/p/42");
+
+  let requests = server.requests();
+  assert_eq!(requests.len(), 4);
+  assert_eq!(requests[1].json()["id"], "20031813");
+  assert_eq!(requests[2].json()["id"], "19550517");
+  let body = requests[3].json();
+  assert_eq!(requests[3].method, "PUT");
+  assert_eq!(body["column"]["id"], "c_1234");
+  assert_eq!(body["commentPermission"], "censor");
+  assert_eq!(body["is_original"], true);
+}
+
+#[test]
+fn test_publish_draft_unknown_topic() {
+  let server = MockServer::start(vec![(
+    "GET",
+    "/api/autocomplete/topics?token=Rust&max_matches=5",
+    200,
+    r#"[{"id": "19551292", "name": "Rust（编程语言）"}]"#,
+  )]);
+  let settings = PublishSettings {
+    topics: vec!["Rust".to_string()],
+    ..PublishSettings::default()
+  };
+  match server.client().publish_draft("42", &settings) {
+    Err(ZhihuApiError::UnknownTopic(name)) => assert_eq!(name, "Rust"),
+    other => panic!("unexpected result: {:?}", other),
+  }
+  assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_comment_permission_parse() {
+  assert_eq!(
+    CommentPermission::parse("nobody"),
+    Some(CommentPermission::Nobody)
+  );
+  assert_eq!(CommentPermission::parse("everyone"), None);
+}
+
+#[test]
+fn test_fetch_remote_image() {
+  // Binary bodies, which the JSON routes of `MockServer` do not serve
+  let server = Server::http("127.0.0.1:0").unwrap();
+  let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+  let handle = thread::spawn(move || {
+    let mut headers = Vec::new();
+    for _ in 0..2 {
+      let request = server.recv().unwrap();
+      headers.push(
+        request
+          .headers()
+          .iter()
+          .map(|h| (h.field.to_string(), h.value.to_string()))
+          .collect::<Vec<_>>(),
+      );
+      let response = match request.url() {
+        "/figure.png" => Response::from_data(png()),
+        _ => Response::from_data(Vec::new()).with_status_code(404),
+      };
+      request.respond(response).unwrap();
+    }
+    headers
+  });
+
+  let dir = std::env::temp_dir().join(format!("zhihu_api_fetch_{}", std::process::id()));
+  let fetcher = HttpFetcher::new();
+  let out = localize_image(&format!("{}/figure.png", url), &dir, &fetcher)
+    .unwrap()
+    .unwrap();
+  assert_eq!(out, dir.join(format!("{}.png", content_hash(&png()))));
+  assert_eq!(std::fs::read(&out).unwrap(), png());
+  match fetcher.fetch(&format!("{}/missing.png", url)) {
+    Err(ZhihuImageError::Fetch(message)) => assert!(message.contains("404"), "{}", message),
+    other => panic!("unexpected result: {:?}", other),
+  }
+
+  // Third-party hosts get no Zhihu cookies
+  for headers in handle.join().unwrap() {
+    assert!(headers
+      .iter()
+      .all(|(key, _)| !key.eq_ignore_ascii_case("cookie")));
+    assert!(headers
+      .iter()
+      .any(|(key, value)| key.eq_ignore_ascii_case("user-agent") && value.starts_with("Mozilla/")));
+  }
+  std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,181 @@
+use crate::ZhihuImageError;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::{
+  path::Path,
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How an image got onto Zhihu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheState {
+  /// Zhihu already had the image, `upload_file.state == 1`.
+  Existing,
+  /// The image was uploaded to the bucket, `upload_file.state == 2`.
+  Uploaded,
+}
+
+impl CacheState {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      CacheState::Existing => "existing",
+      CacheState::Uploaded => "uploaded",
+    }
+  }
+
+  pub fn parse(state: &str) -> Option<Self> {
+    match state {
+      "existing" => Some(CacheState::Existing),
+      "uploaded" => Some(CacheState::Uploaded),
+      _ => None,
+    }
+  }
+}
+
+/// An image Zhihu serves, by the content hash of the local file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedImage {
+  pub hash: String,
+  /// Final `picx.zhimg.com` URL of the image.
+  pub url: String,
+  pub state: CacheState,
+  /// Seconds since the Unix epoch when the image was cached.
+  pub cached_at: i64,
+}
+
+impl CachedImage {
+  pub fn new(hash: &str, url: &str, state: CacheState) -> Self {
+    let cached_at = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs() as i64)
+      .unwrap_or_default();
+    CachedImage {
+      hash: hash.to_string(),
+      url: url.to_string(),
+      state,
+      cached_at,
+    }
+  }
+
+  fn from_row(row: &Row) -> rusqlite::Result<Self> {
+    let state: String = row.get(2)?;
+    Ok(CachedImage {
+      hash: row.get(0)?,
+      url: row.get(1)?,
+      // Unknown states come from a newer version, which only ever records served images
+      state: CacheState::parse(&state).unwrap_or(CacheState::Existing),
+      cached_at: row.get(3)?,
+    })
+  }
+}
+
+/// Persistent cache of the images already on Zhihu, so that unchanged images skip the API.
+pub struct ImageCache {
+  conn: Connection,
+}
+
+impl ImageCache {
+  /// Open the cache database, creating it if needed.
+  pub fn open(path: impl AsRef<Path>) -> Result<Self, ZhihuImageError> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+      std::fs::create_dir_all(dir)?;
+    }
+    Self::init(Connection::open(path)?)
+  }
+
+  /// A cache which lives as long as the value.
+  pub fn in_memory() -> Result<Self, ZhihuImageError> {
+    Self::init(Connection::open_in_memory()?)
+  }
+
+  fn init(conn: Connection) -> Result<Self, ZhihuImageError> {
+    // Several Neovim instances may upload at once
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    conn.execute_batch(
+      "CREATE TABLE IF NOT EXISTS images (
+        hash TEXT PRIMARY KEY,
+        url TEXT NOT NULL,
+        state TEXT NOT NULL,
+        cached_at INTEGER NOT NULL
+      );
+      CREATE TABLE IF NOT EXISTS sources (
+        uri TEXT PRIMARY KEY,
+        hash TEXT NOT NULL
+      )",
+    )?;
+    Ok(ImageCache { conn })
+  }
+
+  pub fn get(&self, hash: &str) -> Result<Option<CachedImage>, ZhihuImageError> {
+    Ok(
+      self
+        .conn
+        .query_row(
+          "SELECT hash, url, state, cached_at FROM images WHERE hash = ?",
+          [hash],
+          CachedImage::from_row,
+        )
+        .optional()?,
+    )
+  }
+
+  /// The image uploaded from a remote URL or `data:` URI, so that it is not downloaded again.
+  pub fn get_source(&self, uri: &str) -> Result<Option<CachedImage>, ZhihuImageError> {
+    Ok(
+      self
+        .conn
+        .query_row(
+          "SELECT images.hash, url, state, cached_at FROM sources
+          JOIN images ON images.hash = sources.hash WHERE uri = ?",
+          [uri],
+          CachedImage::from_row,
+        )
+        .optional()?,
+    )
+  }
+
+  /// Record the content hash of the image downloaded from `uri`.
+  pub fn insert_source(&self, uri: &str, hash: &str) -> Result<(), ZhihuImageError> {
+    self.conn.execute(
+      "INSERT OR REPLACE INTO sources (uri, hash) VALUES (?, ?)",
+      params![uri, hash],
+    )?;
+    Ok(())
+  }
+
+  /// Record an image, replacing the entry of the same hash.
+  pub fn insert(&self, image: &CachedImage) -> Result<(), ZhihuImageError> {
+    self.conn.execute(
+      "INSERT OR REPLACE INTO images (hash, url, state, cached_at) VALUES (?, ?, ?, ?)",
+      params![image.hash, image.url, image.state.as_str(), image.cached_at],
+    )?;
+    Ok(())
+  }
+
+  /// Forget an image, returns whether it was cached.
+  pub fn invalidate(&self, hash: &str) -> Result<bool, ZhihuImageError> {
+    Ok(
+      self
+        .conn
+        .execute("DELETE FROM images WHERE hash = ?", [hash])?
+        > 0,
+    )
+  }
+
+  /// Forget every image, returns how many were cached.
+  pub fn clear(&self) -> Result<usize, ZhihuImageError> {
+    self.conn.execute("DELETE FROM sources", [])?;
+    Ok(self.conn.execute("DELETE FROM images", [])?)
+  }
+
+  /// Every cached image, the most recent first.
+  pub fn entries(&self) -> Result<Vec<CachedImage>, ZhihuImageError> {
+    let mut stmt = self
+      .conn
+      .prepare("SELECT hash, url, state, cached_at FROM images ORDER BY cached_at DESC, hash")?;
+    let entries = stmt
+      .query_map([], CachedImage::from_row)?
+      .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+  }
+}
@@ -0,0 +1,87 @@
+use crate::ZhihuImageError;
+use md5::{Digest, Md5};
+use std::fs;
+use std::path::Path;
+
+/// Image formats accepted by Zhihu's image upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+  Jpeg,
+  Png,
+  Gif,
+  Webp,
+  Bmp,
+}
+
+impl ImageFormat {
+  /// Detect the format from the magic bytes at the start of the file.
+  pub fn sniff(bytes: &[u8]) -> Option<Self> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+      Some(ImageFormat::Jpeg)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+      Some(ImageFormat::Png)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+      Some(ImageFormat::Gif)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+      Some(ImageFormat::Webp)
+    } else if bytes.starts_with(b"BM") {
+      Some(ImageFormat::Bmp)
+    } else {
+      None
+    }
+  }
+
+  pub fn mime_type(self) -> &'static str {
+    match self {
+      ImageFormat::Jpeg => "image/jpeg",
+      ImageFormat::Png => "image/png",
+      ImageFormat::Gif => "image/gif",
+      ImageFormat::Webp => "image/webp",
+      ImageFormat::Bmp => "image/bmp",
+    }
+  }
+
+  /// Extension used in `picx.zhimg.com` URLs, the subtype of the MIME type.
+  pub fn extension(self) -> &'static str {
+    match self {
+      ImageFormat::Jpeg => "jpeg",
+      ImageFormat::Png => "png",
+      ImageFormat::Gif => "gif",
+      ImageFormat::Webp => "webp",
+      ImageFormat::Bmp => "bmp",
+    }
+  }
+}
+
+/// What the upload needs to know about an image file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+  /// Content hash Zhihu uses as image key (`image_hash`, `v2-<hash>`).
+  pub hash: String,
+  pub format: ImageFormat,
+  pub width: usize,
+  pub height: usize,
+}
+
+/// Lowercase hex MD5 of the image content, as `openssl dgst -md5` printed it.
+pub fn content_hash(bytes: &[u8]) -> String {
+  format!("{:x}", Md5::digest(bytes))
+}
+
+/// Hash, sniff and measure an image held in memory.
+pub fn image_info(bytes: &[u8]) -> Result<ImageInfo, ZhihuImageError> {
+  let format = ImageFormat::sniff(bytes).ok_or(ZhihuImageError::UnsupportedFormat)?;
+  let size =
+    imagesize::blob_size(bytes).map_err(|e| ZhihuImageError::InvalidImage(e.to_string()))?;
+  Ok(ImageInfo {
+    hash: content_hash(bytes),
+    format,
+    width: size.width,
+    height: size.height,
+  })
+}
+
+/// Hash, sniff and measure the image file at `path`.
+pub fn read_image_info(path: impl AsRef<Path>) -> Result<ImageInfo, ZhihuImageError> {
+  image_info(&fs::read(path)?)
+}
@@ -0,0 +1,295 @@
+mod cache;
+mod info;
+mod oss;
+mod pdf;
+mod preprocess;
+mod rasterize;
+mod remote;
+pub use cache::{CacheState, CachedImage, ImageCache};
+pub use info::{content_hash, image_info, read_image_info, ImageFormat, ImageInfo};
+use mlua::{Error as LuaError, Lua, Result as LuaResult, UserData, UserDataMethods};
+pub use oss::{http_date, sign, OssRequest, ZHIHU_PICS_BUCKET, ZHIHU_PICS_UPLOAD_URL};
+pub use preprocess::{preprocess, preprocess_file, ConvertTarget, PreprocessOptions, Preprocessed};
+pub use rasterize::{parse_color, rasterize, rasterize_file, RasterizeOptions, VectorFormat};
+pub use remote::{decode_data_uri, is_zhihu_image_url, localize_image, Fetcher};
+use std::{error::Error, fmt};
+
+#[derive(Debug)]
+pub enum ZhihuImageError {
+  IoError(std::io::Error),
+  UnsupportedFormat,
+  InvalidImage(String),
+  CacheError(rusqlite::Error),
+  Rasterize(String),
+  Fetch(String),
+}
+
+impl fmt::Display for ZhihuImageError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ZhihuImageError::IoError(e) => write!(f, "IO error: {}", e),
+      ZhihuImageError::UnsupportedFormat => {
+        write!(
+          f,
+          "Unsupported image format, use JPEG, PNG, GIF, WebP or BMP"
+        )
+      }
+      ZhihuImageError::InvalidImage(e) => write!(f, "Invalid image: {}", e),
+      ZhihuImageError::CacheError(e) => write!(f, "Image cache error: {}", e),
+      ZhihuImageError::Rasterize(e) => write!(f, "Cannot rasterize figure: {}", e),
+      ZhihuImageError::Fetch(e) => write!(f, "Cannot download image: {}", e),
+    }
+  }
+}
+
+impl Error for ZhihuImageError {}
+impl From<std::io::Error> for ZhihuImageError {
+  fn from(err: std::io::Error) -> Self {
+    ZhihuImageError::IoError(err)
+  }
+}
+
+impl From<rusqlite::Error> for ZhihuImageError {
+  fn from(err: rusqlite::Error) -> Self {
+    ZhihuImageError::CacheError(err)
+  }
+}
+
+impl From<ZhihuImageError> for LuaError {
+  fn from(err: ZhihuImageError) -> Self {
+    LuaError::external(err)
+  }
+}
+
+/// A dimension limit, `false` or `0` lifts the default one.
+fn max_dimension_from_lua(value: mlua::Value, default: Option<u32>) -> LuaResult<Option<u32>> {
+  match value {
+    mlua::Value::Nil => Ok(default),
+    mlua::Value::Boolean(false) => Ok(None),
+    mlua::Value::Integer(0) => Ok(None),
+    mlua::Value::Integer(n) => u32::try_from(n)
+      .map(Some)
+      .map_err(|_| LuaError::runtime(format!("Invalid image dimension limit {}", n))),
+    value => Err(LuaError::runtime(format!(
+      "Image dimension limit should be an integer, got {}",
+      value.type_name()
+    ))),
+  }
+}
+
+fn preprocess_options_from_lua(table: &mlua::Table) -> LuaResult<PreprocessOptions> {
+  let mut options = PreprocessOptions::default();
+  options.max_width = max_dimension_from_lua(table.get("max_width")?, options.max_width)?;
+  options.max_height = max_dimension_from_lua(table.get("max_height")?, options.max_height)?;
+  if let Some(quality) = table.get::<Option<u8>>("jpeg_quality")? {
+    options.jpeg_quality = quality;
+  }
+  if let Some(strip_metadata) = table.get::<Option<bool>>("strip_metadata")? {
+    options.strip_metadata = strip_metadata;
+  }
+  if let Some(keep_webp) = table.get::<Option<bool>>("keep_webp")? {
+    options.keep_webp = keep_webp;
+  }
+  if let Some(convert_to) = table.get::<Option<String>>("convert_to")? {
+    options.convert_to = ConvertTarget::parse(&convert_to).ok_or_else(|| {
+      LuaError::runtime(format!(
+        "Unknown image format `{}`, use auto, png or jpeg",
+        convert_to
+      ))
+    })?;
+  }
+  Ok(options)
+}
+
+fn rasterize_options_from_lua(table: &mlua::Table) -> LuaResult<RasterizeOptions> {
+  let mut options = RasterizeOptions::default();
+  if let Some(dpi) = table.get::<Option<f32>>("dpi")? {
+    options.dpi = dpi;
+  }
+  // A `#rrggbb` color, or `false` for a transparent background
+  match table.get::<mlua::Value>("background")? {
+    mlua::Value::Nil => {}
+    mlua::Value::Boolean(false) => options.background = None,
+    mlua::Value::String(color) => {
+      let color = color.to_str()?;
+      options.background = Some(parse_color(&color).ok_or_else(|| {
+        LuaError::runtime(format!(
+          "Invalid background color `{}`, use #rrggbb or #rrggbbaa",
+          color
+        ))
+      })?);
+    }
+    value => {
+      return Err(LuaError::runtime(format!(
+        "Background should be a color or false, got {}",
+        value.type_name()
+      )))
+    }
+  }
+  Ok(options)
+}
+
+fn cached_image_to_lua(lua: &Lua, image: CachedImage) -> LuaResult<mlua::Table> {
+  let table = lua.create_table()?;
+  table.set("hash", image.hash)?;
+  table.set("url", image.url)?;
+  table.set("state", image.state.as_str())?;
+  table.set("cached_at", image.cached_at)?;
+  Ok(table)
+}
+
+impl UserData for ImageCache {
+  fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+    // cache:get(hash: String) -> { hash, url, state, cached_at }?
+    methods.add_method("get", |lua, this, hash: String| {
+      this
+        .get(&hash)?
+        .map(|image| cached_image_to_lua(lua, image))
+        .transpose()
+    });
+
+    // cache:put(hash: String, url: String, state: "existing"|"uploaded")
+    methods.add_method(
+      "put",
+      |_, this, (hash, url, state): (String, String, String)| {
+        let state = CacheState::parse(&state).ok_or_else(|| {
+          LuaError::RuntimeError(format!(
+            "Unknown image cache state `{}`, use `existing` or `uploaded`",
+            state
+          ))
+        })?;
+        Ok(this.insert(&CachedImage::new(&hash, &url, state))?)
+      },
+    );
+
+    // cache:get_source(uri: String) -> { hash, url, state, cached_at }?
+    methods.add_method("get_source", |lua, this, uri: String| {
+      this
+        .get_source(&uri)?
+        .map(|image| cached_image_to_lua(lua, image))
+        .transpose()
+    });
+
+    // cache:put_source(uri: String, hash: String)
+    methods.add_method("put_source", |_, this, (uri, hash): (String, String)| {
+      Ok(this.insert_source(&uri, &hash)?)
+    });
+
+    // cache:invalidate(hash: String) -> bool
+    methods.add_method("invalidate", |_, this, hash: String| {
+      Ok(this.invalidate(&hash)?)
+    });
+
+    // cache:clear() -> integer
+    methods.add_method("clear", |_, this, ()| Ok(this.clear()?));
+
+    // cache:entries() -> { { hash, url, state, cached_at } }
+    methods.add_method("entries", |lua, this, ()| {
+      let table = lua.create_table()?;
+      for (i, image) in this.entries()?.into_iter().enumerate() {
+        table.set(i + 1, cached_image_to_lua(lua, image)?)?;
+      }
+      Ok(table)
+    });
+  }
+}
+
+#[mlua::lua_module]
+fn zhihu_image_lib(lua: &Lua) -> LuaResult<mlua::Table> {
+  let exports = lua.create_table()?;
+
+  // hash_file(path: String) -> String
+  exports.set(
+    "hash_file",
+    lua.create_function(|_, path: String| -> LuaResult<String> {
+      let bytes = std::fs::read(&path).map_err(ZhihuImageError::from)?;
+      Ok(content_hash(&bytes))
+    })?,
+  )?;
+
+  // image_info(path: String) -> { hash, mime_type, extension, width, height }
+  exports.set(
+    "image_info",
+    lua.create_function(|lua, path: String| -> LuaResult<mlua::Table> {
+      let info = read_image_info(&path)?;
+      let table = lua.create_table()?;
+      table.set("hash", info.hash)?;
+      table.set("mime_type", info.format.mime_type())?;
+      table.set("extension", info.format.extension())?;
+      table.set("width", info.width)?;
+      table.set("height", info.height)?;
+      Ok(table)
+    })?,
+  )?;
+
+  // preprocess(path: String, out_dir: String, opts?: { max_width?, max_height?, jpeg_quality?,
+  //   strip_metadata?, keep_webp?, convert_to? }) -> String
+  exports.set(
+    "preprocess",
+    lua.create_function(
+      |_, (path, out_dir, opts): (String, String, Option<mlua::Table>)| -> LuaResult<String> {
+        let options = match opts {
+          Some(opts) => preprocess_options_from_lua(&opts)?,
+          None => PreprocessOptions::default(),
+        };
+        let out = preprocess_file(&path, &out_dir, &options)?;
+        Ok(out.to_string_lossy().into_owned())
+      },
+    )?,
+  )?;
+
+  // rasterize(path: String, out_dir: String, opts?: { dpi?, background? }) -> String
+  exports.set(
+    "rasterize",
+    lua.create_function(
+      |_, (path, out_dir, opts): (String, String, Option<mlua::Table>)| -> LuaResult<String> {
+        let options = match opts {
+          Some(opts) => rasterize_options_from_lua(&opts)?,
+          None => RasterizeOptions::default(),
+        };
+        let out = rasterize_file(&path, &out_dir, &options)?;
+        Ok(out.to_string_lossy().into_owned())
+      },
+    )?,
+  )?;
+
+  // cache(path: String) -> ImageCache
+  exports.set(
+    "cache",
+    lua.create_function(|_, path: String| -> LuaResult<ImageCache> {
+      Ok(ImageCache::open(&path)?)
+    })?,
+  )?;
+
+  // sign_upload({ access_id, access_key, security_token, user_agent, hash, mime_type, date? })
+  //   -> { authorization, date, url }
+  exports.set(
+    "sign_upload",
+    lua.create_function(|lua, opts: mlua::Table| -> LuaResult<mlua::Table> {
+      let date = opts
+        .get::<Option<String>>("date")?
+        .unwrap_or_else(http_date);
+      let hash: String = opts.get("hash")?;
+      let request = OssRequest::zhihu_image_upload(
+        &hash,
+        &opts.get::<String>("mime_type")?,
+        &date,
+        &opts.get::<String>("security_token")?,
+        &opts.get::<String>("user_agent")?,
+      );
+      let table = lua.create_table()?;
+      table.set(
+        "authorization",
+        request.authorization(
+          &opts.get::<String>("access_id")?,
+          &opts.get::<String>("access_key")?,
+        ),
+      )?;
+      table.set("date", date)?;
+      table.set("url", format!("{}/v2-{}", ZHIHU_PICS_UPLOAD_URL, hash))?;
+      Ok(table)
+    })?,
+  )?;
+
+  Ok(exports)
+}
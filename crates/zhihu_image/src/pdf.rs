@@ -0,0 +1,106 @@
+//! PDF figures are rendered by poppler's `pdftocairo` or MuPDF's `mutool`, whichever is
+//! installed, which handle the fonts, shadings and images LaTeX and matplotlib export.
+
+use std::{
+  ffi::OsString,
+  fs, io,
+  path::PathBuf,
+  process::{Command, Stdio},
+  sync::atomic::{AtomicU32, Ordering},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Private directory the PDF file and its rendering are written to, removed on drop.
+struct WorkDir(PathBuf);
+
+impl WorkDir {
+  fn new() -> io::Result<Self> {
+    static COUNT: AtomicU32 = AtomicU32::new(0);
+    let nanos = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |d| d.subsec_nanos());
+    let name = format!(
+      "zhihu_image-pdf-{}-{}-{:x}",
+      std::process::id(),
+      COUNT.fetch_add(1, Ordering::Relaxed),
+      nanos
+    );
+    let path = std::env::temp_dir().join(name);
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    // Fails rather than reusing a directory someone else created
+    builder.create(&path)?;
+    Ok(WorkDir(path))
+  }
+}
+
+impl Drop for WorkDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.0);
+  }
+}
+
+/// Render the first page of `pdf` to a transparent PNG image at `dpi`.
+pub(crate) fn render_png(pdf: &[u8], dpi: f32) -> Result<Vec<u8>, String> {
+  let dir = WorkDir::new().map_err(|e| format!("cannot create a temporary directory: {}", e))?;
+  let input = dir.0.join("figure.pdf");
+  let output = dir.0.join("figure.png");
+  fs::write(&input, pdf).map_err(|e| e.to_string())?;
+  let dpi = dpi.to_string();
+  let renderers: [(&str, Vec<OsString>); 2] = [
+    (
+      "pdftocairo",
+      vec![
+        "-png".into(),
+        "-singlefile".into(),
+        "-transp".into(),
+        "-f".into(),
+        "1".into(),
+        "-l".into(),
+        "1".into(),
+        "-r".into(),
+        dpi.clone().into(),
+        input.clone().into(),
+        // `-singlefile` adds the extension
+        dir.0.join("figure").into(),
+      ],
+    ),
+    (
+      "mutool",
+      vec![
+        "draw".into(),
+        "-r".into(),
+        dpi.into(),
+        "-c".into(),
+        "rgba".into(),
+        "-F".into(),
+        "png".into(),
+        "-o".into(),
+        output.clone().into(),
+        input.into(),
+        "1".into(),
+      ],
+    ),
+  ];
+  for (program, args) in renderers {
+    let out = match Command::new(program)
+      .args(args)
+      .stdin(Stdio::null())
+      .output()
+    {
+      Ok(out) => out,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+      Err(e) => return Err(format!("cannot run `{}`: {}", program, e)),
+    };
+    if !out.status.success() {
+      return Err(format!(
+        "`{}` failed: {}",
+        program,
+        String::from_utf8_lossy(&out.stderr).trim()
+      ));
+    }
+    return fs::read(&output).map_err(|e| format!("`{}` wrote no image: {}", program, e));
+  }
+  Err("PDF figures need `pdftocairo` (poppler) or `mutool` (MuPDF) installed".to_string())
+}
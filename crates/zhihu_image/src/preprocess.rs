@@ -0,0 +1,274 @@
+use crate::{content_hash, ImageFormat, ZhihuImageError};
+use image::{
+  codecs::{
+    jpeg::JpegEncoder,
+    png::{CompressionType, FilterType as PngFilter, PngEncoder},
+  },
+  imageops::FilterType,
+  DynamicImage, ImageDecoder, ImageReader,
+};
+use std::{
+  fs,
+  io::Cursor,
+  path::{Path, PathBuf},
+};
+
+/// Format images are converted to when Zhihu does not take theirs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvertTarget {
+  /// PNG for images with transparency or from lossless formats, JPEG for photos.
+  Auto,
+  Png,
+  Jpeg,
+}
+
+impl ConvertTarget {
+  pub fn parse(target: &str) -> Option<Self> {
+    match target {
+      "auto" => Some(ConvertTarget::Auto),
+      "png" => Some(ConvertTarget::Png),
+      "jpeg" | "jpg" => Some(ConvertTarget::Jpeg),
+      _ => None,
+    }
+  }
+}
+
+/// What to do with an image before it is hashed and uploaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreprocessOptions {
+  /// Images wider than this are scaled down, keeping their aspect ratio.
+  pub max_width: Option<u32>,
+  /// Images taller than this are scaled down, keeping their aspect ratio.
+  pub max_height: Option<u32>,
+  /// Quality of re-encoded JPEG images, from 1 to 100.
+  pub jpeg_quality: u8,
+  /// Re-encode JPEG, PNG and WebP images carrying EXIF or XMP data, e.g. GPS positions.
+  pub strip_metadata: bool,
+  /// Upload WebP images as they are instead of converting them.
+  pub keep_webp: bool,
+  pub convert_to: ConvertTarget,
+}
+
+impl Default for PreprocessOptions {
+  fn default() -> Self {
+    PreprocessOptions {
+      max_width: Some(1920),
+      max_height: None,
+      jpeg_quality: 85,
+      strip_metadata: true,
+      keep_webp: true,
+      convert_to: ConvertTarget::Auto,
+    }
+  }
+}
+
+/// An image re-encoded by [`preprocess`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preprocessed {
+  pub bytes: Vec<u8>,
+  pub format: ImageFormat,
+  pub width: u32,
+  pub height: u32,
+}
+
+impl From<image::ImageError> for ZhihuImageError {
+  fn from(err: image::ImageError) -> Self {
+    match err {
+      image::ImageError::Unsupported(_) => ZhihuImageError::UnsupportedFormat,
+      image::ImageError::IoError(e) => ZhihuImageError::IoError(e),
+      e => ZhihuImageError::InvalidImage(e.to_string()),
+    }
+  }
+}
+
+/// Whether the image carries EXIF or XMP data.
+fn has_metadata(bytes: &[u8], format: ImageFormat) -> bool {
+  match format {
+    ImageFormat::Jpeg => {
+      // Segments up to the start of scan, each `FF <marker> <length: u16>`
+      let mut i = 2;
+      while i + 4 <= bytes.len() && bytes[i] == 0xFF {
+        let marker = bytes[i + 1];
+        if marker == 0xDA {
+          break;
+        }
+        let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+        if length < 2 {
+          break;
+        }
+        let data = &bytes[(i + 4).min(bytes.len())..(i + 2 + length).min(bytes.len())];
+        if marker == 0xE1
+          && (data.starts_with(b"Exif\0") || data.starts_with(b"http://ns.adobe.com/xap/"))
+        {
+          return true;
+        }
+        i += 2 + length;
+      }
+      false
+    }
+    ImageFormat::Png => {
+      // Chunks after the signature, each `<length: u32> <type> <data> <crc>`
+      let mut i = 8;
+      while i + 8 <= bytes.len() {
+        let length = u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        match &bytes[i + 4..i + 8] {
+          b"eXIf" => return true,
+          b"iTXt" if bytes[i + 8..].starts_with(b"XML:com.adobe.xmp") => return true,
+          b"IEND" => break,
+          _ => {}
+        }
+        i += 12 + length as usize;
+      }
+      false
+    }
+    ImageFormat::Webp => {
+      // Chunks after the `RIFF <size> WEBP` header, padded to an even length
+      let mut i = 12;
+      while i + 8 <= bytes.len() {
+        if matches!(&bytes[i..i + 4], b"EXIF" | b"XMP ") {
+          return true;
+        }
+        let length = u32::from_le_bytes([bytes[i + 4], bytes[i + 5], bytes[i + 6], bytes[i + 7]]);
+        i += 8 + (length as usize).div_ceil(2) * 2;
+      }
+      false
+    }
+    ImageFormat::Gif | ImageFormat::Bmp => false,
+  }
+}
+
+/// Scale down, convert and strip the metadata of an image according to `options`.
+///
+/// Returns `None` when the image can be uploaded as it is, so that its hash, and the URL
+/// Zhihu serves it at, stay the same. GIF images are never touched, as that would drop their
+/// animation. The EXIF orientation is applied before it is stripped.
+pub fn preprocess(
+  bytes: &[u8],
+  options: &PreprocessOptions,
+) -> Result<Option<Preprocessed>, ZhihuImageError> {
+  let format = ImageFormat::sniff(bytes);
+  let convert = match format {
+    Some(ImageFormat::Gif) => return Ok(None),
+    Some(ImageFormat::Jpeg) | Some(ImageFormat::Png) => false,
+    Some(ImageFormat::Webp) => !options.keep_webp,
+    // BMP, and TIFF or any other format the decoder knows
+    Some(ImageFormat::Bmp) | None => true,
+  };
+  let max_width = options.max_width.unwrap_or(u32::MAX);
+  let max_height = options.max_height.unwrap_or(u32::MAX);
+  if !convert {
+    let size =
+      imagesize::blob_size(bytes).map_err(|e| ZhihuImageError::InvalidImage(e.to_string()))?;
+    let fits = size.width as u64 <= max_width as u64 && size.height as u64 <= max_height as u64;
+    let strip = options.strip_metadata && format.is_some_and(|f| has_metadata(bytes, f));
+    if fits && !strip {
+      return Ok(None);
+    }
+  }
+
+  let mut decoder = ImageReader::new(Cursor::new(bytes))
+    .with_guessed_format()?
+    .into_decoder()?;
+  let orientation = decoder.orientation()?;
+  let mut image = DynamicImage::from_decoder(decoder)?;
+  image.apply_orientation(orientation);
+  if image.width() > max_width || image.height() > max_height {
+    image = image.resize(max_width, max_height, FilterType::Lanczos3);
+  }
+
+  let target = match (format, options.convert_to) {
+    (Some(ImageFormat::Jpeg), _) => ImageFormat::Jpeg,
+    (Some(ImageFormat::Png), _) => ImageFormat::Png,
+    // The WebP encoder is lossless only, so kept WebP images are re-encoded as photos
+    (_, ConvertTarget::Auto) if image.color().has_alpha() => ImageFormat::Png,
+    (Some(ImageFormat::Webp), ConvertTarget::Auto) => ImageFormat::Jpeg,
+    (_, ConvertTarget::Auto) | (_, ConvertTarget::Png) => ImageFormat::Png,
+    (_, ConvertTarget::Jpeg) => ImageFormat::Jpeg,
+  };
+
+  let mut out = Vec::new();
+  match target {
+    ImageFormat::Jpeg => {
+      let encoder = JpegEncoder::new_with_quality(&mut out, options.jpeg_quality.clamp(1, 100));
+      DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+    }
+    _ => {
+      let encoder =
+        PngEncoder::new_with_quality(&mut out, CompressionType::Best, PngFilter::Adaptive);
+      if image.color().has_alpha() {
+        DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(encoder)?;
+      } else {
+        DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+      }
+    }
+  }
+  Ok(Some(Preprocessed {
+    bytes: out,
+    format: target,
+    width: image.width(),
+    height: image.height(),
+  }))
+}
+
+/// Name of the result of processing `bytes` with `options`, so that it is only made once.
+pub(crate) fn output_key(bytes: &[u8], options: &impl std::fmt::Debug) -> String {
+  let mut key = bytes.to_vec();
+  key.extend_from_slice(format!("{:?}", options).as_bytes());
+  content_hash(&key)
+}
+
+/// A result of an earlier run for the same key.
+pub(crate) fn cached_output(out_dir: &Path, key: &str) -> Option<PathBuf> {
+  [ImageFormat::Jpeg, ImageFormat::Png]
+    .into_iter()
+    .map(|format| out_dir.join(format!("{}.{}", key, format.extension())))
+    .find(|cached| cached.exists())
+}
+
+pub(crate) fn write_output(
+  out_dir: &Path,
+  key: &str,
+  image: Preprocessed,
+) -> Result<PathBuf, ZhihuImageError> {
+  write_atomic(
+    out_dir,
+    &format!("{}.{}", key, image.format.extension()),
+    &image.bytes,
+  )
+}
+
+/// Write `out_dir/name`, renamed into place so that an interrupted write is not taken for a result.
+pub(crate) fn write_atomic(
+  out_dir: &Path,
+  name: &str,
+  bytes: &[u8],
+) -> Result<PathBuf, ZhihuImageError> {
+  fs::create_dir_all(out_dir)?;
+  let out = out_dir.join(name);
+  let temp = out_dir.join(format!("{}.tmp", name));
+  fs::write(&temp, bytes)?;
+  fs::rename(&temp, &out)?;
+  Ok(out)
+}
+
+/// Preprocess the image file at `path`, writing the result to `out_dir`.
+///
+/// Returns `path` itself when the image needs no change. Results are named after the source
+/// content and the options, so an image is only processed once.
+pub fn preprocess_file(
+  path: impl AsRef<Path>,
+  out_dir: impl AsRef<Path>,
+  options: &PreprocessOptions,
+) -> Result<PathBuf, ZhihuImageError> {
+  let path = path.as_ref();
+  let bytes = fs::read(path)?;
+  let key = output_key(&bytes, options);
+  let out_dir = out_dir.as_ref();
+  if let Some(cached) = cached_output(out_dir, &key) {
+    return Ok(cached);
+  }
+  match preprocess(&bytes, options)? {
+    Some(image) => write_output(out_dir, &key, image),
+    None => Ok(path.to_path_buf()),
+  }
+}
@@ -0,0 +1,203 @@
+use crate::{
+  pdf,
+  preprocess::{cached_output, output_key, write_output},
+  ImageFormat, Preprocessed, ZhihuImageError,
+};
+use resvg::{tiny_skia, usvg};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, OnceLock},
+};
+
+/// Pixel count above which figures are refused, about 8K by 8K.
+const MAX_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Vector formats figures are exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorFormat {
+  Svg,
+  Pdf,
+}
+
+impl VectorFormat {
+  /// Detect the format from the start of the file.
+  pub fn sniff(bytes: &[u8]) -> Option<Self> {
+    if bytes.starts_with(b"%PDF-") {
+      return Some(VectorFormat::Pdf);
+    }
+    // Compressed `.svgz` files
+    if bytes.starts_with(&[0x1F, 0x8B]) {
+      return Some(VectorFormat::Svg);
+    }
+    let head = &bytes[..bytes.len().min(4096)];
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let start = head.iter().position(|c| !c.is_ascii_whitespace())?;
+    let is_svg = head[start] == b'<' && head.windows(4).any(|w| w == b"<svg");
+    is_svg.then_some(VectorFormat::Svg)
+  }
+}
+
+/// How vector figures are turned into PNG images.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterizeOptions {
+  /// Pixels per inch, 96 renders SVG figures at their CSS pixel size.
+  pub dpi: f32,
+  /// Color painted under the figure as RGBA, `None` keeps it transparent.
+  pub background: Option<[u8; 4]>,
+}
+
+impl Default for RasterizeOptions {
+  fn default() -> Self {
+    RasterizeOptions {
+      dpi: 192.0,
+      background: Some([255, 255, 255, 255]),
+    }
+  }
+}
+
+/// Parse a `#rrggbb` or `#rrggbbaa` color.
+pub fn parse_color(color: &str) -> Option<[u8; 4]> {
+  let hex = color.strip_prefix('#')?;
+  if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+    return None;
+  }
+  let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+  Some([
+    channel(0)?,
+    channel(2)?,
+    channel(4)?,
+    if hex.len() == 8 { channel(6)? } else { 255 },
+  ])
+}
+
+/// System fonts for the text of SVG figures, loaded once.
+fn system_fonts() -> Arc<usvg::fontdb::Database> {
+  static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+  FONTS
+    .get_or_init(|| {
+      let mut fonts = usvg::fontdb::Database::new();
+      fonts.load_system_fonts();
+      Arc::new(fonts)
+    })
+    .clone()
+}
+
+fn canvas(
+  width: f32,
+  height: f32,
+  options: &RasterizeOptions,
+) -> Result<tiny_skia::Pixmap, ZhihuImageError> {
+  let (width, height) = (width.ceil().max(1.0) as u64, height.ceil().max(1.0) as u64);
+  if width * height > MAX_PIXELS {
+    return Err(ZhihuImageError::Rasterize(format!(
+      "figure would be {}x{} pixels, lower the DPI",
+      width, height
+    )));
+  }
+  let mut pixmap = tiny_skia::Pixmap::new(width as u32, height as u32)
+    .ok_or_else(|| ZhihuImageError::Rasterize("figure is empty".to_string()))?;
+  if let Some([r, g, b, a]) = options.background {
+    pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+  }
+  Ok(pixmap)
+}
+
+fn rasterize_in(
+  bytes: &[u8],
+  options: &RasterizeOptions,
+  resources_dir: Option<&Path>,
+) -> Result<Option<Preprocessed>, ZhihuImageError> {
+  if !options.dpi.is_finite() || options.dpi <= 0.0 {
+    return Err(ZhihuImageError::Rasterize(format!(
+      "invalid DPI {}",
+      options.dpi
+    )));
+  }
+  let pixmap = match VectorFormat::sniff(bytes) {
+    None => return Ok(None),
+    Some(VectorFormat::Svg) => {
+      let usvg_options = usvg::Options {
+        resources_dir: resources_dir.map(Path::to_path_buf),
+        fontdb: system_fonts(),
+        ..usvg::Options::default()
+      };
+      let tree = usvg::Tree::from_data(bytes, &usvg_options)
+        .map_err(|e| ZhihuImageError::Rasterize(format!("invalid SVG: {}", e)))?;
+      // SVG sizes are in CSS pixels, 96 per inch
+      let scale = options.dpi / 96.0;
+      let size = tree.size();
+      let mut pixmap = canvas(size.width() * scale, size.height() * scale, options)?;
+      resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+      );
+      pixmap
+    }
+    Some(VectorFormat::Pdf) => {
+      let png = pdf::render_png(bytes, options.dpi).map_err(ZhihuImageError::Rasterize)?;
+      let size = imagesize::blob_size(&png)
+        .map_err(|e| ZhihuImageError::Rasterize(format!("unreadable PDF rendering: {}", e)))?;
+      // Checked before decoding, the renderer does not bound the page size
+      let mut pixmap = canvas(size.width as f32, size.height as f32, options)?;
+      let page = tiny_skia::Pixmap::decode_png(&png)
+        .map_err(|e| ZhihuImageError::Rasterize(format!("unreadable PDF rendering: {}", e)))?;
+      pixmap.draw_pixmap(
+        0,
+        0,
+        page.as_ref(),
+        &tiny_skia::PixmapPaint::default(),
+        tiny_skia::Transform::identity(),
+        None,
+      );
+      pixmap
+    }
+  };
+  let bytes = pixmap
+    .encode_png()
+    .map_err(|e| ZhihuImageError::Rasterize(e.to_string()))?;
+  Ok(Some(Preprocessed {
+    bytes,
+    format: ImageFormat::Png,
+    width: pixmap.width(),
+    height: pixmap.height(),
+  }))
+}
+
+/// Render an SVG figure or the first page of a PDF figure to PNG at `options.dpi`.
+///
+/// PDF figures are rendered by `pdftocairo` or `mutool`, one of which must be installed.
+/// Returns `None` for any other file, which Zhihu may take as it is. Images an SVG file links
+/// to by relative paths are not resolved, see [`rasterize_file`].
+pub fn rasterize(
+  bytes: &[u8],
+  options: &RasterizeOptions,
+) -> Result<Option<Preprocessed>, ZhihuImageError> {
+  rasterize_in(bytes, options, None)
+}
+
+/// Rasterize the figure file at `path`, writing the PNG image to `out_dir`.
+///
+/// Returns `path` itself when it is not a vector figure. Like [`crate::preprocess_file`],
+/// results are named after the source content and the options.
+pub fn rasterize_file(
+  path: impl AsRef<Path>,
+  out_dir: impl AsRef<Path>,
+  options: &RasterizeOptions,
+) -> Result<PathBuf, ZhihuImageError> {
+  let path = path.as_ref();
+  let bytes = fs::read(path)?;
+  if VectorFormat::sniff(&bytes).is_none() {
+    return Ok(path.to_path_buf());
+  }
+  let key = output_key(&bytes, options);
+  let out_dir = out_dir.as_ref();
+  if let Some(cached) = cached_output(out_dir, &key) {
+    return Ok(cached);
+  }
+  match rasterize_in(&bytes, options, path.parent())? {
+    Some(image) => write_output(out_dir, &key, image),
+    None => Ok(path.to_path_buf()),
+  }
+}
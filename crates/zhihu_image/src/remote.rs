@@ -0,0 +1,110 @@
+use crate::{content_hash, preprocess::write_atomic, ImageFormat, VectorFormat, ZhihuImageError};
+use base64::{
+  engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+  Engine,
+};
+use percent_encoding::percent_decode_str;
+use std::path::{Path, PathBuf};
+
+/// Downloads remote images, an HTTP client in the plugin and a stand-in in tests.
+pub trait Fetcher {
+  fn fetch(&self, url: &str) -> Result<Vec<u8>, ZhihuImageError>;
+}
+
+/// Whether Zhihu already serves the image, e.g. from `picx.zhimg.com`.
+pub fn is_zhihu_image_url(url: &str) -> bool {
+  let Some(rest) = url
+    .strip_prefix("https://")
+    .or_else(|| url.strip_prefix("http://"))
+  else {
+    return false;
+  };
+  let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+  let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+  let host = host
+    .split(':')
+    .next()
+    .unwrap_or_default()
+    .to_ascii_lowercase();
+  host == "zhimg.com" || host.ends_with(".zhimg.com")
+}
+
+fn is_remote_url(uri: &str) -> bool {
+  let scheme = uri.split(':').next().unwrap_or_default();
+  uri.contains("://") && matches!(scheme.to_ascii_lowercase().as_str(), "http" | "https")
+}
+
+fn is_data_uri(uri: &str) -> bool {
+  uri
+    .get(..5)
+    .is_some_and(|prefix| prefix.eq_ignore_ascii_case("data:"))
+}
+
+/// Content of a `data:[<media type>][;base64],<data>` URI.
+pub fn decode_data_uri(uri: &str) -> Result<Vec<u8>, ZhihuImageError> {
+  let invalid =
+    |reason: &str| ZhihuImageError::InvalidImage(format!("invalid data URI, {}", reason));
+  let rest = uri
+    .get(5..)
+    .filter(|_| is_data_uri(uri))
+    .ok_or_else(|| invalid("it does not start with `data:`"))?;
+  let (header, data) = rest.split_once(',').ok_or_else(|| invalid("no `,`"))?;
+  let base64 = header
+    .rsplit(';')
+    .next()
+    .is_some_and(|param| param.trim().eq_ignore_ascii_case("base64"));
+  if !base64 {
+    return Ok(percent_decode_str(data).collect());
+  }
+  // Line breaks and percent-encoding are common in URIs pasted from elsewhere
+  let data: Vec<u8> = percent_decode_str(data)
+    .filter(|c| !c.is_ascii_whitespace())
+    .collect();
+  STANDARD
+    .decode(&data)
+    .or_else(|_| STANDARD_NO_PAD.decode(&data))
+    .map_err(|e| invalid(&e.to_string()))
+}
+
+/// Extension of an image Zhihu or the rasterizer takes, or which preprocessing converts.
+fn extension(bytes: &[u8]) -> Option<&'static str> {
+  if let Some(format) = ImageFormat::sniff(bytes) {
+    return Some(format.extension());
+  }
+  match VectorFormat::sniff(bytes) {
+    Some(VectorFormat::Svg) => return Some("svg"),
+    Some(VectorFormat::Pdf) => return Some("pdf"),
+    None => {}
+  }
+  image::guess_format(bytes)
+    .ok()
+    .and_then(|format| format.extensions_str().first().copied())
+}
+
+/// Bring a remote or data URI image into `out_dir`, so that it can be uploaded to Zhihu.
+///
+/// Returns `None` for local paths and images Zhihu already serves, which need no download.
+/// Files are named after their content, so an image downloaded twice is stored once.
+pub fn localize_image(
+  uri: &str,
+  out_dir: impl AsRef<Path>,
+  fetcher: &dyn Fetcher,
+) -> Result<Option<PathBuf>, ZhihuImageError> {
+  let bytes = if is_data_uri(uri) {
+    decode_data_uri(uri)?
+  } else if is_remote_url(uri) && !is_zhihu_image_url(uri) {
+    fetcher.fetch(uri)?
+  } else {
+    return Ok(None);
+  };
+  let extension = extension(&bytes).ok_or_else(|| {
+    let source = if is_data_uri(uri) { "data URI" } else { uri };
+    ZhihuImageError::InvalidImage(format!("{} is not an image", source))
+  })?;
+  let name = format!("{}.{}", content_hash(&bytes), extension);
+  let out = out_dir.as_ref().join(&name);
+  if out.exists() {
+    return Ok(Some(out));
+  }
+  write_atomic(out_dir.as_ref(), &name, &bytes).map(Some)
+}
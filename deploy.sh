@@ -2,6 +2,9 @@
 
 make chrome_cookie_lua51
 make markdown_to_html_lua51
+make zhihu_image_lua51
+make zhihu_api_lua51
+make buf_id_lua51
 
 # Define the project root and virtual environment directory
 PROJECT_ROOT="/Users/pxwg-dogggie/zhihu_on_nvim"
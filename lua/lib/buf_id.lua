@@ -0,0 +1,81 @@
+local M = {}
+
+-- State management
+local state = {
+  initialized = false,
+  buf_id = nil,
+}
+
+-- Helper function to get the plugin root directory
+local function get_plugin_root()
+  local source = debug.getinfo(2, "S").source
+  local file = string.sub(source, 2) -- Remove the '@' prefix
+  local dir = string.match(file, "(.*/)")
+  return string.gsub(dir, "lua/lib/$", "")
+end
+
+-- Try to load a dynamic library
+local function try_load(path)
+  local success, result = pcall(function()
+    return package.loadlib(path, "luaopen_buf_id_lib")
+  end)
+
+  if success and type(result) == "function" then
+    return result
+  end
+
+  return nil
+end
+
+-- Initialize the library
+function M.initialize()
+  if state.initialized then
+    return state.buf_id ~= nil
+  end
+
+  local plugin_root = get_plugin_root()
+
+  -- Try with different extensions based on the platform
+  local lib_paths = {
+    plugin_root .. "/build/buf_id_lua51.dylib",
+    plugin_root .. "/build/buf_id_lua51.so",
+    plugin_root .. "/build/buf_id_lua51.dll",
+    plugin_root .. "/build/buf_id_jit.dylib",
+    plugin_root .. "/build/buf_id_jit.so",
+    plugin_root .. "/build/buf_id_jit.dll",
+  }
+
+  local lib_func = nil
+  for _, path in ipairs(lib_paths) do
+    lib_func = try_load(path)
+    if lib_func then
+      break
+    end
+  end
+
+  if not lib_func then
+    vim.notify("Failed to load buf_id library. Make sure you run `bash deploy.sh` first.", vim.log.levels.ERROR)
+    state.initialized = true
+    return false
+  end
+
+  state.buf_id = lib_func()
+  state.initialized = true
+  return true
+end
+
+---@class buf_id.Store
+---@field check fun(self: buf_id.Store, path: string): string|nil Draft ID of a file, following it after a move or copy
+---@field assign fun(self: buf_id.Store, path: string, id: string) Set the draft ID of a file
+---@field update fun(self: buf_id.Store, path: string, id: string): boolean Change the draft ID of a file, `false` if it had none
+---@field remove fun(self: buf_id.Store, path: string): string|nil Forget the draft ID of a file and return it
+
+---Open the draft ID store. Its methods raise an error if a file cannot be read or the store is corrupted.
+---@param path string JSON file of the store, created on first write
+---@param legacy_path? string Inode map imported when the store does not exist yet
+---@return buf_id.Store
+function M.store(path, legacy_path)
+  return state.buf_id.store(path, legacy_path)
+end
+
+return M
@@ -64,12 +64,30 @@ function M.initialize()
   return true
 end
 
----Decrypt a Chrome cookie value using the provided master key.
+---@class chrome_cookie.DecryptOpts
+---@field platform? "macos"|"linux"|"windows" Platform the cookie database comes from, which decides how `v10` values are decrypted. Defaults to the current one.
+---@field master_key? string AES-GCM master key of Windows databases and `v20` values, as bytes
+---@field path? string Only cookies sent to requests for this path, e.g. "/api/v4"
+---@field include_expired? boolean Also return expired cookies the browser has not purged yet
+
+---@class chrome_cookie.Cookie
+---@field host string Domain of the cookie, with a leading "." when it is also sent to subdomains
+---@field name string
+---@field value string
+---@field path string
+---@field expires? integer Expiry in seconds since the Unix epoch, nil for session cookies
+---@field secure boolean
+---@field http_only boolean
+---@field same_site "none"|"lax"|"strict"|"unspecified"
+---@field partition_key? string Top-level site of a partitioned (CHIPS) cookie
+
+---Decrypt a Chrome cookie value, choosing the scheme from its version prefix and the platform.
 ---@param encrypted_value string The encrypted cookie value as a byte array.
----@param master_key string The Chrome master key as a byte array.
+---@param password? string The Chrome password, nil on Linux without a keyring.
+---@param opts? chrome_cookie.DecryptOpts
 ---@return string The decrypted cookie value as a UTF-8 string.
-function M.decrypt_chrome_cookie_str(encrypted_value, master_key)
-  return state.chrome_cookie.decrypt_chrome_cookie(encrypted_value, master_key)
+function M.decrypt_chrome_cookie_str(encrypted_value, password, opts)
+  return state.chrome_cookie.decrypt_chrome_cookie(encrypted_value, password, opts)
 end
 
 ---Get Chrome master key.
@@ -78,44 +96,171 @@ function M.get_chrome_master_key()
   return state.chrome_cookie.get_master_key()
 end
 
----Get Chrome cookies.
+---Get Chrome cookies, expired ones left out unless `opts.include_expired` is set.
 ---@param cookie_path string
----@param password string
----@return table<string, string> A table where keys are cookie names and values are cookie values.
-function M.get_cookies(cookie_path, password)
-  return state.chrome_cookie.get_cookies(cookie_path, password)
+---@param password? string Keychain or keyring password, nil on Linux without a keyring
+---@param opts? chrome_cookie.DecryptOpts
+---@return chrome_cookie.Cookie[]
+function M.get_cookies(cookie_path, password, opts)
+  return state.chrome_cookie.get_cookies(cookie_path, password, opts)
 end
 
----Get Chrome password.
----@return string The Chrome password as a byte array.
-function M.get_chrome_password()
-  return state.chrome_cookie.get_chrome_password()
+---@class chrome_cookie.Browser
+---@field id string Name used in the configuration and `:ZhihuAuth`, e.g. "brave"
+---@field name string Display name, e.g. "Brave"
+
+---Chromium-based browsers cookies can be read from.
+---@return chrome_cookie.Browser[]
+function M.browsers()
+  return state.chrome_cookie.browsers()
+end
+
+---Cookie database of a browser's last used profile, from its native, Flatpak or Snap install.
+---Raises an error if the browser is unknown or not installed.
+---@param browser string Browser ID, e.g. "chromium"
+---@return string
+function M.cookie_path(browser)
+  return state.chrome_cookie.cookie_path(browser)
+end
+
+---@class chrome_cookie.Profile
+---@field name string Name shown in the profile menu
+---@field dir string Directory name, e.g. "Default" or "Profile 2"
+---@field path string Full path of the profile directory
+---@field cookie_path? string Cookie database, `Network/Cookies` or `Cookies`, nil until the profile stores a cookie
+---@field last_used boolean Whether the browser opened this profile last
+
+---Profiles of a browser from its `Local State`, the last used one first.
+---Raises an error if the browser is unknown or not installed.
+---@param browser string Browser ID, e.g. "chrome"
+---@return chrome_cookie.Profile[]
+function M.profiles(browser)
+  return state.chrome_cookie.profiles(browser)
+end
+
+---@class chrome_cookie.FirefoxProfile
+---@field name string Name shown in `about:profiles`
+---@field path string Full path of the profile directory
+---@field default boolean Whether an install of Firefox (release, ESR, Developer Edition, ...) opens it by default
+---@field cookie_path? string `cookies.sqlite` of the profile, nil until it stores a cookie
+
+---Firefox profiles from `profiles.ini` and `installs.ini`, the default ones first.
+---Raises an error if Firefox is not installed.
+---@return chrome_cookie.FirefoxProfile[]
+function M.firefox_profiles()
+  return state.chrome_cookie.firefox_profiles()
 end
 
----Get Chrome cookies for a specific host.
+---`cookies.sqlite` of the default Firefox profile, from its native, Flatpak or Snap install.
+---Raises an error if Firefox is not installed or no default profile has cookies.
+---@return string
+function M.firefox_cookie_path()
+  return state.chrome_cookie.firefox_cookie_path()
+end
+
+---@class chrome_cookie.OriginOpts
+---@field user_context_id? integer Multi-Account Container of the cookies, 0 (the default) outside of any
+---@field first_party_domain? string Site the cookies are isolated to with first-party isolation
+---@field partition_key? string Top-level site of partitioned cookies, e.g. "(https,example.com)"
+---@field path? string Only cookies sent to requests for this path, e.g. "/api/v4"
+---@field include_expired? boolean Also return expired cookies Firefox has not purged yet
+
+---@class chrome_cookie.FirefoxSession
+---@field user_context_id integer Multi-Account Container of the session, 0 outside of any
+---@field first_party_domain string
+---@field partition_key string
+---@field container? string Container name from `containers.json`
+---@field cookies chrome_cookie.Cookie[] Cookies of the session in the order they are sent
+
+---Cookies of a host grouped by the jar they are stored in (container, first-party domain and partition), the default jar first.
 ---@param cookie_path string
----@param password string
 ---@param host string The host for which to retrieve cookies.
----@return table<string, string> A table where keys are cookie names and values are cookie values for the specified host.
-function M.get_cookies_for_host(cookie_path, password, host)
-  local cookies = state.chrome_cookie.get_cookies_for_host(cookie_path, password, host)
-  local result = {}
-  for k, v in pairs(cookies) do
-    -- HACK: Remove the first 56 rubbish characters from the cookie value
-    v = v:sub(57)
-    result[k] = v
-  end
-  return result
+---@param opts? chrome_cookie.OriginOpts Only `path` and `include_expired` apply
+---@return chrome_cookie.FirefoxSession[]
+function M.firefox_sessions(cookie_path, host, opts)
+  return state.chrome_cookie.firefox_sessions(cookie_path, host, opts)
+end
+
+---Get Firefox cookies.
+---Cookies of the session store (`sessionstore-backups/recovery.jsonlz4`), which Firefox writes first, are merged in, the newest value of each kept.
+---@param cookie_path string
+---@param opts? chrome_cookie.OriginOpts Jar to read, the default one outside of containers if not set
+---@return chrome_cookie.Cookie[]
+function M.get_firefox_cookies(cookie_path, opts)
+  return state.chrome_cookie.get_firefox_cookies(cookie_path, opts)
 end
 
----Get the value of a specific cookie for a given host.
+---Get the Firefox cookies sent to a host, the session store merged in.
+---Cookies of the host and of its parent domains are returned in the order they are sent, so the first of each name is the one a request gets.
 ---@param cookie_path string
----@param password string
+---@param host string The host for which to retrieve cookies, e.g. "www.zhihu.com"
+---@param opts? chrome_cookie.OriginOpts Jar to read, the default one outside of containers if not set
+---@return chrome_cookie.Cookie[]
+function M.get_firefox_cookies_for_host(cookie_path, host, opts)
+  return state.chrome_cookie.get_firefox_cookies_for_host(cookie_path, host, opts)
+end
+
+---Get the value of a specific Firefox cookie for a given host, the newest of the database and session store.
+---@param cookie_path string
+---@param host string The host for which to retrieve the cookie.
+---@param name string The name of the cookie to retrieve.
+---@param opts? chrome_cookie.OriginOpts Jar to read, the default one outside of containers if not set
+---@return string|nil The value of the specified cookie for the given host, or nil if not found.
+function M.get_firefox_cookie_value(cookie_path, host, name, opts)
+  return state.chrome_cookie.get_firefox_cookie_value(cookie_path, host, name, opts)
+end
+
+---Get the password of a browser's cookies, from the macOS Keychain, or the Secret Service or KDE Wallet on Linux.
+---Raises an error if a lookup failed, e.g. because `secret-tool` is not installed, and no password was found.
+---@param browser string Browser ID, e.g. "edge"
+---@param key? string Password from the configuration, which skips the lookup
+---@return string|nil The password, nil on Linux if the browser has no keyring and only `v10` cookies
+function M.get_browser_password(browser, key)
+  return state.chrome_cookie.get_browser_password(browser, key)
+end
+
+---Get Chrome password.
+---On Linux it is looked up in the Secret Service and KDE Wallet, nil if Chrome stores none there.
+---@param key? string Password from the configuration, which skips the lookup
+---@return string|nil The Chrome password as a byte array.
+function M.get_chrome_password(key)
+  return state.chrome_cookie.get_chrome_password(key)
+end
+
+---Get the Chrome cookies sent to a host.
+---Cookies of the host and of its parent domains are returned in the order they are sent, so the first of each name is the one a request gets.
+---The SHA-256 host prefix of newer cookie databases is already stripped from the values.
+---@param cookie_path string
+---@param password? string Keychain or keyring password, nil on Linux without a keyring
+---@param host string The host for which to retrieve cookies, e.g. "www.zhihu.com"
+---@param opts? chrome_cookie.DecryptOpts
+---@return chrome_cookie.Cookie[]
+function M.get_cookies_for_host(cookie_path, password, host, opts)
+  return state.chrome_cookie.get_cookies_for_host(cookie_path, password, host, opts)
+end
+
+---Get the value of the cookie a request to a host gets, unexpired and of the longest path and most specific domain.
+---@param cookie_path string
+---@param password? string Keychain or keyring password, nil on Linux without a keyring
 ---@param host string The host for which to retrieve the cookie.
 ---@param name string The name of the cookie to retrieve.
+---@param opts? chrome_cookie.DecryptOpts
 ---@return string The value of the specified cookie for the given host, or nil if not found.
-function M.get_cookie_value(cookie_path, password, host, name)
-  return state.chrome_cookie.get_cookie_value(cookie_path, password, host, name)
+function M.get_cookie_value(cookie_path, password, host, name, opts)
+  return state.chrome_cookie.get_cookie_value(cookie_path, password, host, name, opts)
+end
+
+---Cookie names and values of a list in the order cookies are sent, the first of each name kept.
+---@param cookies chrome_cookie.Cookie[]
+---@return table<string, string>
+function M.cookie_table(cookies)
+  local values = {}
+  for _, cookie in ipairs(cookies) do
+    if values[cookie.name] == nil then
+      values[cookie.name] = cookie.value
+    end
+  end
+  return values
 end
 
 return M
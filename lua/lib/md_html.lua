@@ -74,6 +74,71 @@ function M.md_to_html(markdown)
   return state.markdown_to_html.md_to_html(markdown)
 end
 
+---@class md_html.ConvertOpts
+---@field links? table<string, string> Note key (relative path without `.md`) -> Zhihu article ID
+---@field toc? "off"|"block"|"zhihu" Table of contents: none, a list of heading links, or Zhihu's own
+---@field math? "image"|"ztext" Math markup: legacy equation images or Zhihu's `ztext-math` spans
+---@field images? md_html.ImageOpts Defaults for images without their own attributes
+
+---@class md_html.ImageOpts
+---@field size? "small"|"normal"|"large" Display width of images
+---@field watermark? boolean Whether Zhihu adds its watermark
+---@field caption_from_alt? boolean Use the alt text as caption when an image has none
+---@field preprocess? zhihu_image.PreprocessOpts|false How local images are prepared before upload, `false` uploads them as they are
+---@field rasterize? zhihu_image.RasterizeOpts|false How SVG and PDF figures are rendered to PNG, `false` uploads them as they are
+
+---@class md_html.ArticleMeta
+---@field title? string Article title
+---@field id? string Zhihu draft or article ID
+---@field topics string[] Topic IDs or names
+---@field column? string Column ID
+---@field cover? string Cover image, a local path or URL
+---@field comment_permission? "anyone"|"followee"|"censor"|"nobody" Who may comment
+---@field original? boolean Declare the article as original content
+
+---@class md_html.Heading
+---@field level integer Heading level, 1 to 6
+---@field text string Heading text
+---@field line integer 1-based line of the heading in the Markdown source
+---@field id string Anchor id of the heading
+
+---@class md_html.Conversion
+---@field html string The converted HTML string
+---@field warnings string[] Problems found during conversion, e.g. unresolved note links
+---@field outline md_html.Heading[] Heading outline of the document
+---@field table_of_contents boolean Whether Zhihu should render its own table of contents
+---@field meta md_html.ArticleMeta Settings from the front matter, which is left out of the HTML
+
+--- Function to convert Markdown to HTML with per-document options
+--- @param markdown string: The Markdown text to convert
+--- @param opts? md_html.ConvertOpts: Conversion options
+--- @return md_html.Conversion
+function M.convert(markdown, opts)
+  return state.markdown_to_html.convert(markdown, opts)
+end
+
+--- Function to parse the YAML (`---`) or TOML (`+++`) front matter of a Markdown document
+--- @param markdown string: The Markdown text to scan
+--- @return md_html.ArticleMeta|nil: The article settings, or nil if the document has no front matter
+--- @return string[]|nil: Problems found in the front matter, e.g. unknown keys
+function M.front_matter(markdown)
+  return state.markdown_to_html.front_matter(markdown)
+end
+
+--- Function to collect the heading outline of a Markdown document
+--- @param markdown string: The Markdown text to scan
+--- @return md_html.Heading[]
+function M.outline(markdown)
+  return state.markdown_to_html.outline(markdown)
+end
+
+--- Function to collect links to local notes, both `[text](./note.md)` and `[[note]]`
+--- @param markdown string: The Markdown text to scan
+--- @return string[]: Note keys, i.e. relative paths without the `.md` extension
+function M.note_links(markdown)
+  return state.markdown_to_html.note_links(markdown)
+end
+
 _G.md_to_html = M.md_to_html
 
 return M
@@ -0,0 +1,120 @@
+local M = {}
+
+-- State management
+local state = {
+  initialized = false,
+  zhihu_api = nil,
+}
+
+-- Helper function to get the plugin root directory
+local function get_plugin_root()
+  local source = debug.getinfo(2, "S").source
+  local file = string.sub(source, 2) -- Remove the '@' prefix
+  local dir = string.match(file, "(.*/)")
+  return string.gsub(dir, "lua/lib/$", "")
+end
+
+-- Try to load a dynamic library
+local function try_load(path)
+  local success, result = pcall(function()
+    return package.loadlib(path, "luaopen_zhihu_api_lib")
+  end)
+
+  if success and type(result) == "function" then
+    return result
+  end
+
+  return nil
+end
+
+-- Initialize the library
+function M.initialize()
+  if state.initialized then
+    return state.zhihu_api ~= nil
+  end
+
+  local plugin_root = get_plugin_root()
+
+  -- Try with different extensions based on the platform
+  local lib_paths = {
+    plugin_root .. "/build/zhihu_api_lua51.dylib",
+    plugin_root .. "/build/zhihu_api_lua51.so",
+    plugin_root .. "/build/zhihu_api_lua51.dll",
+    plugin_root .. "/build/zhihu_api_jit.dylib",
+    plugin_root .. "/build/zhihu_api_jit.so",
+    plugin_root .. "/build/zhihu_api_jit.dll",
+  }
+
+  local lib_func = nil
+  for _, path in ipairs(lib_paths) do
+    lib_func = try_load(path)
+    if lib_func then
+      break
+    end
+  end
+
+  if not lib_func then
+    vim.notify("Failed to load zhihu_api library. Make sure you run `bash deploy.sh` first.", vim.log.levels.ERROR)
+    state.initialized = true
+    return false
+  end
+
+  state.zhihu_api = lib_func()
+  state.initialized = true
+  return true
+end
+
+---@class zhihu_api.Endpoints
+---@field zhuanlan? string Base URL of the drafts API, defaults to `https://zhuanlan.zhihu.com`
+---@field api? string Base URL of the image API, defaults to `https://api.zhihu.com`
+---@field upload? string Base URL of the image bucket, defaults to `https://zhihu-pics-upload.zhimg.com`
+
+---@class zhihu_api.Draft
+---@field title string
+---@field content string HTML content of the article
+---@field table_of_contents? boolean Whether Zhihu renders its table of contents
+---@field delta_time? integer Seconds since the last save
+---@field title_image? string Cover image URL
+
+---@class zhihu_api.PublishSettings
+---@field column? string Column ID to publish into
+---@field topics? string[] Topic IDs or exact topic names
+---@field comment_permission? "anyone"|"followee"|"censor"|"nobody" Who may comment
+---@field original? boolean Declare the article as original content
+---@field table_of_contents? boolean Whether Zhihu renders its table of contents
+
+---@class zhihu_api.Published
+---@field id string Article ID
+---@field url string Article URL
+
+---@class zhihu_api.Topic
+---@field id string
+---@field name string
+
+---@class zhihu_api.Client
+---@field create_draft fun(self: zhihu_api.Client, draft: zhihu_api.Draft): string Create a draft and return its ID
+---@field update_draft fun(self: zhihu_api.Client, draft_id: string, draft: zhihu_api.Draft)
+---@field publish_draft fun(self: zhihu_api.Client, draft_id: string, settings: zhihu_api.PublishSettings): zhihu_api.Published Publish a draft
+---@field search_topics fun(self: zhihu_api.Client, name: string): zhihu_api.Topic[] Topics matching a name
+---@field image_upload fun(self: zhihu_api.Client, image_hash: string): upload_response Register an image hash
+---@field upload_image fun(self: zhihu_api.Client, path: string, upload_token: upload_token) Upload an image to the bucket
+---@field download_article fun(self: zhihu_api.Client, url: string): string Download the HTML page of an article
+
+---Create a Zhihu API client. Its methods raise an error with the HTTP status and Zhihu's message on failure.
+---@param cookie string Cookies for authentication
+---@param endpoints? zhihu_api.Endpoints Base URLs, e.g. of a local test server
+---@return zhihu_api.Client
+function M.client(cookie, endpoints)
+  return state.zhihu_api.client(cookie, endpoints)
+end
+
+---Download a remote image without the Zhihu cookies, or decode a data URI, into `out_dir`.
+---Raises an error if the download fails or the content is not an image.
+---@param uri string `http(s)://` URL or `data:` URI
+---@param out_dir string Directory the image is written to, named after its content
+---@return string|nil path The local copy, nil for local paths and images Zhihu already serves
+function M.fetch_image(uri, out_dir)
+  return state.zhihu_api.fetch_image(uri, out_dir)
+end
+
+return M
@@ -0,0 +1,165 @@
+local M = {}
+
+-- State management
+local state = {
+  initialized = false,
+  zhihu_image = nil,
+}
+
+-- Helper function to get the plugin root directory
+local function get_plugin_root()
+  local source = debug.getinfo(2, "S").source
+  local file = string.sub(source, 2) -- Remove the '@' prefix
+  local dir = string.match(file, "(.*/)")
+  return string.gsub(dir, "lua/lib/$", "")
+end
+
+-- Try to load a dynamic library
+local function try_load(path)
+  local success, result = pcall(function()
+    return package.loadlib(path, "luaopen_zhihu_image_lib")
+  end)
+
+  if success and type(result) == "function" then
+    return result
+  end
+
+  return nil
+end
+
+-- Initialize the library
+function M.initialize()
+  if state.initialized then
+    return state.zhihu_image ~= nil
+  end
+
+  local plugin_root = get_plugin_root()
+
+  -- Try with different extensions based on the platform
+  local lib_paths = {
+    plugin_root .. "/build/zhihu_image_lua51.dylib",
+    plugin_root .. "/build/zhihu_image_lua51.so",
+    plugin_root .. "/build/zhihu_image_lua51.dll",
+    plugin_root .. "/build/zhihu_image_jit.dylib",
+    plugin_root .. "/build/zhihu_image_jit.so",
+    plugin_root .. "/build/zhihu_image_jit.dll",
+  }
+
+  local lib_func = nil
+  for _, path in ipairs(lib_paths) do
+    lib_func = try_load(path)
+    if lib_func then
+      break
+    end
+  end
+
+  if not lib_func then
+    vim.notify("Failed to load zhihu_image library. Make sure you run `bash deploy.sh` first.", vim.log.levels.ERROR)
+    state.initialized = true
+    return false
+  end
+
+  state.zhihu_image = lib_func()
+  state.initialized = true
+  return true
+end
+
+---@class zhihu_image.ImageInfo
+---@field hash string Content hash Zhihu uses as image key
+---@field mime_type string MIME type sniffed from the file content
+---@field extension string Extension of the image URL on `picx.zhimg.com`
+---@field width integer Width in pixels
+---@field height integer Height in pixels
+
+---Hash the content of a file the way Zhihu expects.
+---@param path string Absolute path to the file
+---@return string The lowercase hex MD5 of the file content
+function M.hash_file(path)
+  return state.zhihu_image.hash_file(path)
+end
+
+---Hash an image, detect its format from the magic bytes and read its dimensions.
+---Raises an error if the file cannot be read or is not a JPEG, PNG, GIF, WebP or BMP image.
+---@param path string Absolute path to the image
+---@return zhihu_image.ImageInfo
+function M.image_info(path)
+  return state.zhihu_image.image_info(path)
+end
+
+---@class zhihu_image.PreprocessOpts
+---@field max_width? integer|false Wider images are scaled down, `false` for no limit (default 1920)
+---@field max_height? integer|false Taller images are scaled down, `false` for no limit (default none)
+---@field jpeg_quality? integer Quality of re-encoded JPEG images, 1 to 100 (default 85)
+---@field strip_metadata? boolean Re-encode images carrying EXIF or XMP data such as GPS positions (default true)
+---@field keep_webp? boolean Upload WebP images as they are instead of converting them (default true)
+---@field convert_to? "auto"|"png"|"jpeg" Format of converted BMP, TIFF and WebP images (default "auto")
+
+---Scale down, strip the metadata of and convert an image before it is hashed and uploaded.
+---GIF images are left alone. Raises an error if the file cannot be read or decoded.
+---@param path string Absolute path to the image
+---@param out_dir string Directory the processed image is written to
+---@param opts? zhihu_image.PreprocessOpts
+---@return string path `path` itself if the image needs no change, otherwise the processed image
+function M.preprocess(path, out_dir, opts)
+  return state.zhihu_image.preprocess(path, out_dir, opts)
+end
+
+---@class zhihu_image.RasterizeOpts
+---@field dpi? number Pixels per inch of the PNG image, 96 keeps SVG figures at their pixel size (default 192)
+---@field background? string|false `#rrggbb` or `#rrggbbaa` color under the figure, `false` keeps it transparent (default "#ffffff")
+
+---Render an SVG figure or the first page of a PDF figure to PNG, so that Zhihu takes it.
+---PDF figures need `pdftocairo` (poppler) or `mutool` (MuPDF) installed.
+---Raises an error if the figure cannot be read or rendered.
+---@param path string Absolute path to the figure
+---@param out_dir string Directory the PNG image is written to
+---@param opts? zhihu_image.RasterizeOpts
+---@return string path `path` itself if it is not an SVG or PDF file, otherwise the PNG image
+function M.rasterize(path, out_dir, opts)
+  return state.zhihu_image.rasterize(path, out_dir, opts)
+end
+
+---@class zhihu_image.CachedImage
+---@field hash string Content hash of the local image
+---@field url string Final `picx.zhimg.com` URL
+---@field state "existing"|"uploaded" Whether Zhihu already had the image or it was uploaded
+---@field cached_at integer Unix timestamp of the upload
+
+---@class zhihu_image.Cache
+---@field get fun(self: zhihu_image.Cache, hash: string): zhihu_image.CachedImage|nil Look up an image
+---@field put fun(self: zhihu_image.Cache, hash: string, url: string, state: "existing"|"uploaded") Record an image on Zhihu
+---@field get_source fun(self: zhihu_image.Cache, uri: string): zhihu_image.CachedImage|nil Look up the image downloaded from a URL or data URI
+---@field put_source fun(self: zhihu_image.Cache, uri: string, hash: string) Record the hash of the image downloaded from a URL or data URI
+---@field invalidate fun(self: zhihu_image.Cache, hash: string): boolean Forget an image, `false` if it was not cached
+---@field clear fun(self: zhihu_image.Cache): integer Forget every image and return how many there were
+---@field entries fun(self: zhihu_image.Cache): zhihu_image.CachedImage[] Every cached image, the most recent first
+
+---Open the SQLite cache of images already on Zhihu, creating it if needed.
+---@param path string Path to the database
+---@return zhihu_image.Cache
+function M.cache(path)
+  return state.zhihu_image.cache(path)
+end
+
+---@class zhihu_image.SignUploadOpts
+---@field access_id string `upload_token.access_id`
+---@field access_key string `upload_token.access_key`, the access key secret
+---@field security_token string `upload_token.access_token`
+---@field user_agent string Value of the `x-oss-user-agent` header
+---@field hash string Image hash, the object is `v2-<hash>`
+---@field mime_type string Content type of the upload
+---@field date? string RFC 1123 date, defaults to now
+
+---@class zhihu_image.SignedUpload
+---@field authorization string Value of the `Authorization` header
+---@field date string Value of the `Date` and `x-oss-date` headers
+---@field url string Object URL to `PUT` the image to
+
+---Sign the OSS `PUT` request uploading an image to Zhihu.
+---@param opts zhihu_image.SignUploadOpts
+---@return zhihu_image.SignedUpload
+function M.sign_upload(opts)
+  return state.zhihu_image.sign_upload(opts)
+end
+
+return M
@@ -1,4 +1,5 @@
-local curl = require("plenary.curl")
+local api = require("lib.zhihu_api")
+local image_lib = require("lib.zhihu_image")
 local util = require("zhvim.util")
 local M = {}
 
@@ -15,68 +16,156 @@ local M = {}
 
 ---@class upload_response
 ---@field upload_vendor string
----@field upload_token upload_token
+---@field upload_token? upload_token Absent when Zhihu already has the image
 ---@field upload_file upload_file
 
 ---@class html_content
 ---@field title string
 ---@field content string
+---@field table_of_contents? boolean
+---@field title_image? string Cover image URL
 
----TODO: Reads a file as binary and calculates its SHA256 hash.
+---Reads a file as binary and calculates the hash Zhihu uses as image key.
 ---@param file_path string The absolute path to the file
----@return string|nil The SHA256 hash of the file content, or nil if an error occurs
+---@return string|nil The MD5 hash of the file content, or nil if an error occurs
 function M.read_file_and_hash(file_path)
-  local sha256_cmd = "openssl dgst -md5 " .. vim.fn.shellescape(file_path) .. " | awk '{print $2}'"
-  local hash = vim.fn.system(sha256_cmd):gsub("%s+", "")
+  local ok, hash = pcall(image_lib.hash_file, file_path)
+  if not ok then
+    vim.notify("Failed to hash file " .. file_path .. ": " .. tostring(hash), vim.log.levels.ERROR)
+    return nil
+  end
   return hash
 end
 
--- Function to infer MIME type from file extension
-local function infer_mime_type(file_path)
-  local ext = file_path:match("^.+(%..+)$"):lower()
-  local mime_types = {
-    [".jpg"] = "image/jpeg",
-    [".jpeg"] = "image/jpeg",
-    [".png"] = "image/png",
-    [".gif"] = "image/gif",
-    [".bmp"] = "image/bmp",
-    [".webp"] = "image/webp",
-  }
-  return mime_types[ext] or "application/octet-stream" -- Default to binary stream
+---Reads an image, detecting its format from the content rather than the file extension.
+---@param file_path string The absolute path to the image
+---@return zhihu_image.ImageInfo|nil Hash, MIME type and dimensions, or nil if the file is not a supported image
+function M.read_image_info(file_path)
+  local ok, info = pcall(image_lib.image_info, file_path)
+  if not ok then
+    vim.notify("Failed to read image " .. file_path .. ": " .. tostring(info), vim.log.levels.ERROR)
+    return nil
+  end
+  return info
 end
 
----Calculate the HMAC-SHA1 signature and return it as a base64-encoded string.
----@param access_key_secret string The secret key used for signing
----@param string_to_sign string The string to be signed
----@return string The base64-encoded signature
-local function calculate_signature(access_key_secret, string_to_sign)
-  local escaped_string_to_sign = string_to_sign:gsub("'", "'\\''")
-  local escaped_access_key_secret = access_key_secret:gsub("'", "'\\''")
-  local command = string.format(
-    "printf '%s' | openssl dgst -sha1 -hmac '%s' -binary | openssl base64",
-    escaped_string_to_sign,
-    escaped_access_key_secret
-  )
-  local handle = io.popen(command)
-  local signature = handle:read("*a"):gsub("%s+", "") -- Remove trailing whitespace/newlines
-  handle:close()
-  return signature
+local image_cache_file = vim.fn.stdpath("data") .. "/zhvim_images.sqlite"
+
+---Call a method of the image cache, reporting errors instead of raising them.
+---@param method string Name of the `zhihu_image.Cache` method
+---@param ... any Arguments of the method
+---@return boolean ok
+---@return any result
+local function call_cache(method, ...)
+  local ok, result = pcall(function(...)
+    local cache = image_lib.cache(image_cache_file)
+    return cache[method](cache, ...)
+  end, ...)
+  if not ok then
+    vim.notify("Failed to access image cache " .. image_cache_file .. ": " .. tostring(result), vim.log.levels.WARN)
+  end
+  return ok, result
 end
 
----Function to execute a curl command and return the response
----@param curl_command string command to execute
----@return string response
-local function execute_curl_command(curl_command)
-  local response = vim.fn.system(curl_command)
-  if vim.v.shell_error ~= 0 then
-    local error_message = table.concat(vim.fn.systemlist(curl_command))
-    vim.notify(
-      "Error executing curl command: " .. curl_command .. "\n Error message: " .. error_message,
-      vim.log.levels.ERROR
-    )
-    return ""
+---Look up the URL of an image uploaded before.
+---@param img_hash string Content hash of the image
+---@return string|nil url The `picx.zhimg.com` URL, or nil if the image is not cached
+function M.cached_image_link(img_hash)
+  local ok, image = call_cache("get", img_hash)
+  if ok and image then
+    return image.url
   end
-  return response
+  return nil
+end
+
+---Look up the URL of an image downloaded from a remote URL or data URI and uploaded before.
+---@param uri string URL or data URI of the image
+---@return string|nil url The `picx.zhimg.com` URL, or nil if the image is not cached
+function M.cached_remote_image_link(uri)
+  local ok, image = call_cache("get_source", uri)
+  if ok and image then
+    return image.url
+  end
+  return nil
+end
+
+---Remember the content hash of an image downloaded from a remote URL or data URI.
+---@param uri string URL or data URI of the image
+---@param img_hash string Content hash of the image uploaded for it
+function M.remember_remote_image(uri, img_hash)
+  call_cache("put_source", uri, img_hash)
+end
+
+---List the images uploaded before, the most recent first.
+---@return zhihu_image.CachedImage[]
+function M.image_cache_entries()
+  local ok, entries = call_cache("entries")
+  return ok and entries or {}
+end
+
+---Forget an uploaded image, or every image if no hash is given, so that it is uploaded again.
+---@param img_hash? string Content hash of the image
+---@return integer count Number of images forgotten
+function M.invalidate_image_cache(img_hash)
+  if img_hash then
+    local ok, removed = call_cache("invalidate", img_hash)
+    return (ok and removed) and 1 or 0
+  end
+  local ok, count = call_cache("clear")
+  return ok and count or 0
+end
+
+---Download a remote image or decode a data URI into the image cache directory.
+---@param uri string `http(s)://` URL or `data:` URI
+---@return string|nil file_path The local copy, nil if Zhihu already serves the image or it cannot be fetched
+function M.fetch_remote_image(uri)
+  local out_dir = vim.fn.stdpath("cache") .. "/zhvim/images"
+  local ok, result = pcall(api.fetch_image, uri, out_dir)
+  if not ok then
+    local source = uri:lower():match("^data:") and "data URI" or uri
+    vim.notify("Failed to download image " .. source .. ": " .. tostring(result), vim.log.levels.WARN)
+    return nil
+  end
+  return result
+end
+
+---Render an SVG or PDF figure to PNG, returning any other image as it is.
+---@param file_path string Absolute path to the image
+---@param opts zhihu_image.RasterizeOpts
+---@return string file_path The image to upload, `file_path` itself if the figure cannot be rendered
+function M.rasterize_image(file_path, opts)
+  local out_dir = vim.fn.stdpath("cache") .. "/zhvim/images"
+  local ok, result = pcall(image_lib.rasterize, file_path, out_dir, opts)
+  if not ok then
+    vim.notify("Failed to rasterize figure " .. file_path .. ": " .. tostring(result), vim.log.levels.ERROR)
+    return file_path
+  end
+  return result
+end
+
+---Prepare a local image for upload, falling back to the image itself on failure.
+---@param file_path string Absolute path to the image
+---@param opts zhihu_image.PreprocessOpts
+---@return string file_path The image to upload
+function M.preprocess_image(file_path, opts)
+  local out_dir = vim.fn.stdpath("cache") .. "/zhvim/images"
+  local ok, result = pcall(image_lib.preprocess, file_path, out_dir, opts)
+  if not ok then
+    vim.notify("Failed to preprocess image " .. file_path .. ": " .. tostring(result), vim.log.levels.WARN)
+    return file_path
+  end
+  return result
+end
+
+---Call a Zhihu API client method, reporting errors instead of raising them.
+---@param cookies string Cookies for authentication
+---@param method string Name of the `zhihu_api.Client` method
+---@param ... any Arguments of the method
+---@return boolean ok
+---@return any result The result of the method, or the error message
+local function call_api(cookies, method, ...)
+  local client = api.client(cookies)
+  return pcall(client[method], client, ...)
 end
 
 ---Function to initialize a draft on Zhihu
@@ -85,36 +174,18 @@ end
 ---@return string|nil
 ---@return string|nil
 function M.init_draft(html_content, cookies)
-  local draft_url = "https://zhuanlan.zhihu.com/api/articles/drafts"
-
-  local draft_body = {
+  local ok, draft_id = call_api(cookies, "create_draft", {
     title = html_content.title,
     content = html_content.content,
+    table_of_contents = html_content.table_of_contents or false,
     delta_time = 0,
-    can_reward = false,
-  }
-
-  local headers = {
-    ["User-Agent"] = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36",
-    ["Content-Type"] = "application/json",
-    ["Cookie"] = cookies,
-    ["x-requested-with"] = "fetch",
-  }
-
-  local response = curl.post(draft_url, {
-    headers = headers,
-    body = vim.fn.json_encode(draft_body),
+    title_image = html_content.title_image,
   })
 
-  local draft_response = nil
-  if response and response.body then
-    draft_response = vim.fn.json_decode(response.body)
-  end
-
-  if draft_response and draft_response.id then
-    return draft_response.id, html_content.content
+  if ok then
+    return draft_id, html_content.content
   else
-    vim.notify("Error generating draft.", vim.log.levels.ERROR)
+    vim.notify("Error generating draft: " .. tostring(draft_id), vim.log.levels.ERROR)
     return nil, nil
   end
 end
@@ -122,34 +193,37 @@ end
 ---Function to update a draft on Zhihu
 ---@param draft_id string ID of the draft to update
 ---@param html_content html_content
----@param cookies number
+---@param cookies string
+---@return boolean success
 function M.update_draft(draft_id, html_content, cookies)
-  local patch_url = string.format("https://zhuanlan.zhihu.com/api/articles/%s/draft", draft_id)
-
-  local patch_body = {
+  local ok, err = call_api(cookies, "update_draft", draft_id, {
     title = html_content.title,
     content = html_content.content,
-    table_of_contents = false,
+    table_of_contents = html_content.table_of_contents or false,
     delta_time = 30,
-    can_reward = false,
-  }
-
-  local headers = {
-    ["User-Agent"] = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/115.0.0.0 Safari/537.36",
-    ["Content-Type"] = "application/json",
-    ["Cookie"] = cookies,
-    ["x-requested-with"] = "fetch",
-  }
-
-  local response = curl.patch(patch_url, {
-    headers = headers,
-    body = vim.fn.json_encode(patch_body),
+    title_image = html_content.title_image,
   })
 
-  if response and response.status and response.status >= 200 and response.status < 300 then
+  if ok then
     vim.notify("Updated draft successfully.", vim.log.levels.INFO)
   else
-    vim.notify("Error updating draft.", vim.log.levels.ERROR)
+    vim.notify("Error updating draft: " .. tostring(err), vim.log.levels.ERROR)
+  end
+  return ok
+end
+
+---Publish a draft on Zhihu.
+---@param draft_id string ID of the draft to publish
+---@param settings zhihu_api.PublishSettings Column, topics, comment permission and original declaration
+---@param cookies string Cookies for authentication
+---@return zhihu_api.Published|nil
+function M.publish_draft(draft_id, settings, cookies)
+  local ok, result = call_api(cookies, "publish_draft", draft_id, settings)
+  if ok then
+    return result
+  else
+    vim.notify("Error publishing draft: " .. tostring(result), vim.log.levels.ERROR)
+    return nil
   end
 end
 
@@ -158,35 +232,14 @@ end
 ---@param cookie string Authentication cookie for Zhihu API
 ---@return upload_response|nil
 function M.get_image_id_from_hash(img_hash, cookie)
-  local url = "https://api.zhihu.com/images"
-
-  local headers = {
-    ["Content-Type"] = "application/json",
-    ["Accept-Language"] = "zh-CN,zh;q=0.8,zh-TW;q=0.7,zh-HK;q=0.5,en-US;q=0.3,en;q=0.2",
-    ["Cookie"] = cookie,
-  }
-  local body = {
-    image_hash = img_hash,
-    source = "article",
-  }
-
-  local response = curl.post(url, {
-    headers = headers,
-    body = vim.fn.json_encode(body),
-  })
-  local result = nil
-  if response and response.body then
-    local parsed_response = vim.fn.json_decode(response.body)
-    result = parsed_response
-    if result then
-      vim.notify("Image ID retrieved successfully.", vim.log.levels.INFO)
-    else
-      vim.notify("Failed to parse response from Zhihu API", vim.log.levels.ERROR)
-    end
+  local ok, result = call_api(cookie, "image_upload", img_hash)
+  if ok then
+    vim.notify("Image ID retrieved successfully.", vim.log.levels.INFO)
+    return result
   else
-    vim.notify("Failed to retrieve image ID.", vim.log.levels.ERROR)
+    vim.notify("Failed to retrieve image ID: " .. tostring(result), vim.log.levels.ERROR)
+    return nil
   end
-  return result
 end
 
 ---Generate a random hash using the system's time and a random number.
@@ -221,74 +274,16 @@ end
 -- local cookie = vim.env.ZHIVIM_COOKIES or vim.g.zhvim_cookies
 -- print(vim.inspect(M.get_image_id_from_hash(hash, cookie)))
 
----Upload an image to Zhihu and return the response
----Since the plenary.curl module does not support binary data upload,
----we use the curl command line tool to upload the image.
+---Upload an image to Zhihu's image bucket.
 ---@param image_path string Absolute path to the image
 ---@param upload_token upload_token Authentication token for Zhihu API
 ---@return boolean|nil response
 function M.upload_image(image_path, upload_token)
-  local mime_type = infer_mime_type(image_path)
-  if not mime_type then
-    vim.notify("Failed to infer MIME type for file: " .. image_path, vim.log.levels.ERROR)
-    return nil
-  end
-  local img_hash = M.read_file_and_hash(image_path)
-  local utc_date = os.date("!%a, %d %b %Y %H:%M:%S GMT")
-  local ua = "aliyun-sdk-js/6.8.0 Firefox 137.0 on OS X 10.15"
-
-  local string_to_sign = string.format(
-    "PUT\n\n%s\n%s\nx-oss-date:%s\nx-oss-security-token:%s\nx-oss-user-agent:%s\n/zhihu-pics/v2-%s",
-    mime_type,
-    utc_date,
-    utc_date,
-    upload_token.access_token,
-    ua,
-    img_hash
-  )
-
-  local signature = calculate_signature(upload_token.access_key, string_to_sign)
-  if not signature then
-    vim.notify("Failed to calculate signature.", vim.log.levels.ERROR)
-    return nil
-  end
-
-  local headers = {
-    "User-Agent: " .. ua,
-    "Accept-Encoding: gzip, deflate, br, zstd",
-    "Content-Type: " .. mime_type,
-    "Accept-Language: zh-CN,zh;q=0.8,zh-TW;q=0.7,zh-HK;q=0.5,en-US;q=0.3,en;q=0.2",
-    "x-oss-date: " .. utc_date,
-    "x-oss-user-agent: " .. ua,
-    "x-oss-security-token: " .. upload_token.access_token,
-    "Authorization: OSS " .. upload_token.access_id .. ":" .. signature,
-  }
-
-  -- Prepare headers for curl command
-  local curl_headers = ""
-  for _, header in ipairs(headers) do
-    curl_headers = curl_headers .. string.format('-H "%s" ', header)
-  end
-
-  local file = assert(io.open(image_path, "rb"))
-  local binary_data = file:read("*all")
-  file:close()
-
-  local curl_command = string.format(
-    [[curl -s -X PUT https://zhihu-pics-upload.zhimg.com/v2-%s \
-  %s--data-binary @-]],
-    img_hash,
-    curl_headers
-  )
-
-  -- Sending the binary data to curl command and capturing the response
-  local handle = assert(io.popen(curl_command, "w"))
-  handle:write(binary_data)
-  local response = handle:close()
-  if response then
-    return response
+  local ok, err = call_api("", "upload_image", image_path, upload_token)
+  if ok then
+    return true
   else
-    vim.notify("Failed to upload image: " .. image_path, vim.log.levels.ERROR)
+    vim.notify("Failed to upload image " .. image_path .. ": " .. tostring(err), vim.log.levels.ERROR)
     return nil
   end
 end
@@ -302,20 +297,20 @@ function M.get_image_link(image_path, upload_token, upload_file)
   local base_dir = vim.fn.fnamemodify(vim.api.nvim_buf_get_name(0), ":h")
   image_path = util.get_absolute_path(image_path, base_dir)
 
-  local img_hash = M.read_file_and_hash(image_path)
-  if not img_hash then
-    vim.notify("Failed to read or hash the file: " .. image_path, vim.log.levels.ERROR)
+  local info = M.read_image_info(image_path)
+  if not info then
     return nil
   end
-  local mime_type = infer_mime_type(image_path)
   local image_status = upload_file.state
-  local url = "https://picx.zhimg.com/v2-" .. img_hash .. "." .. mime_type:match("image/(%w+)")
+  local url = "https://picx.zhimg.com/v2-" .. info.hash .. "." .. info.extension
   if image_status == 1 then
+    call_cache("put", info.hash, url, "existing")
     return url
   elseif image_status == 2 then
     local response = M.upload_image(image_path, upload_token)
     if response then
       vim.notify("Image uploaded successfully.", vim.log.levels.INFO)
+      call_cache("put", info.hash, url, "uploaded")
       return url
     else
       vim.notify("Failed to upload image.", vim.log.levels.ERROR)
@@ -1,149 +1,59 @@
+local lib = require("lib.buf_id")
 local M = {}
 
---- TODO: Support windows which do not have `stat` command
-local id_file = vim.fn.stdpath("data") .. "/zhvim_buf_ids.json"
+local data_dir = vim.fn.stdpath("data")
+local id_file = data_dir .. "/zhvim_drafts.json"
+-- Inode map of older versions, imported into `id_file` on first use
+local legacy_id_file = data_dir .. "/zhvim_buf_ids.json"
 
----Load IDs from the JSON file
----@return table
-local function load_ids()
-  local dir = vim.fn.fnamemodify(id_file, ":h")
-  if not vim.fn.isdirectory(dir) then
-    vim.fn.mkdir(dir, "p")
+---Call a method of the draft ID store, reporting errors.
+---@param method string Method name on `buf_id.Store`
+---@param ... any Arguments of the method
+---@return boolean ok
+---@return any result
+local function call_store(method, ...)
+  local store = lib.store(id_file, legacy_id_file)
+  local ok, result = pcall(store[method], store, ...)
+  if not ok then
+    vim.notify("Failed to access draft IDs in " .. id_file .. ": " .. tostring(result), vim.log.levels.ERROR)
   end
-  if vim.fn.filereadable(id_file) == 0 then
-    vim.api.nvim_echo({ { "ID file not found, creating a new one: " .. id_file, "WarningMsg" } }, true, {})
-    local file = io.open(id_file, "w")
-    if file then
-      file:write("{}")
-      file:close()
-    end
-    return {}
-  end
-  local content = vim.fn.readfile(id_file)
-  if not content or #content == 0 then
-    return {}
-  end
-  return vim.fn.json_decode(table.concat(content, "\n")) or {}
-end
-
----Get the inode of a file as a string
----@param filepath string
----@return string|nil
-local function get_inode(filepath)
-  local handle = io.popen("ls -i " .. filepath)
-  if not handle then
-    vim.notify("Failed to get inode for " .. filepath, vim.log.levels.ERROR)
-    return nil
-  end
-  local result = handle:read("*a")
-  handle:close()
-  return result:match("^(%d+)")
-end
-
----Save IDs to the JSON file
----@param ids table
-local function save_ids(ids)
-  local json_content = vim.fn.json_encode(ids)
-  local file = io.open(id_file, "w")
-  if file then
-    file:write(json_content)
-    file:close()
-  else
-    vim.notify("Failed to save IDs to file: " .. id_file, vim.log.levels.ERROR)
-  end
-end
-
----Assign an ID to a file based on its inode
----@param filepath string
----@param id string
-local function assign_id(filepath, id)
-  local ids = load_ids()
-  local inode = get_inode(filepath)
-  if not inode then
-    vim.notify("Failed to get inode for " .. filepath, vim.log.levels.ERROR)
-    return
-  end
-  ids[inode] = id
-  save_ids(ids)
-end
-
----Update the ID of a file
----@param filepath string
----@param new_id string
-local function update_id(filepath, new_id)
-  local ids = load_ids()
-  local inode = get_inode(filepath)
-  if not inode then
-    vim.notify("Failed to get inode for " .. filepath, vim.log.levels.ERROR)
-    return
-  end
-  if ids[inode] then
-    ids[inode] = new_id
-    save_ids(ids)
-  else
-    vim.notify("No ID found for " .. filepath, vim.log.levels.WARN)
-  end
-end
-
----Remove an ID from a file based on its inode
----@param filepath string
-local function remove_id(filepath)
-  local ids = load_ids()
-  local inode = get_inode(filepath)
-  if not inode then
-    vim.notify("Failed to get inode for " .. filepath, vim.log.levels.ERROR)
-    return
-  end
-  if ids[inode] then
-    ids[inode] = nil
-    save_ids(ids)
-  else
-    vim.notify("No ID found for " .. filepath, vim.log.levels.WARN)
-  end
-end
-
----Get the ID of a file based on its inode
----@param filepath string
----@return string|nil
-function M.get_inode(filepath)
-  local inode = get_inode(filepath)
-  if not inode then
-    vim.notify("Failed to get inode for " .. filepath, vim.log.levels.ERROR)
-    return nil
-  end
-  return inode
+  return ok, result
 end
 
 ---Remove the ID of a file
 ---@param filepath string
 function M.remove_id(filepath)
-  remove_id(filepath)
+  local ok, id = call_store("remove", filepath)
+  if ok and not id then
+    vim.notify("No ID found for " .. filepath, vim.log.levels.WARN)
+  end
 end
 
 ---Check if a file has an assigned ID
 ---@param filepath string
 ---@return string|nil
 function M.check_id(filepath)
-  local ids = load_ids()
-  local inode = get_inode(filepath)
-  return ids[inode] or nil
+  local ok, id = call_store("check", filepath)
+  if not ok then
+    return nil
+  end
+  return id
 end
 
----Assign an ID to a file based on its inode
+---Assign an ID to a file
 ---@param filepath string
 ---@param id string
 function M.assign_id(filepath, id)
-  assign_id(filepath, id)
+  call_store("assign", filepath, id)
 end
 
----Update the ID of a file
+---Update the ID of a file, assigning it if the file has none
 ---@param filepath string
 ---@param new_id string
 function M.update_id(filepath, new_id)
-  if M.check_id(filepath) == nil then
+  local ok, updated = call_store("update", filepath, new_id)
+  if ok and not updated then
     M.assign_id(filepath, new_id)
-  else
-    update_id(filepath, new_id)
   end
 end
 
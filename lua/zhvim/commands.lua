@@ -10,6 +10,9 @@ local util = require("zhvim.util")
 
 ---@param cmd_opts table? Options for the command
 ---@param opts ZhnvimConfigs User configs
+---@return string|nil draft_id ID of the created or updated draft
+---@return html_content|nil html_content The uploaded article
+---@return md_html.ArticleMeta|nil meta Settings from the front matter
 local function init_draft(cmd_opts, opts)
   local cookies = vim.g.zhvim_cookies
   if not cookies or cookies == "" then
@@ -32,6 +35,7 @@ local function init_draft(cmd_opts, opts)
   local extension = util.merge_extension_table(opts)
   local filetypes = util.get_ft_by_patterns(patterns, extension)
   local md_content = { content = "", title = "" }
+  local meta = { topics = {} }
 
   -- TODO: debug mode
   -- debug test
@@ -53,7 +57,7 @@ local function init_draft(cmd_opts, opts)
       path = filepath,
     }
     md_content = script.execute_user_script(opts, filetype, content_input)
-    local content_uploaded = html.update_md_images(md_content.content, cookies)
+    local content_uploaded = html.update_md_images(md_content.content, cookies, opts.images)
     md_content = {
       content = content_uploaded,
       title = md_content.title or vim.fn.expand("%:t:r"),
@@ -61,19 +65,43 @@ local function init_draft(cmd_opts, opts)
   end
 
   if filetype == "markdown" or filetype == "md" then
-    local title, _ = util.get_markdown_title(0)
-    local content = vim.api.nvim_buf_get_lines(0, 1, -1, false)
+    local has_front_matter
+    meta, has_front_matter = html.front_matter(table.concat(buf_content, "\n"))
+    local content = vim.deepcopy(buf_content)
+    local title = meta.title
+    if not title then
+      local title_line
+      title, title_line = util.get_markdown_title(0)
+      if title_line and title_line > 0 then
+        table.remove(content, title_line)
+      elseif not has_front_matter then
+        table.remove(content, 1)
+      end
+    end
     if cmd_opts and cmd_opts.fargs and #cmd_opts.fargs > 0 then
       title = cmd_opts.fargs[1]
     end
     local content_input = table.concat(content, "\n")
-    content_input = html.update_md_images(content_input, cookies)
+    content_input = html.update_md_images(content_input, cookies, opts.images)
     md_content = {
       content = content_input,
       title = title,
     }
   end
+  md_content.links = html.resolve_note_links(md_content.content, vim.fn.fnamemodify(filepath, ":h"))
+  md_content.toc = opts.toc
+  md_content.math = opts.math
+  md_content.images = opts.images
+  if meta.cover then
+    md_content.cover = meta.cover:match("^https?://") and meta.cover
+      or html.upload_image(meta.cover, cookies, opts.images)
+  end
   local file_id = buf_id.check_id(filepath)
+  if meta.id and meta.id ~= file_id then
+    -- The front matter links the note to an article, e.g. one published from zhihu_obsidian.
+    buf_id.update_id(filepath, meta.id)
+    file_id = meta.id
+  end
   if file_id == nil then
     local html_content, error = html.convert_md_to_html(md_content)
     if html_content and error == nil then
@@ -81,6 +109,7 @@ local function init_draft(cmd_opts, opts)
       if draft_id then
         vim.api.nvim_echo({ { "Draft created with ID: " .. draft_id, "Msg" } }, true, {})
         buf_id.assign_id(filepath, draft_id)
+        return draft_id, html_content, meta
       else
         vim.api.nvim_echo({ { "Failed to create draft.", "ErrorMsg" } }, true, {})
       end
@@ -89,13 +118,39 @@ local function init_draft(cmd_opts, opts)
     end
   else
     local html_content, error = html.convert_md_to_html(md_content)
-    if html_content and error == nil then
-      upl.update_draft(file_id, html_content, cookies)
+    if html_content and error == nil and upl.update_draft(file_id, html_content, cookies) then
       vim.api.nvim_echo({ { "Draft updated with ID: " .. file_id, "Msg" } }, true, {})
+      return file_id, html_content, meta
     end
   end
 end
 
+---Upload the current buffer as draft, then publish it.
+---Publish settings are `opts.publish`, overridden by the front matter of the document.
+---@param cmd_opts table? Options for the command
+---@param opts ZhnvimConfigs User configs
+local function publish_article(cmd_opts, opts)
+  local draft_id, html_content, meta = init_draft(cmd_opts, opts)
+  if not draft_id or not html_content then
+    return
+  end
+  local settings = vim.deepcopy(opts.publish or {})
+  meta = meta or {}
+  if meta.topics and #meta.topics > 0 then
+    settings.topics = meta.topics
+  end
+  settings.column = meta.column or settings.column
+  settings.comment_permission = meta.comment_permission or settings.comment_permission
+  if meta.original ~= nil then
+    settings.original = meta.original
+  end
+  settings.table_of_contents = html_content.table_of_contents or false
+  local published = upl.publish_draft(draft_id, settings, vim.g.zhvim_cookies)
+  if published then
+    vim.api.nvim_echo({ { "Article published: " .. published.url, "Msg" } }, true, {})
+  end
+end
+
 --- TODO: modifiable commands to open draft by passing key 'cmd' in config
 local function open_draft()
   local filepath = vim.api.nvim_buf_get_name(0)
@@ -208,8 +263,14 @@ function M.setup_commands(opts, err_browser)
   vim.api.nvim_create_user_command("ZhihuDraft", function(cmd_opts)
     init_draft(cmd_opts, opts)
   end, { nargs = "*", complete = "file" })
+  vim.api.nvim_create_user_command("ZhihuPublish", function(cmd_opts)
+    publish_article(cmd_opts, opts)
+  end, { nargs = "*" })
   vim.api.nvim_create_user_command("ZhihuOpen", open_draft, {})
   vim.api.nvim_create_user_command("ZhihuSync", sync_article, {})
+  vim.api.nvim_create_user_command("ZhihuOutline", function()
+    html.select_heading()
+  end, {})
   vim.api.nvim_create_user_command("ZhihuLink", function(cmd_opts)
     link_id(cmd_opts.fargs[1], cmd_opts.fargs[2])
   end, {
@@ -249,6 +310,11 @@ function M.setup_commands(opts, err_browser)
           if not err_browser.firefox then
             table.insert(available, "firefox")
           end
+          for _, browser in ipairs(require("lib.chrome_cookie").browsers()) do
+            if browser.id ~= "chrome" then
+              table.insert(available, browser.id)
+            end
+          end
           return available
         elseif #args == 3 and (args[2] == "chrome" or args[2] == "firefox") then
           return { "interface", "no-interface" }
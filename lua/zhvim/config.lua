@@ -17,11 +17,19 @@ local util = require("zhvim.util")
 ---@field init_url? string The initial URL to open in the browser for cookie extraction. We recommend using your Zhihu user homepage, for example, `https://www.zhihu.com/people/bu-hui-fei-de-qi-e-71`.
 ---@field port? number The port to use for control browser. By default, it will use 6000 for Firefox and 9222 for Chrome.
 ---@field db_path? string The path to the database file for storing cookies. If not set, it will use the default path for the browser.
+---@field profile? string The profile to read cookies from, by directory (`"Profile 2"`, `"ijkl.default-release"`) or display name. If not set, the last used (Chromium) or default (Firefox) profile logged in to Zhihu is picked.
+---@field container? string|integer The Firefox Multi-Account Container to read cookies from, by name (`"Zhihu"`) or `userContextId`. If not set, cookies outside of containers are preferred, then the first container logged in to Zhihu.
+---@field platform? "macos"|"linux"|"windows" The platform the Chrome cookie database was written on, e.g. for a profile copied from another machine. Defaults to the current one.
+---@field key? string Chrome's "Safe Storage" password, which encrypts its cookies. If not set, it is read from the macOS Keychain, or on Linux from the Secret Service or KDE Wallet, falling back to the fixed password Chrome uses without a keyring.
 
 ---@class ZhnvimConfigs
 ---@field script table<string, ZhnvimConfigs.FiletypesScript> A table of filetype scripts.
----@field browser? { firefox?: ZhnvimConfigs.BrowserScript, chrome?: ZhnvimConfigs.BrowserScript } The browser which has already logged in Zhihu, used to extract cookies from the browser.
----@field default_browser? "chrome"|"firefox" The default browser to use for cookie extraction. If not set, it will try chrome first.
+---@field browser? table<string, ZhnvimConfigs.BrowserScript> The browser which has already logged in Zhihu, used to extract cookies from the browser: `firefox`, `chrome`, or another Chromium-based browser (`chromium`, `brave`, `edge`, `vivaldi`, `opera`), whose `key` and `platform` work like Chrome's.
+---@field default_browser? string The default browser to use for cookie extraction. If not set, it will try chrome first.
+---@field toc? "off"|"block"|"zhihu" Table of contents of uploaded articles: none, a list of heading links at the top, or the one rendered by Zhihu.
+---@field math? "image"|"ztext" Math markup of uploaded articles: legacy `<img eeimg>` equation images or the `ztext-math` spans of Zhihu's newer editor.
+---@field publish? zhihu_api.PublishSettings Defaults of `:ZhihuPublish`, overridden per document.
+---@field images? md_html.ImageOpts Defaults for images of uploaded articles, overridden per image by `![alt](a.png "large nowatermark")` or `![alt](a.png){size=small caption="..."}`.
 
 ---@type ZhnvimConfigs
 local default_config = {
@@ -33,6 +41,16 @@ local default_config = {
     },
   },
   default_browser = "chrome", -- Default browser to use for cookie extraction, can be "firefox" or "chrome"
+  toc = "off",
+  math = "image",
+  images = {
+    size = "normal",
+    watermark = true,
+    caption_from_alt = true,
+    preprocess = { max_width = 1920, jpeg_quality = 85, strip_metadata = true, keep_webp = true, convert_to = "auto" },
+    rasterize = { dpi = 192, background = "#ffffff" },
+  },
+  publish = { topics = {}, comment_permission = "anyone", original = false },
   browser = {
     ---@type ZhnvimConfigs.BrowserScript
     firefox = {
@@ -6,138 +6,147 @@ local decrypt = require("lib.chrome_cookie")
 local utils = require("zhvim.util")
 local M = {}
 
----Get the Firefox cookies.sqlite path for the current user
+---Get the cookies.sqlite path of the default Firefox profile, found through `profiles.ini`
 ---@return string|nil cookies_path Full path to cookies.sqlite or nil if not found
 local function get_firefox_cookies_path()
-  local home = os.getenv("HOME")
-  if not home then
-    vim.notify("Cannot get HOME environment variable", vim.log.levels.ERROR)
+  local ok, path = pcall(decrypt.firefox_cookie_path)
+  if not ok then
+    vim.notify(tostring(path), vim.log.levels.ERROR)
     return nil
   end
+  return path
+end
 
-  local sysname = vim.loop.os_uname().sysname
-  local profile_dir = nil
-
-  if sysname == "Darwin" then
-    -- macOS Firefox profile directory
-    local base = home .. "/Library/Application Support/Firefox/Profiles"
-    local handle = io.popen("find '" .. base .. "' -name '*.default-release' -type d 2>/dev/null")
-    if handle then
-      profile_dir = handle:read("*l")
-      handle:close()
-    end
-  elseif sysname == "Linux" then
-    -- Linux Firefox profile directory
-    local base = home .. "/.mozilla/firefox"
-    local handle = io.popen("find '" .. base .. "' -name '*.default-release' -type d 2>/dev/null")
-    if handle then
-      profile_dir = handle:read("*l")
-      handle:close()
+---Whether the browser is one of the Chromium-based browsers cookies can be read from
+---@param browser string Browser ID, e.g. "brave"
+---@return boolean
+function M.is_chromium(browser)
+  for _, known in ipairs(decrypt.browsers()) do
+    if known.id == browser then
+      return true
     end
-  else
-    vim.notify("Unsupported OS: " .. sysname .. ". Only macOS and Linux are supported.", vim.log.levels.ERROR)
-    return nil
-  end
-
-  if profile_dir then
-    return profile_dir .. "/cookies.sqlite"
-  else
-    vim.notify("Cannot find Firefox cookies.sqlite", vim.log.levels.ERROR)
-    return nil
   end
+  return false
 end
 
----Get the Chrome Cookies file path for the current user
+---Get the Cookies file path of a Chromium-based browser for the current user
+---@param browser string Browser ID, e.g. "chrome" or "brave"
 ---@return string|nil cookies_path Full path to Cookies file or nil if not found
-local function get_chrome_cookies_path()
-  local home = os.getenv("HOME")
-  if not home then
-    vim.notify("Cannot get HOME environment variable", vim.log.levels.ERROR)
+local function get_chromium_cookies_path(browser)
+  local ok, path = pcall(decrypt.cookie_path, browser)
+  if not ok then
+    vim.notify(tostring(path), vim.log.levels.ERROR)
     return nil
   end
+  return path
+end
 
-  local sysname = vim.loop.os_uname().sysname
-
-  if sysname == "Darwin" then
-    -- macOS Chrome Cookies file
-    return home .. "/Library/Application Support/Google/Chrome/Default/Cookies"
-  elseif sysname == "Linux" then
-    -- Linux Chrome Cookies file
-    return home .. "/.config/google-chrome/Default/Cookies"
-  else
-    vim.notify("Unsupported OS: " .. sysname .. ". Only macOS and Linux are supported.", vim.log.levels.ERROR)
+---Find the profile of a Chromium-based browser that is logged in to Zhihu
+---@param browser string Browser ID, e.g. "chrome"
+---@param password string|nil Password of the browser's cookies, nil on Linux without a keyring
+---@param browser_opts ZhnvimConfigs.BrowserScript
+---@return string|nil cookies_path Cookie database of the profile holding `z_c0`, or nil if none does
+local function find_zhihu_profile(browser, password, browser_opts)
+  local ok, profiles = pcall(decrypt.profiles, browser)
+  if not ok then
+    vim.notify(tostring(profiles), vim.log.levels.ERROR)
     return nil
   end
+  local decrypt_opts = { platform = browser_opts.platform }
+  local failure = nil
+  for _, profile in ipairs(profiles) do
+    local wanted = not browser_opts.profile or browser_opts.profile == profile.dir or browser_opts.profile == profile.name
+    if wanted and profile.cookie_path then
+      local found, value =
+        pcall(decrypt.get_cookie_value, profile.cookie_path, password, "www.zhihu.com", "z_c0", decrypt_opts)
+      if found and value then
+        return profile.cookie_path
+      elseif not found then
+        failure = tostring(value)
+      end
+    end
+  end
+  local message = browser_opts.profile
+      and ("Profile " .. browser_opts.profile .. " of " .. browser .. " is not logged in to Zhihu.")
+    or ("No " .. browser .. " profile is logged in to Zhihu.")
+  if failure then
+    message = message .. " " .. failure
+  end
+  vim.notify(message, vim.log.levels.ERROR)
+  return nil
 end
 
 ---Get cookies path for a specified browser
 ---d_c0 and z_c0 are the cookies needed for Zhihu update articles
----@param browser "firefox"|"chrome" Browser name: "firefox" or "chrome"
+---@param browser string Browser name: "firefox", "chrome" or another Chromium-based browser
 ---@return string|nil cookies_path Full path to cookies file or nil if not found
 function M.get_cookies_path(browser)
   if browser == "firefox" then
     return get_firefox_cookies_path()
-  elseif browser == "chrome" then
-    return get_chrome_cookies_path()
+  elseif M.is_chromium(browser) then
+    return get_chromium_cookies_path(browser)
   else
-    vim.notify("Unsupported browser: " .. browser .. ". Only firefox and chrome are supported.", vim.log.levels.ERROR)
+    vim.notify("Unsupported browser: " .. browser .. ".", vim.log.levels.ERROR)
     return nil
   end
 end
 
----Extract Zhihu cookies from Firefox database
----@return table<string, string> cookies Table with d_c0 and z_c0 cookies
-local function get_zhihu_cookies_firefox()
-  local cookies_db = get_firefox_cookies_path()
-  if not cookies_db then
-    return {}
+---Whether a Firefox session is in the container chosen in the configuration
+---@param session chrome_cookie.FirefoxSession
+---@param container? string|integer Container name or `userContextId`, nil for any
+---@return boolean
+local function in_container(session, container)
+  if container == nil then
+    return true
+  end
+  if type(container) == "number" then
+    return session.user_context_id == container
   end
-  local query_cmd = {
-    "sqlite3",
-    cookies_db,
-    "SELECT name, value FROM moz_cookies WHERE host='.zhihu.com';",
-  }
-  local res = vim.system(query_cmd, { text = true }):wait()
+  return session.container == container
+end
 
-  if res.code ~= 0 then
-    if res.stderr and res.stderr:match("database is locked") then
-      vim.notify(
-        "The database is locked. Please try closing your browser and reload this plugin.",
-        vim.log.levels.ERROR
-      )
-      return {}
-    end
-    vim.notify("Failed to execute sqlite3 command: " .. (res.stderr or ""), vim.log.levels.ERROR)
+---Extract Zhihu cookies from the Firefox profile and container logged in to Zhihu
+---@param browser_opts ZhnvimConfigs.BrowserScript
+---@return table<string, string> cookies Table with d_c0 and z_c0 cookies
+local function get_zhihu_cookies_firefox(browser_opts)
+  local ok, profiles = pcall(decrypt.firefox_profiles)
+  if not ok then
+    vim.notify(tostring(profiles), vim.log.levels.ERROR)
     return {}
   end
-
-  local cookies = {}
-  for line in (res.stdout or ""):gmatch("[^\r\n]+") do
-    local name, value = line:match("^(.-)|(.+)$")
-    if name and value then
-      cookies[name] = value
+  for _, profile in ipairs(profiles) do
+    local wanted = not browser_opts.profile
+      or browser_opts.profile == profile.name
+      or browser_opts.profile == vim.fn.fnamemodify(profile.path, ":t")
+    if wanted and profile.cookie_path then
+      local found, sessions = pcall(decrypt.firefox_sessions, profile.cookie_path, "www.zhihu.com")
+      if not found then
+        vim.notify("Failed to read Firefox cookies: " .. tostring(sessions), vim.log.levels.ERROR)
+        return {}
+      end
+      -- The jar outside of containers comes first. Partitioned jars only hold Zhihu embedded in other sites
+      for _, session in ipairs(sessions) do
+        local partitioned = session.partition_key ~= ""
+        local cookies = decrypt.cookie_table(session.cookies)
+        if cookies.z_c0 and not partitioned and in_container(session, browser_opts.container) then
+          return cookies
+        end
+      end
     end
   end
-
-  if vim.tbl_isempty(cookies) then
-    if res.stdout and res.stdout:match("database is locked") then
-      vim.notify(
-        "The database is locked. Please try closing your browser and reload this plugin.",
-        vim.log.levels.ERROR
-      )
-    else
-      vim.notify("Failed to get Zhihu cookies, make sure you are logged in via Firefox", vim.log.levels.ERROR)
-    end
+  local where = browser_opts.profile and ("Firefox profile " .. browser_opts.profile) or "Firefox"
+  if browser_opts.container then
+    where = where .. " (container " .. tostring(browser_opts.container) .. ")"
   end
-
-  return cookies
+  vim.notify("Failed to get Zhihu cookies, make sure you are logged in via " .. where, vim.log.levels.ERROR)
+  return {}
 end
 
 ---Extract Zhihu cookies from Chrome database
 ---@return table<string, string> cookies Table with d_c0 and z_c0 cookies
 
 ---Get Zhihu cookies for a specified browser
----@param browser "firefox"|"chrome" Browser name to extract cookies from
+---@param browser string Browser name to extract cookies from: "firefox", "chrome" or another Chromium-based browser
 ---@param opts ZhnvimConfigs Configuration options
 ---@param interface? "interface"|"no-interface" Whether to use browser interface for cookie extraction
 function M.get_zhihu_cookies(browser, opts, interface)
@@ -152,9 +161,12 @@ function M.get_zhihu_cookies(browser, opts, interface)
     interface_bool = interface_boolen[interface]
   end
 
+  -- Chromium-based browsers other than Chrome need no configuration
+  local browser_opts = opts.browser[browser] or {}
+
   local function update_interface_bool(current_bool)
     if current_bool == nil then
-      return opts.browser[browser].interface
+      return browser_opts.interface
     end
     return current_bool
   end
@@ -162,18 +174,33 @@ function M.get_zhihu_cookies(browser, opts, interface)
   interface_bool = update_interface_bool(interface_bool)
 
   --TODO: MacOS/Linux detection
-  if browser == "chrome" then
-    if interface_bool == false then
-      local host_name = ".zhihu.com"
-      local cookie_path = get_chrome_cookies_path()
-      local password = decrypt.get_chrome_password()
+  if M.is_chromium(browser) then
+    if interface_bool ~= true or browser ~= "chrome" then
+      if interface_bool == true then
+        vim.notify(
+          "Browser interface authorization only supports Chrome, reading the " .. browser .. " cookie database instead.",
+          vim.log.levels.WARN
+        )
+      end
+      local host_name = "www.zhihu.com"
+      local found, password = pcall(decrypt.get_browser_password, browser, browser_opts.key)
+      if not found then
+        -- Only `v10` cookies can be read without the keyring password
+        vim.notify("Failed to look up the " .. browser .. " password: " .. tostring(password), vim.log.levels.WARN)
+        password = nil
+      end
+      local cookie_path = find_zhihu_profile(browser, password, browser_opts)
       if not cookie_path then
-        vim.notify("Could not find Chrome cookies path. Please check your browser installation.", vim.log.levels.ERROR)
         return {}
       end
-      local cookie = decrypt.get_cookies_for_host(cookie_path, password, host_name)
+      local cookie = decrypt.cookie_table(
+        decrypt.get_cookies_for_host(cookie_path, password, host_name, { platform = browser_opts.platform })
+      )
       if not cookie or vim.tbl_isempty(cookie) then
-        vim.notify("Failed to get Zhihu cookies from Chrome. Please make sure you are logged in.", vim.log.levels.ERROR)
+        vim.notify(
+          "Failed to get Zhihu cookies from " .. browser .. ". Please make sure you are logged in.",
+          vim.log.levels.ERROR
+        )
         return {}
       end
       return cookie
@@ -219,9 +246,8 @@ function M.get_zhihu_cookies(browser, opts, interface)
     end
   end
   if browser == "firefox" then
-    -- TODO: Implement Firefox cookie extraction with a similar approach instead of this
     if interface_bool == false then
-      cookies = get_zhihu_cookies_firefox()
+      cookies = get_zhihu_cookies_firefox(browser_opts)
     else
       vim.notify(
         "Browser interface authorization is not implemented yet. Please use `opts.browser.firefox.interface = false` or use `:ZhihuAuth firefox no-interface` to extract cookies directly from Firefox database.",
@@ -234,11 +260,11 @@ function M.get_zhihu_cookies(browser, opts, interface)
 end
 
 ---Load Zhihu cookies into vim.g.zhvim_cookies
----@param browser "firefox"|"chrome" Configuration table containing browser option
+---@param browser string "firefox", "chrome" or another Chromium-based browser
 ---@param opts ZhnvimConfigs Configuration options
 ---@param interface? "interface"|"no-interface" Whether to use browser interface for cookie extraction
 function M.load_cookie(browser, opts, interface)
-  if browser == "chrome" or browser == "firefox" then
+  if browser == "firefox" or M.is_chromium(browser) then
     local cookies = M.get_zhihu_cookies(browser, opts, interface)
     local cookie_str = cookies and require("zhvim.util").table_to_cookie(cookies) or nil
     if cookie_str and cookies.d_c0 ~= "" and cookies.z_c0 ~= "" then
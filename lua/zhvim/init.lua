@@ -2,7 +2,11 @@ local default_config = require("zhvim.config")
 local M = {}
 ---@param opts? ZhnvimConfigs
 M.setup = function(opts)
-  local suscess = require("lib.md_html").initialize() and require("lib.chrome_cookie").initialize()
+  local suscess = require("lib.md_html").initialize()
+    and require("lib.chrome_cookie").initialize()
+    and require("lib.zhihu_image").initialize()
+    and require("lib.zhihu_api").initialize()
+    and require("lib.buf_id").initialize()
   if not suscess then
     return
   else
@@ -1,3 +1,4 @@
+local buf_id = require("zhvim.buf_id")
 local lib = require("lib.md_html")
 local upl = require("zhvim.article_upload")
 local util = require("zhvim.util")
@@ -6,6 +7,11 @@ local M = {}
 ---@class md_content
 ---@field content string Markdown content to be converted to HTML
 ---@field title string Title of the Markdown content
+---@field links? table<string, string> Note key -> Zhihu article ID of the local notes it links to
+---@field toc? "off"|"block"|"zhihu" Table of contents style
+---@field math? "image"|"ztext" Math markup style
+---@field images? md_html.ImageOpts Image defaults
+---@field cover? string Cover image URL
 
 -- Helper function to get the plugin root directory
 local function get_plugin_root()
@@ -17,12 +23,36 @@ local function get_plugin_root()
   return string.gsub(dir, "lua/zhvim/$", "")
 end
 
--- Traverse the syntax tree to find image nodes and collect changes
-local function get_md_image_changes(root, bufnr, cookies)
-  local changes = {}
+---Whether the image is not a local file: an `http(s)://` URL or a `data:` URI.
+---@param uri string
+---@return boolean
+local function is_remote_image(uri)
+  local scheme = uri:lower()
+  return scheme:match("^https?://") ~= nil or scheme:match("^data:") ~= nil
+end
 
-  local function upload_image(uri)
-    local file_path = vim.fn.expand(uri)
+---Upload an image to Zhihu.
+---Remote images and data URIs are downloaded first, so that the article does not hotlink them.
+---@param uri string Image path, relative to the current buffer's directory, URL or data URI
+---@param cookies string Authentication cookies for Zhihu API
+---@param image_opts? md_html.ImageOpts How the image is prepared before upload
+---@return string url The uploaded image URL, or `uri` if the upload failed
+function M.upload_image(uri, cookies, image_opts)
+  local file_path
+  local remote = is_remote_image(uri)
+  if remote then
+    -- Uploaded before, no need to download it again
+    local cached = upl.cached_remote_image_link(uri)
+    if cached then
+      return cached
+    end
+    -- nil for images Zhihu already serves
+    file_path = upl.fetch_remote_image(uri)
+    if not file_path then
+      return uri
+    end
+  else
+    file_path = vim.fn.expand(uri)
     local base_dir = vim.fn.fnamemodify(vim.api.nvim_buf_get_name(0), ":h")
     file_path = util.get_absolute_path(file_path, base_dir)
 
@@ -31,20 +61,39 @@ local function get_md_image_changes(root, bufnr, cookies)
       vim.notify("File does not exist: " .. file_path, vim.log.levels.ERROR)
       return uri
     end
-    local img_hash = upl.read_file_and_hash(file_path)
-    if not img_hash then
-      return uri
-    end
-    local upload_result = upl.get_image_id_from_hash(img_hash, cookies)
+  end
+  image_opts = image_opts or {}
+  -- SVG and PDF figures become PNG images, which are then preprocessed like any other
+  if image_opts.rasterize ~= false then
+    file_path = upl.rasterize_image(file_path, image_opts.rasterize or {})
+  end
+  if image_opts.preprocess then
+    file_path = upl.preprocess_image(file_path, image_opts.preprocess)
+  end
+  local info = upl.read_image_info(file_path)
+  if not info then
+    return uri
+  end
+  local result = upl.cached_image_link(info.hash)
+  if not result then
+    local upload_result = upl.get_image_id_from_hash(info.hash, cookies)
     if not upload_result then
       return uri
     end
-    local result = upl.get_image_link(file_path, upload_result.upload_token, upload_result.upload_file)
+    result = upl.get_image_link(file_path, upload_result.upload_token, upload_result.upload_file)
     if not result then
       return uri
     end
-    return result
   end
+  if remote then
+    upl.remember_remote_image(uri, info.hash)
+  end
+  return result
+end
+
+-- Traverse the syntax tree to find image nodes and collect changes
+local function get_md_image_changes(root, bufnr, cookies, image_opts)
+  local changes = {}
 
   local function process_node(node)
     if node:type() == "image" then
@@ -57,7 +106,7 @@ local function get_md_image_changes(root, bufnr, cookies)
       end
       local url = url_node and vim.treesitter.get_node_text(url_node, bufnr) or nil
       if url then
-        local new_url = upload_image(url)
+        local new_url = M.upload_image(url, cookies, image_opts)
         table.insert(changes, {
           node = url_node,
           new_text = new_url,
@@ -77,8 +126,9 @@ end
 ---Create a scratch buffer to fit the condition that filetype is not markdown.
 ---@param md_content string Markdown content to be processed
 ---@param cookies string Authentication cookies for Zhihu API
+---@param image_opts? md_html.ImageOpts How images are prepared before upload
 ---@return string Updated Markdown content with new image links
-function M.update_md_images(md_content, cookies)
+function M.update_md_images(md_content, cookies, image_opts)
   local bufnr = vim.api.nvim_create_buf(false, true)
   -- Set filetype to markdown to enable Treesitter
   vim.bo[bufnr].filetype = "markdown"
@@ -94,7 +144,7 @@ function M.update_md_images(md_content, cookies)
   local tree = parser:parse()[1]
   local root = tree:root()
 
-  local changes = get_md_image_changes(root, bufnr, cookies)
+  local changes = get_md_image_changes(root, bufnr, cookies, image_opts)
 
   for _, change in ipairs(changes) do
     local start_row, start_col, end_row, end_col = change.node:range()
@@ -107,13 +157,41 @@ end
 
 ---TODO: better replace based on Treesitter node range
 
+---Look up the Zhihu article IDs of the local notes linked from the Markdown content.
+---Notes which have not been uploaded yet are left out and reported as warnings by the converter.
+---@param md_content string Markdown content to be scanned
+---@param base_dir string Directory of the current note, relative links are resolved against it
+---@return table<string, string> links Note key -> Zhihu article ID
+function M.resolve_note_links(md_content, base_dir)
+  local links = {}
+  for _, key in ipairs(lib.note_links(md_content)) do
+    local note_path = util.get_absolute_path(key .. ".md", base_dir)
+    if vim.fn.filereadable(note_path) == 1 then
+      local id = buf_id.check_id(note_path)
+      if id then
+        links[key] = id
+      end
+    end
+  end
+  return links
+end
+
 ---Convert Markdown content to HTML satisfying zhihu structure using a Python script.
 ---@param md_content md_content Markdown content to be converted
 ---@return html_content html_content content or an error message
 ---@return string|nil error
 function M.convert_md_to_html(md_content)
   local title = md_content.title or "Untitled"
-  local content = lib.md_to_html(md_content.content or "")
+  local conversion = lib.convert(md_content.content or "", {
+    links = md_content.links or {},
+    toc = md_content.toc,
+    math = md_content.math,
+    images = md_content.images,
+  })
+  for _, warning in ipairs(conversion.warnings) do
+    vim.notify(warning, vim.log.levels.WARN)
+  end
+  local content = conversion.html
   local result = {
     title = title,
     content = content,
@@ -122,7 +200,43 @@ function M.convert_md_to_html(md_content)
   return {
     title = result.title or "",
     content = result.content or "",
+    table_of_contents = conversion.table_of_contents,
+    title_image = md_content.cover,
   }, nil
 end
 
+---Read the front matter of Markdown content. Its problems are reported when converting.
+---@param content string Markdown content
+---@return md_html.ArticleMeta meta Article settings, empty if there is no front matter
+---@return boolean has_front_matter
+function M.front_matter(content)
+  local meta = lib.front_matter(content)
+  if not meta then
+    return { topics = {} }, false
+  end
+  return meta, true
+end
+
+---Pick a heading of the Markdown buffer and jump to it.
+---@param bufnr? number Buffer number (default: current buffer)
+function M.select_heading(bufnr)
+  bufnr = bufnr or vim.api.nvim_get_current_buf()
+  local content = table.concat(vim.api.nvim_buf_get_lines(bufnr, 0, -1, false), "\n")
+  local headings = lib.outline(content)
+  if #headings == 0 then
+    vim.notify("No heading found in the buffer.", vim.log.levels.WARN)
+    return
+  end
+  vim.ui.select(headings, {
+    prompt = "Headings",
+    format_item = function(heading)
+      return string.rep("  ", heading.level - 1) .. heading.text
+    end,
+  }, function(heading)
+    if heading then
+      vim.api.nvim_win_set_cursor(0, { heading.line, 0 })
+    end
+  end)
+end
+
 return M